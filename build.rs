@@ -25,6 +25,10 @@ fn generate_bindings() {
 fn generate_bindings() {}
 
 fn main() {
+  println!("cargo:rerun-if-changed=lib/nanosvg.c");
+  println!("cargo:rerun-if-changed=lib/nanosvg.h");
+  println!("cargo:rerun-if-changed=lib/nanosvgrast.h");
+
   cc::Build::new()
     .file("lib/nanosvg.c")
     .warnings(false)
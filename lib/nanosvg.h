@@ -73,7 +73,8 @@ enum NSVGpaintType {
   NSVG_PAINT_NONE = 0,
   NSVG_PAINT_COLOR = 1,
   NSVG_PAINT_LINEAR_GRADIENT = 2,
-  NSVG_PAINT_RADIAL_GRADIENT = 3
+  NSVG_PAINT_RADIAL_GRADIENT = 3,
+  NSVG_PAINT_IMAGE = 4
 };
 
 enum NSVGspreadType {
@@ -103,6 +104,54 @@ enum NSVGflags {
   NSVG_FLAGS_VISIBLE = 0x01
 };
 
+enum NSVGalign {
+  NSVG_ALIGN_MIN = 0,
+  NSVG_ALIGN_MID = 1,
+  NSVG_ALIGN_MAX = 2
+};
+
+enum NSVGalignType {
+  NSVG_ALIGN_NONE = 0,    // Scaled to fill the viewport exactly.
+  NSVG_ALIGN_MEET = 1,    // Scaled uniformly to fit inside the viewport.
+  NSVG_ALIGN_SLICE = 2    // Scaled uniformly to cover the whole viewport.
+};
+
+enum NSVGtextAnchor {
+  NSVG_ANCHOR_START = 0,
+  NSVG_ANCHOR_MIDDLE = 1,
+  NSVG_ANCHOR_END = 2
+};
+
+enum NSVGtextFlags {
+  NSVG_TEXT_X = 0x01,   // The run starts at 'x', instead of where the previous one ended.
+  NSVG_TEXT_Y = 0x02    // The run starts at 'y', instead of where the previous one ended.
+};
+
+enum NSVGwarning {
+  NSVG_WARNING_UNCLOSED_TAG = 1,        // Tag or element was never closed.
+  NSVG_WARNING_MISMATCHED_END_TAG = 2,  // End tag without a matching open element.
+  NSVG_WARNING_BAD_NUMBER = 3,          // Attribute value is not a number.
+  NSVG_WARNING_UNKNOWN_UNIT = 4,        // Number has a unit that is not recognised.
+  NSVG_WARNING_UNSUPPORTED_ELEMENT = 5, // Element is not supported and was skipped.
+  NSVG_WARNING_USE_LIMIT = 6,           // <use> instanced too much, it and any further ones were skipped.
+  NSVG_WARNING_CLIP_LIMIT = 7,          // Element is inside too many clip paths, its own clipping was skipped.
+  NSVG_WARNING_LAYER_LIMIT = 8          // Group is inside too many translucent groups, its opacity applies to each shape.
+};
+
+// Called for each problem found while parsing. 'offset' is the byte offset of the '<' of the
+// offending element in the input, 'line' and 'column' its position in lines and UTF-8
+// characters, both starting at 1, 'element' its name and 'value' the offending text, if any.
+typedef void (*NSVGwarningCallback)(void* ud, int warning, int offset, int line, int column, const char* element, const char* value);
+
+typedef struct NSVGparseOptions {
+  NSVGwarningCallback warningCb;  // Called with problems found in the input, or NULL.
+  void* warningUd;          // Passed to warningCb.
+  unsigned int currentColor;    // Color used for 'currentColor' when no 'color' is set, 0x00BBGGRR.
+  int variableCount;        // Number of variables available to var(--name).
+  const char** variableNames;   // Variable names, with or without the leading '--'.
+  const char** variableValues;  // Value substituted for each variable.
+} NSVGparseOptions;
+
 typedef struct NSVGgradientStop {
   unsigned int color;
   float offset;
@@ -116,11 +165,22 @@ typedef struct NSVGgradient {
   NSVGgradientStop stops[1];
 } NSVGgradient;
 
+typedef struct NSVGbitmap {
+  float xform[6];       // Transform from image coordinates to bitmap pixels, once loaded.
+  float placement[6];     // Transform from the user space of the <image> element to image coordinates.
+  float viewport[4];      // Area the bitmap is fitted into [x,y,width,height], in the element's user space.
+  char alignX, alignY, alignType; // preserveAspectRatio of the element.
+  char* href;         // Where the bitmap is, such as a data: URI.
+  int width, height;      // Size of the bitmap in pixels, 0 until loaded.
+  unsigned char* pixels;    // Premultiplied RGBA pixels, NULL until loaded.
+} NSVGbitmap;
+
 typedef struct NSVGpaint {
   char type;
   union {
     unsigned int color;
     NSVGgradient* gradient;
+    NSVGbitmap* bitmap;
   };
 } NSVGpaint;
 
@@ -133,12 +193,25 @@ typedef struct NSVGpath
   struct NSVGpath* next;    // Pointer to next path, or NULL if last element.
 } NSVGpath;
 
+#define NSVG_MAX_CLIP_PATHS 4
+#define NSVG_MAX_LAYERS 8
+
+struct NSVGclipPath;
+struct NSVGmask;
+
+typedef struct NSVGlayer
+{
+  float opacity;        // Opacity the layer is composited with.
+  struct NSVGlayer* parent;   // Layer this one is composited into, or NULL for the image.
+  struct NSVGlayer* next;   // Pointer to next layer, or NULL if last element.
+} NSVGlayer;
+
 typedef struct NSVGshape
 {
   char id[64];        // Optional 'id' attr of the shape or its group
   NSVGpaint fill;       // Fill paint
   NSVGpaint stroke;     // Stroke paint
-  float opacity;        // Opacity of the shape.
+  float opacity;        // Opacity of the shape, not including that of its groups.
   float strokeWidth;      // Stroke width (scaled).
   float strokeDashOffset;   // Stroke dash offset (scaled).
   float strokeDashArray[8];     // Stroke dash array (scaled).
@@ -150,14 +223,53 @@ typedef struct NSVGshape
   unsigned char flags;    // Logical or of NSVG_FLAGS_* flags
   float bounds[4];      // Tight bounding box of the shape [minx,miny,maxx,maxy].
   NSVGpath* paths;      // Linked list of paths in the image.
+  char* groups;       // Space separated ids of the groups containing the shape, outermost first, or NULL.
+  struct NSVGclipPath* clip[NSVG_MAX_CLIP_PATHS]; // Clip paths the shape is drawn inside of all of, unused entries are NULL.
+  struct NSVGmask* mask;    // Luminance mask the shape is drawn through, or NULL.
+  NSVGlayer* layer;     // Innermost translucent group containing the shape, or NULL.
   struct NSVGshape* next;   // Pointer to next shape, or NULL if last element.
 } NSVGshape;
 
+typedef struct NSVGtext
+{
+  float xform[6];       // Transform from the user space of the run to the image (scaled).
+  float x, y;         // Position of the run in user space, where flags has NSVG_TEXT_X or NSVG_TEXT_Y.
+  float dx, dy;       // Shift of the run from its position.
+  float fontSize;       // Font size in user space.
+  char fontFamily[64];    // 'font-family' of the run, as written.
+  char anchor;        // Alignment of the text chunk the run starts or is part of, see NSVGtextAnchor.
+  unsigned char flags;    // Logical or of NSVG_TEXT_* flags.
+  char* content;        // UTF-8 characters of the run, with white space collapsed.
+  NSVGshape* shape;     // Shape drawing the run, without paths until it is outlined.
+  struct NSVGtext* next;    // Pointer to next run, or NULL if last element.
+} NSVGtext;
+
+typedef struct NSVGclipPath
+{
+  char id[64];        // 'id' of the <clipPath> element.
+  NSVGshape* shapes;      // Linked list of shapes whose filled union is the clip region.
+  struct NSVGclipPath* next;  // Pointer to next clip path, or NULL if last element.
+} NSVGclipPath;
+
+typedef struct NSVGmask
+{
+  char id[64];        // 'id' of the <mask> element.
+  NSVGshape* region;      // Rectangle outside of which the mask hides everything.
+  NSVGshape* shapes;      // Linked list of shapes whose luminance times alpha is the mask.
+  struct NSVGmask* next;    // Pointer to next mask, or NULL if last element.
+} NSVGmask;
+
 typedef struct NSVGimage
 {
   float width;        // Width of the image.
   float height;       // Height of the image.
   NSVGshape* shapes;      // Linked list of shapes in the image.
+  NSVGclipPath* clipPaths;  // Linked list of clip paths used by the shapes.
+  NSVGmask* masks;      // Linked list of masks used by the shapes.
+  NSVGlayer* layers;      // Linked list of layers used by the shapes.
+  NSVGtext* texts;      // Linked list of text runs, in document order.
+  float viewBox[4];     // Area of user space fitted into the image [minx,miny,width,height].
+  char alignX, alignY, alignType; // preserveAspectRatio of the image, see NSVGalign and NSVGalignType.
 } NSVGimage;
 
 // Parses SVG file from a file, returns SVG image as paths.
@@ -167,6 +279,29 @@ NSVGimage* nsvgParseFromFile(const char* filename, const char* units, float dpi)
 // Important note: changes the string.
 NSVGimage* nsvgParse(char* input, const char* units, float dpi);
 
+// As nsvgParse, but reports problems in the input to the callback.
+NSVGimage* nsvgParseWithWarnings(char* input, const char* units, float dpi, NSVGwarningCallback cb, void* ud);
+
+// Replaces a fill or stroke with a solid color, freeing any gradient it had.
+//   color - packed as 0xAABBGGRR
+void nsvgSetPaintColor(NSVGpaint* paint, unsigned int color);
+
+// Loads the pixels of an <image>, which isn't drawn until then. Returns 0 if out of memory.
+//   rgba - width*height pixels with non-premultiplied alpha, which are copied
+int nsvgSetBitmapPixels(NSVGbitmap* bitmap, const unsigned char* rgba, int width, int height);
+
+// Loads the pixels another <image> with the same href was loaded with, without copying them.
+void nsvgShareBitmapPixels(NSVGbitmap* bitmap, const NSVGbitmap* source);
+
+// Replaces the paths of the shape of a text run, which has none after parsing. Returns 0 if out of memory.
+//   pts - cubic bezier points of every path in the user space of the run, one after the other
+//   npts - number of points of each path
+//   closed - whether each path is closed
+int nsvgSetTextOutline(NSVGtext* text, const float* pts, const int* npts, const char* closed, int npaths);
+
+// As nsvgParse, with more control over parsing. Options may be NULL.
+NSVGimage* nsvgParseWithOptions(char* input, const char* units, float dpi, const NSVGparseOptions* options);
+
 // Deletes list of paths.
 void nsvgDelete(NSVGimage* image);
 
@@ -185,13 +320,6 @@ void nsvgDelete(NSVGimage* image);
 #define NSVG_PI (3.14159265358979323846264338327f)
 #define NSVG_KAPPA90 (0.5522847493f)  // Length proportional to radius of a cubic bezier handle for 90deg arcs.
 
-#define NSVG_ALIGN_MIN 0
-#define NSVG_ALIGN_MID 1
-#define NSVG_ALIGN_MAX 2
-#define NSVG_ALIGN_NONE 0
-#define NSVG_ALIGN_MEET 1
-#define NSVG_ALIGN_SLICE 2
-
 #define NSVG_NOTUSED(v) do { (void)(1 ? (void)0 : ( (void)(v) ) ); } while(0)
 #define NSVG_RGB(r, g, b) (((unsigned int)r) | ((unsigned int)g << 8) | ((unsigned int)b << 16))
 
@@ -237,8 +365,7 @@ static void nsvg__parseContent(char* s,
                  void (*contentCb)(void* ud, const char* s),
                  void* ud)
 {
-  // Trim start white spaces
-  while (*s && nsvg__isspace(*s)) s++;
+  // White space is kept, it separates the words of text
   if (!*s) return;
 
   if (contentCb)
@@ -274,8 +401,13 @@ static void nsvg__parseElement(char* s,
 
   // Get tag name
   name = s;
-  while (*s && !nsvg__isspace(*s)) s++;
-  if (*s) { *s++ = '\0'; }
+  while (*s && !nsvg__isspace(*s) && *s != '/') s++;
+  if (*s == '/') {
+    *s++ = '\0';
+    end = 1;
+  } else if (*s) {
+    *s++ = '\0';
+  }
 
   // Get attribs
   while (!end && *s && nattr < NSVG_XML_MAX_ATTRIBS-3) {
@@ -321,7 +453,8 @@ static void nsvg__parseElement(char* s,
     (*endelCb)(ud, name);
 }
 
-int nsvg__parseXML(char* input,
+// Returns a pointer to the contents of a tag left unterminated at the end of the input, or NULL.
+char* nsvg__parseXML(char* input,
            void (*startelCb)(void* ud, const char* el, const char** attr),
            void (*endelCb)(void* ud, const char* el),
            void (*contentCb)(void* ud, const char* s),
@@ -330,8 +463,25 @@ int nsvg__parseXML(char* input,
   char* s = input;
   char* mark = s;
   int state = NSVG_XML_CONTENT;
+  char* close;
   while (*s) {
-    if (*s == '<' && state == NSVG_XML_CONTENT) {
+    if (*s == '<' && state == NSVG_XML_CONTENT && strncmp(s, "<!--", 4) == 0) {
+      // Skip comments, which may contain '>'
+      close = strstr(s + 4, "-->");
+      if (close == NULL) return mark;
+      *s = '\0';
+      nsvg__parseContent(mark, contentCb, ud);
+      s = mark = close + 3;
+    } else if (*s == '<' && state == NSVG_XML_CONTENT && strncmp(s, "<![CDATA[", 9) == 0) {
+      // Character data is passed on as content
+      close = strstr(s + 9, "]]>");
+      if (close == NULL) return mark;
+      *s = '\0';
+      nsvg__parseContent(mark, contentCb, ud);
+      *close = '\0';
+      nsvg__parseContent(s + 9, contentCb, ud);
+      s = mark = close + 3;
+    } else if (*s == '<' && state == NSVG_XML_CONTENT) {
       // Start of a tag
       *s++ = '\0';
       nsvg__parseContent(mark, contentCb, ud);
@@ -348,13 +498,17 @@ int nsvg__parseXML(char* input,
     }
   }
 
-  return 1;
+  return state == NSVG_XML_TAG ? mark : NULL;
 }
 
 
 /* Simple SVG parser. */
 
 #define NSVG_MAX_ATTR 128
+#define NSVG_MAX_DEPTH 128
+#define NSVG_MAX_REPLAYED_EVENTS 100000
+#define NSVG_MAX_STYLE_MATCHES 64
+#define NSVG_MAX_RESOLVE_DEPTH 8
 
 enum NSVGgradientUnits {
   NSVG_USER_SPACE = 0,
@@ -410,6 +564,7 @@ typedef struct NSVGattrib
 {
   char id[64];
   float xform[6];
+  unsigned int color;
   unsigned int fillColor;
   unsigned int strokeColor;
   float opacity;
@@ -432,8 +587,98 @@ typedef struct NSVGattrib
   char hasFill;
   char hasStroke;
   char visible;
+  char clipRule;
+  char clipIds[NSVG_MAX_CLIP_PATHS][64];
+  float clipXforms[NSVG_MAX_CLIP_PATHS][6];
+  int clipSerials[NSVG_MAX_CLIP_PATHS];
+  int clipCount;
+  int clipPending;
+  char maskId[64];
+  float maskXform[6];
+  int maskSerial;
+  char maskPending;
+  NSVGlayer* layer;
+  char fontFamily[64];
+  char textAnchor;
 } NSVGattrib;
 
+// A <clipPath> element, with its shapes in its own coordinates.
+typedef struct NSVGclipPathData
+{
+  char id[64];
+  char units;
+  NSVGshape* shapes;
+  NSVGshape* shapesTail;
+  char hasRefs;
+  struct NSVGclipPathData* next;
+} NSVGclipPathData;
+
+// A <mask> element, with its shapes in its own coordinates.
+typedef struct NSVGmaskData
+{
+  char id[64];
+  char units;
+  char contentUnits;
+  NSVGcoordinate x, y, width, height;
+  NSVGshape* shapes;
+  NSVGshape* shapesTail;
+  char hasRefs;
+  struct NSVGmaskData* next;
+} NSVGmaskData;
+
+// A shape's reference to a clip path or mask, in the user space of the referring element.
+typedef struct NSVGclipRef
+{
+  NSVGshape* shape;
+  char mask;
+  char content;   // The shape is part of a clip path or mask, and is placed with each instance of it.
+  char id[64];
+  float xform[6];
+  int serial;
+} NSVGclipRef;
+
+// A clip path placed by a transform, shared by shapes with the same placement.
+typedef struct NSVGclipInstance
+{
+  NSVGclipPath* clip;
+  NSVGclipPathData* def;
+  float xform[6];
+  struct NSVGclipInstance* next;
+} NSVGclipInstance;
+
+// A mask placed by a transform and region, shared by shapes with the same placement.
+typedef struct NSVGmaskInstance
+{
+  NSVGmask* mask;
+  NSVGmaskData* def;
+  float xform[6];
+  float region[4];
+  struct NSVGmaskInstance* next;
+} NSVGmaskInstance;
+
+enum NSVGeventType {
+  NSVG_EVENT_START = 0,
+  NSVG_EVENT_END = 1,
+  NSVG_EVENT_CONTENT = 2
+};
+
+// An XML callback, recorded so parts of the document can be replayed by <use>.
+typedef struct NSVGevent
+{
+  char type;
+  const char* name;   // Element name, or the text of content.
+  const char** attr;    // Attributes of start events.
+  int end;        // Index of the end event matching a start event.
+} NSVGevent;
+
+typedef struct NSVGstyleRule
+{
+  char selector[64];
+  int specificity;
+  char* declarations;
+  struct NSVGstyleRule* next;
+} NSVGstyleRule;
+
 typedef struct NSVGparser
 {
   NSVGattrib attr[NSVG_MAX_ATTR];
@@ -450,6 +695,60 @@ typedef struct NSVGparser
   float dpi;
   char pathFlag;
   char defsFlag;
+  NSVGwarningCallback warningCb;
+  void* warningUd;
+  const char* input;
+  int* lineStarts;
+  int nlines;
+  int elementOffset;
+  char elementName[64];
+  int openOffsets[NSVG_MAX_DEPTH];
+  char openNames[NSVG_MAX_DEPTH][64];
+  int openCount;
+  char groupIds[NSVG_MAX_ATTR][64];
+  int groupCount;
+  int variableCount;
+  const char** variableNames;
+  const char** variableValues;
+  NSVGstyleRule* styleRules;
+  NSVGstyleRule* styleRulesTail;
+  char* styleText;
+  size_t styleLength;
+  char styleFlag;
+  NSVGevent* events;
+  int nevents;
+  int cevents;
+  int openEvents[NSVG_MAX_DEPTH];
+  int openEventCount;
+  int replayDepth;
+  int replayedEvents;
+  float useWidth, useHeight;
+  NSVGbitmap* bitmap;
+  NSVGclipPathData* clipDefs;
+  NSVGclipPathData* clipPath;
+  int clipPathDepth;
+  char clipDefsFlag;
+  NSVGclipRef* clipRefs;
+  int nclipRefs;
+  int cclipRefs;
+  int clipSerial;
+  NSVGclipInstance* clipInstances;
+  NSVGmaskData* maskDefs;
+  NSVGmaskData* maskDef;
+  int maskDepth;
+  char maskDefsFlag;
+  NSVGmaskInstance* maskInstances;
+  const void* resolving[NSVG_MAX_RESOLVE_DEPTH];
+  int resolveDepth;
+  int copiedShapes;
+  NSVGtext* text;
+  NSVGtext* textsTail;
+  int textDepth;
+  float textX, textY, textDx, textDy;
+  unsigned char textFlags;
+  char textSpace;
+  float textPen;
+  int svgDepth;
 } NSVGparser;
 
 static void nsvg__xformIdentity(float* t)
@@ -636,6 +935,12 @@ static NSVGparser* nsvg__createParser()
   p->attr[0].fillRule = NSVG_FILLRULE_NONZERO;
   p->attr[0].hasFill = 1;
   p->attr[0].visible = 1;
+  p->attr[0].fontSize = 16;
+
+  // The viewBox is centered and fitted inside by default
+  p->alignX = NSVG_ALIGN_MID;
+  p->alignY = NSVG_ALIGN_MID;
+  p->alignType = NSVG_ALIGN_MEET;
 
   return p;
 
@@ -658,10 +963,46 @@ static void nsvg__deletePaths(NSVGpath* path)
   }
 }
 
+// Bitmap pixels are shared by every instance of an <image>, and counted by
+// a header just before them.
+static unsigned char* nsvg__allocPixels(size_t size)
+{
+  size_t* refs = (size_t*)malloc(sizeof(size_t) + size);
+  if (refs == NULL) return NULL;
+  *refs = 1;
+  return (unsigned char*)(refs + 1);
+}
+
+static unsigned char* nsvg__retainPixels(unsigned char* pixels)
+{
+  if (pixels != NULL)
+    ((size_t*)pixels)[-1]++;
+  return pixels;
+}
+
+static void nsvg__releasePixels(unsigned char* pixels)
+{
+  size_t* refs;
+  if (pixels == NULL) return;
+  refs = (size_t*)pixels - 1;
+  if (--*refs == 0)
+    free(refs);
+}
+
+static void nsvg__deleteBitmap(NSVGbitmap* bitmap)
+{
+  if (bitmap == NULL) return;
+  free(bitmap->href);
+  nsvg__releasePixels(bitmap->pixels);
+  free(bitmap);
+}
+
 static void nsvg__deletePaint(NSVGpaint* paint)
 {
   if (paint->type == NSVG_PAINT_LINEAR_GRADIENT || paint->type == NSVG_PAINT_RADIAL_GRADIENT)
     free(paint->gradient);
+  else if (paint->type == NSVG_PAINT_IMAGE)
+    nsvg__deleteBitmap(paint->bitmap);
 }
 
 static void nsvg__deleteGradientData(NSVGgradientData* grad)
@@ -675,9 +1016,78 @@ static void nsvg__deleteGradientData(NSVGgradientData* grad)
   }
 }
 
+static void nsvg__deleteShapes(NSVGshape* shape)
+{
+  NSVGshape* snext;
+  while (shape != NULL) {
+    snext = shape->next;
+    nsvg__deletePaths(shape->paths);
+    nsvg__deletePaint(&shape->fill);
+    nsvg__deletePaint(&shape->stroke);
+    free(shape->groups);
+    free(shape);
+    shape = snext;
+  }
+}
+
+static void nsvg__deleteClipPathData(NSVGclipPathData* clip)
+{
+  NSVGclipPathData* next;
+  while (clip != NULL) {
+    next = clip->next;
+    nsvg__deleteShapes(clip->shapes);
+    free(clip);
+    clip = next;
+  }
+}
+
+static void nsvg__deleteMaskData(NSVGmaskData* mask)
+{
+  NSVGmaskData* next;
+  while (mask != NULL) {
+    next = mask->next;
+    nsvg__deleteShapes(mask->shapes);
+    free(mask);
+    mask = next;
+  }
+}
+
+static void nsvg__deleteStyleRules(NSVGstyleRule* rule)
+{
+  NSVGstyleRule* next;
+  while (rule != NULL) {
+    next = rule->next;
+    free(rule->declarations);
+    free(rule);
+    rule = next;
+  }
+}
+
 static void nsvg__deleteParser(NSVGparser* p)
 {
+  NSVGclipInstance* instance;
+  NSVGmaskInstance* maskInstance;
+  int i;
   if (p != NULL) {
+    nsvg__deleteClipPathData(p->clipDefs);
+    nsvg__deleteMaskData(p->maskDefs);
+    free(p->clipRefs);
+    while (p->clipInstances != NULL) {
+      instance = p->clipInstances->next;
+      free(p->clipInstances);
+      p->clipInstances = instance;
+    }
+    while (p->maskInstances != NULL) {
+      maskInstance = p->maskInstances->next;
+      free(p->maskInstances);
+      p->maskInstances = maskInstance;
+    }
+    for (i = 0; i < p->nevents; i++)
+      free((void*)p->events[i].attr);
+    free(p->events);
+    nsvg__deleteStyleRules(p->styleRules);
+    free(p->styleText);
+    free(p->lineStarts);
     nsvg__deletePaths(p->plist);
     nsvg__deleteGradientData(p->gradients);
     nsvgDelete(p->image);
@@ -686,6 +1096,103 @@ static void nsvg__deleteParser(NSVGparser* p)
   }
 }
 
+// Records where each line of the input starts, while it's still intact.
+static void nsvg__indexLines(NSVGparser* p)
+{
+  const char* s;
+  int n = 1;
+
+  for (s = p->input; *s; s++)
+    if (*s == '\n') n++;
+  p->lineStarts = (int*)malloc(sizeof(int) * n);
+  if (p->lineStarts == NULL)
+    return;
+  p->lineStarts[p->nlines++] = 0;
+  for (s = p->input; *s; s++)
+    if (*s == '\n') p->lineStarts[p->nlines++] = (int)(s + 1 - p->input);
+}
+
+static void nsvg__report(NSVGparser* p, int warning, int offset, const char* element, const char* value)
+{
+  int lo = 0, hi = p->nlines - 1, mid, start = 0, column = 1, i;
+
+  if (p->warningCb == NULL)
+    return;
+  while (lo < hi) {
+    mid = (lo + hi + 1) / 2;
+    if (p->lineStarts[mid] <= offset) lo = mid;
+    else hi = mid - 1;
+  }
+  if (p->nlines > 0)
+    start = p->lineStarts[lo];
+  // The tokenizer only overwrites ASCII, so UTF-8 continuation bytes are intact
+  for (i = start; i < offset; i++)
+    if ((p->input[i] & 0xc0) != 0x80) column++;
+  (*p->warningCb)(p->warningUd, warning, offset, lo + 1, column, element, value != NULL ? value : "");
+}
+
+static void nsvg__warn(NSVGparser* p, int warning, int offset, const char* element, const char* value)
+{
+  // Replayed elements were already reported where they were defined
+  if (p->replayDepth == 0)
+    nsvg__report(p, warning, offset, element, value);
+}
+
+// Reports a problem with the element currently being parsed.
+static void nsvg__warnElement(NSVGparser* p, int warning, const char* value)
+{
+  nsvg__warn(p, warning, p->elementOffset, p->elementName, value);
+}
+
+static int nsvg__tagOffset(NSVGparser* p, const char* name)
+{
+  const char* s = name;
+  if (p->input == NULL)
+    return 0;
+  // Skip back over the white space and '/' between the '<' and the name.
+  while (s > p->input && s[-1] != '\0' && (s[-1] == '/' || nsvg__isspace(s[-1]))) s--;
+  // The '<' itself has been replaced by a terminator.
+  if (s > p->input) s--;
+  return (int)(s - p->input);
+}
+
+static void nsvg__openElement(NSVGparser* p, const char* el)
+{
+  if (p->openCount < NSVG_MAX_DEPTH) {
+    p->openOffsets[p->openCount] = p->elementOffset;
+    strncpy(p->openNames[p->openCount], el, 63);
+    p->openNames[p->openCount][63] = '\0';
+  }
+  p->openCount++;
+}
+
+static void nsvg__closeElement(NSVGparser* p, const char* el)
+{
+  int i;
+
+  // Elements nested too deep are not tracked.
+  if (p->openCount > NSVG_MAX_DEPTH) {
+    p->openCount--;
+    return;
+  }
+
+  for (i = p->openCount-1; i >= 0; i--) {
+    if (strncmp(p->openNames[i], el, 63) == 0)
+      break;
+  }
+  if (i < 0) {
+    nsvg__warn(p, NSVG_WARNING_MISMATCHED_END_TAG, nsvg__tagOffset(p, el), el, NULL);
+    return;
+  }
+
+  // Anything opened inside the matching element was left unclosed.
+  while (p->openCount-1 > i) {
+    p->openCount--;
+    nsvg__warn(p, NSVG_WARNING_UNCLOSED_TAG, p->openOffsets[p->openCount], p->openNames[p->openCount], NULL);
+  }
+  p->openCount--;
+}
+
 static void nsvg__resetPath(NSVGparser* p)
 {
   p->npts = 0;
@@ -927,6 +1434,110 @@ static void nsvg__getLocalBounds(float* bounds, NSVGshape *shape, float* xform)
   }
 }
 
+static char* nsvg__groupList(NSVGparser* p)
+{
+  char* groups;
+  size_t len = 0;
+  int i;
+
+  for (i = 0; i < p->groupCount; i++)
+    if (p->groupIds[i][0] != '\0')
+      len += strlen(p->groupIds[i]) + 1;
+  if (len == 0)
+    return NULL;
+
+  groups = (char*)malloc(len);
+  if (groups == NULL)
+    return NULL;
+  groups[0] = '\0';
+  for (i = 0; i < p->groupCount; i++) {
+    if (p->groupIds[i][0] == '\0') continue;
+    if (groups[0] != '\0') strcat(groups, " ");
+    strcat(groups, p->groupIds[i]);
+  }
+  return groups;
+}
+
+static void nsvg__pushGroup(NSVGparser* p, const char** attr)
+{
+  int i;
+  char* id;
+
+  if (p->groupCount >= NSVG_MAX_ATTR)
+    return;
+  id = p->groupIds[p->groupCount++];
+  id[0] = '\0';
+  for (i = 0; attr[i]; i += 2) {
+    if (strcmp(attr[i], "id") == 0) {
+      strncpy(id, attr[i + 1], 63);
+      id[63] = '\0';
+    }
+  }
+}
+
+// Places clip paths and masks added by the current element in its user space,
+// once its transform is known.
+static void nsvg__finishClipPaths(NSVGparser* p)
+{
+  NSVGattrib* attr = nsvg__getAttr(p);
+  int i;
+  for (i = attr->clipCount - attr->clipPending; i < attr->clipCount; i++)
+    memcpy(attr->clipXforms[i], attr->xform, sizeof(float)*6);
+  attr->clipPending = 0;
+  if (attr->maskPending)
+    memcpy(attr->maskXform, attr->xform, sizeof(float)*6);
+  attr->maskPending = 0;
+}
+
+static NSVGclipRef* nsvg__addClipRef(NSVGparser* p)
+{
+  NSVGclipRef* refs;
+  int cclipRefs;
+  if (p->nclipRefs + 1 > p->cclipRefs) {
+    cclipRefs = p->cclipRefs ? p->cclipRefs * 2 : 16;
+    refs = (NSVGclipRef*)realloc(p->clipRefs, sizeof(NSVGclipRef) * cclipRefs);
+    if (refs == NULL) return NULL;
+    p->clipRefs = refs;
+    p->cclipRefs = cclipRefs;
+  }
+  return &p->clipRefs[p->nclipRefs++];
+}
+
+static void nsvg__addClipRefs(NSVGparser* p, NSVGshape* shape)
+{
+  NSVGattrib* attr = nsvg__getAttr(p);
+  NSVGclipRef* ref;
+  char content = p->clipPath != NULL || p->maskDef != NULL;
+  int i;
+
+  nsvg__finishClipPaths(p);
+  if (p->clipPath != NULL && attr->clipCount > 0)
+    p->clipPath->hasRefs = 1;
+  else if (p->maskDef != NULL && (attr->clipCount > 0 || attr->maskId[0] != '\0'))
+    p->maskDef->hasRefs = 1;
+  for (i = 0; i < attr->clipCount; i++) {
+    ref = nsvg__addClipRef(p);
+    if (ref == NULL) return;
+    ref->shape = shape;
+    ref->mask = 0;
+    ref->content = content;
+    memcpy(ref->id, attr->clipIds[i], sizeof ref->id);
+    memcpy(ref->xform, attr->clipXforms[i], sizeof ref->xform);
+    ref->serial = attr->clipSerials[i];
+  }
+  // Masks don't apply to the shapes of a clip path, only their geometry does
+  if (attr->maskId[0] != '\0' && p->clipPath == NULL) {
+    ref = nsvg__addClipRef(p);
+    if (ref == NULL) return;
+    ref->shape = shape;
+    ref->mask = 1;
+    ref->content = content;
+    memcpy(ref->id, attr->maskId, sizeof ref->id);
+    memcpy(ref->xform, attr->maskXform, sizeof ref->xform);
+    ref->serial = attr->maskSerial;
+  }
+}
+
 static void nsvg__addShape(NSVGparser* p)
 {
   NSVGattrib* attr = nsvg__getAttr(p);
@@ -943,6 +1554,7 @@ static void nsvg__addShape(NSVGparser* p)
   memset(shape, 0, sizeof(NSVGshape));
 
   memcpy(shape->id, attr->id, sizeof shape->id);
+  shape->groups = nsvg__groupList(p);
   scale = nsvg__getAverageScale(attr->xform);
   shape->strokeWidth = attr->strokeWidth * scale;
   shape->strokeDashOffset = attr->strokeDashOffset * scale;
@@ -954,6 +1566,7 @@ static void nsvg__addShape(NSVGparser* p)
   shape->miterLimit = attr->miterLimit;
   shape->fillRule = attr->fillRule;
   shape->opacity = attr->opacity;
+  shape->layer = attr->layer;
 
   shape->paths = p->plist;
   p->plist = NULL;
@@ -1003,9 +1616,60 @@ static void nsvg__addShape(NSVGparser* p)
       shape->stroke.type = NSVG_PAINT_NONE;
   }
 
+  if (p->bitmap != NULL) {
+    // Images fill their viewport rectangle
+    nsvg__deletePaint(&shape->fill);
+    nsvg__deletePaint(&shape->stroke);
+    shape->fill.type = NSVG_PAINT_IMAGE;
+    shape->fill.bitmap = p->bitmap;
+    shape->stroke.type = NSVG_PAINT_NONE;
+    shape->fillRule = NSVG_FILLRULE_NONZERO;
+    p->bitmap = NULL;
+  }
+
+  if (p->text != NULL) {
+    // Text is outlined after parsing, its estimated line box only sized the paints
+    nsvg__deletePaths(shape->paths);
+    shape->paths = NULL;
+    p->text->shape = shape;
+    p->text = NULL;
+  }
+
   // Set flags
   shape->flags = (attr->visible ? NSVG_FLAGS_VISIBLE : 0x00);
 
+  if (p->clipPath != NULL) {
+    // Only the geometry of clip path shapes matters
+    nsvg__deletePaint(&shape->fill);
+    nsvg__deletePaint(&shape->stroke);
+    shape->fill.type = NSVG_PAINT_COLOR;
+    shape->fill.color = 0xff000000;
+    shape->stroke.type = NSVG_PAINT_NONE;
+    shape->fillRule = attr->clipRule;
+    shape->layer = NULL;
+    if (p->clipPath->shapes == NULL)
+      p->clipPath->shapes = shape;
+    else
+      p->clipPath->shapesTail->next = shape;
+    p->clipPath->shapesTail = shape;
+    // Clip paths of the shape or the <clipPath> itself intersect with it
+    nsvg__addClipRefs(p, shape);
+    return;
+  }
+  if (p->maskDef != NULL) {
+    // Mask contents are drawn as usual, but group opacity applies to each of
+    // their shapes. Their own clip paths and masks are placed along with them.
+    shape->layer = NULL;
+    if (p->maskDef->shapes == NULL)
+      p->maskDef->shapes = shape;
+    else
+      p->maskDef->shapesTail->next = shape;
+    p->maskDef->shapesTail = shape;
+    nsvg__addClipRefs(p, shape);
+    return;
+  }
+  nsvg__addClipRefs(p, shape);
+
   // Add to tail
   if (p->image->shapes == NULL)
     p->image->shapes = shape;
@@ -1016,7 +1680,10 @@ static void nsvg__addShape(NSVGparser* p)
   return;
 
 error:
-  if (shape) free(shape);
+  if (shape) {
+    free(shape->groups);
+    free(shape);
+  }
 }
 
 static void nsvg__addPath(NSVGparser* p, char closed)
@@ -1401,11 +2068,13 @@ static unsigned int nsvg__parseColorName(const char* str)
   return NSVG_RGB(128, 128, 128);
 }
 
-static unsigned int nsvg__parseColor(const char* str)
+static unsigned int nsvg__parseColor(NSVGparser* p, const char* str)
 {
   size_t len = 0;
   while(*str == ' ') ++str;
   len = strlen(str);
+  if (strncmp(str, "currentColor", 12) == 0 || strncmp(str, "currentcolor", 12) == 0)
+    return nsvg__getAttr(p)->color;
   if (len >= 1 && *str == '#')
     return nsvg__parseColorHex(str);
   else if (len >= 4 && str[0] == 'r' && str[1] == 'g' && str[2] == 'b' && str[3] == '(')
@@ -1413,19 +2082,21 @@ static unsigned int nsvg__parseColor(const char* str)
   return nsvg__parseColorName(str);
 }
 
-static float nsvg__parseOpacity(const char* str)
+static float nsvg__parseOpacity(NSVGparser* p, const char* str)
 {
   float val = 0;
-  sscanf(str, "%f", &val);
+  if (sscanf(str, "%f", &val) < 1)
+    nsvg__warnElement(p, NSVG_WARNING_BAD_NUMBER, str);
   if (val < 0.0f) val = 0.0f;
   if (val > 1.0f) val = 1.0f;
   return val;
 }
 
-static float nsvg__parseMiterLimit(const char* str)
+static float nsvg__parseMiterLimit(NSVGparser* p, const char* str)
 {
   float val = 0;
-  sscanf(str, "%f", &val);
+  if (sscanf(str, "%f", &val) < 1)
+    nsvg__warnElement(p, NSVG_WARNING_BAD_NUMBER, str);
   if (val < 0.0f) val = 0.0f;
   return val;
 }
@@ -1453,12 +2124,15 @@ static int nsvg__parseUnits(const char* units)
   return NSVG_UNITS_USER;
 }
 
-static NSVGcoordinate nsvg__parseCoordinateRaw(const char* str)
+static NSVGcoordinate nsvg__parseCoordinateRaw(NSVGparser* p, const char* str)
 {
   NSVGcoordinate coord = {0, NSVG_UNITS_USER};
   char units[32]="";
-  sscanf(str, "%f%31s", &coord.value, units);
+  if (sscanf(str, "%f%31s", &coord.value, units) < 1)
+    nsvg__warnElement(p, NSVG_WARNING_BAD_NUMBER, str);
   coord.units = nsvg__parseUnits(units);
+  if (units[0] != '\0' && coord.units == NSVG_UNITS_USER)
+    nsvg__warnElement(p, NSVG_WARNING_UNKNOWN_UNIT, units);
   return coord;
 }
 
@@ -1470,7 +2144,7 @@ static NSVGcoordinate nsvg__coord(float v, int units)
 
 static float nsvg__parseCoordinate(NSVGparser* p, const char* str, float orig, float length)
 {
-  NSVGcoordinate coord = nsvg__parseCoordinateRaw(str);
+  NSVGcoordinate coord = nsvg__parseCoordinateRaw(p, str);
   return nsvg__convertToPixels(p, coord, orig, length);
 }
 
@@ -1705,16 +2379,83 @@ static int nsvg__parseStrokeDashArray(NSVGparser* p, const char* str, float* str
   return count;
 }
 
-static void nsvg__parseStyle(NSVGparser* p, const char* str);
+static void nsvg__parseStyle(NSVGparser* p, const char* str, int colorOnly);
+
+static const char* nsvg__findVariable(NSVGparser* p, const char* name, size_t len)
+{
+  const char* candidate;
+  int i;
+
+  if (len >= 2 && name[0] == '-' && name[1] == '-') {
+    name += 2;
+    len -= 2;
+  }
+  for (i = 0; i < p->variableCount; i++) {
+    candidate = p->variableNames[i];
+    if (candidate[0] == '-' && candidate[1] == '-')
+      candidate += 2;
+    if (strlen(candidate) == len && strncmp(candidate, name, len) == 0)
+      return p->variableValues[i];
+  }
+  return NULL;
+}
+
+// Substitutes a value of the form var(--name) or var(--name, fallback), using buf
+// to hold the result. Returns NULL if the variable is unknown and has no fallback.
+static const char* nsvg__resolveVariables(NSVGparser* p, const char* value, char* buf, size_t size)
+{
+  const char *name, *end, *fallback, *found;
+  size_t len;
+  int depth;
+
+  for (depth = 0; depth < 8; depth++) {
+    while (*value && nsvg__isspace(*value)) value++;
+    if (strncmp(value, "var(", 4) != 0)
+      return value;
+
+    name = value + 4;
+    while (*name && nsvg__isspace(*name)) name++;
+    end = name;
+    while (*end && *end != ',' && *end != ')' && !nsvg__isspace(*end)) end++;
+    found = nsvg__findVariable(p, name, (size_t)(end - name));
+
+    if (found == NULL) {
+      fallback = strchr(end, ',');
+      if (fallback == NULL)
+        return NULL;
+      fallback++;
+      // The fallback runs to the closing parenthesis of this var()
+      len = strlen(fallback);
+      while (len > 0 && fallback[len-1] != ')') len--;
+      if (len > 0) len--;
+      while (len > 0 && nsvg__isspace(fallback[len-1])) len--;
+      if (len >= size) len = size - 1;
+      memmove(buf, fallback, len);
+      buf[len] = '\0';
+      value = buf;
+    } else {
+      value = found;
+    }
+  }
+  return value;
+}
 
 static int nsvg__parseAttr(NSVGparser* p, const char* name, const char* value)
 {
   float xform[6];
+  char resolved[512];
   NSVGattrib* attr = nsvg__getAttr(p);
   if (!attr) return 0;
 
+  value = nsvg__resolveVariables(p, value, resolved, sizeof resolved);
+  // An unknown variable leaves the attribute unset
+  if (value == NULL) return 1;
+
   if (strcmp(name, "style") == 0) {
-    nsvg__parseStyle(p, value);
+    nsvg__parseStyle(p, value, 0);
+  } else if (strcmp(name, " style color") == 0) {
+    // Added by nsvg__colorFirst, attribute names can't contain spaces
+    nsvg__parseStyle(p, value, 1);
   } else if (strcmp(name, "display") == 0) {
     if (strcmp(value, "none") == 0)
       attr->visible = 0;
@@ -1728,12 +2469,12 @@ static int nsvg__parseAttr(NSVGparser* p, const char* name, const char* value)
       nsvg__parseUrl(attr->fillGradient, value);
     } else {
       attr->hasFill = 1;
-      attr->fillColor = nsvg__parseColor(value);
+      attr->fillColor = nsvg__parseColor(p, value);
     }
   } else if (strcmp(name, "opacity") == 0) {
-    attr->opacity = nsvg__parseOpacity(value);
+    attr->opacity = nsvg__parseOpacity(p, value);
   } else if (strcmp(name, "fill-opacity") == 0) {
-    attr->fillOpacity = nsvg__parseOpacity(value);
+    attr->fillOpacity = nsvg__parseOpacity(p, value);
   } else if (strcmp(name, "stroke") == 0) {
     if (strcmp(value, "none") == 0) {
       attr->hasStroke = 0;
@@ -1742,7 +2483,7 @@ static int nsvg__parseAttr(NSVGparser* p, const char* name, const char* value)
       nsvg__parseUrl(attr->strokeGradient, value);
     } else {
       attr->hasStroke = 1;
-      attr->strokeColor = nsvg__parseColor(value);
+      attr->strokeColor = nsvg__parseColor(p, value);
     }
   } else if (strcmp(name, "stroke-width") == 0) {
     attr->strokeWidth = nsvg__parseCoordinate(p, value, 0.0f, nsvg__actualLength(p));
@@ -1751,24 +2492,55 @@ static int nsvg__parseAttr(NSVGparser* p, const char* name, const char* value)
   } else if (strcmp(name, "stroke-dashoffset") == 0) {
     attr->strokeDashOffset = nsvg__parseCoordinate(p, value, 0.0f, nsvg__actualLength(p));
   } else if (strcmp(name, "stroke-opacity") == 0) {
-    attr->strokeOpacity = nsvg__parseOpacity(value);
+    attr->strokeOpacity = nsvg__parseOpacity(p, value);
   } else if (strcmp(name, "stroke-linecap") == 0) {
     attr->strokeLineCap = nsvg__parseLineCap(value);
   } else if (strcmp(name, "stroke-linejoin") == 0) {
     attr->strokeLineJoin = nsvg__parseLineJoin(value);
   } else if (strcmp(name, "stroke-miterlimit") == 0) {
-    attr->miterLimit = nsvg__parseMiterLimit(value);
+    attr->miterLimit = nsvg__parseMiterLimit(p, value);
   } else if (strcmp(name, "fill-rule") == 0) {
     attr->fillRule = nsvg__parseFillRule(value);
+  } else if (strcmp(name, "clip-rule") == 0) {
+    attr->clipRule = nsvg__parseFillRule(value);
+  } else if (strcmp(name, "clip-path") == 0) {
+    // Clip paths of the element and its ancestors all apply
+    if (strncmp(value, "url(", 4) == 0 && attr->clipCount < NSVG_MAX_CLIP_PATHS) {
+      nsvg__parseUrl(attr->clipIds[attr->clipCount], value);
+      attr->clipSerials[attr->clipCount] = ++p->clipSerial;
+      attr->clipCount++;
+      attr->clipPending++;
+    }
+  } else if (strcmp(name, "mask") == 0) {
+    // Only the innermost mask applies
+    if (strncmp(value, "url(", 4) == 0) {
+      nsvg__parseUrl(attr->maskId, value);
+      attr->maskSerial = ++p->clipSerial;
+      attr->maskPending = 1;
+    } else {
+      attr->maskId[0] = '\0';
+    }
   } else if (strcmp(name, "font-size") == 0) {
     attr->fontSize = nsvg__parseCoordinate(p, value, 0.0f, nsvg__actualLength(p));
+  } else if (strcmp(name, "font-family") == 0) {
+    strncpy(attr->fontFamily, value, 63);
+    attr->fontFamily[63] = '\0';
+  } else if (strcmp(name, "text-anchor") == 0) {
+    if (strcmp(value, "middle") == 0)
+      attr->textAnchor = NSVG_ANCHOR_MIDDLE;
+    else if (strcmp(value, "end") == 0)
+      attr->textAnchor = NSVG_ANCHOR_END;
+    else
+      attr->textAnchor = NSVG_ANCHOR_START;
   } else if (strcmp(name, "transform") == 0) {
     nsvg__parseTransform(xform, value);
     nsvg__xformPremultiply(attr->xform, xform);
   } else if (strcmp(name, "stop-color") == 0) {
-    attr->stopColor = nsvg__parseColor(value);
+    attr->stopColor = nsvg__parseColor(p, value);
+  } else if (strcmp(name, "color") == 0) {
+    attr->color = nsvg__parseColor(p, value);
   } else if (strcmp(name, "stop-opacity") == 0) {
-    attr->stopOpacity = nsvg__parseOpacity(value);
+    attr->stopOpacity = nsvg__parseOpacity(p, value);
   } else if (strcmp(name, "offset") == 0) {
     attr->stopOffset = nsvg__parseCoordinate(p, value, 0.0f, 1.0f);
   } else if (strcmp(name, "id") == 0) {
@@ -1780,7 +2552,7 @@ static int nsvg__parseAttr(NSVGparser* p, const char* name, const char* value)
   return 1;
 }
 
-static int nsvg__parseNameValue(NSVGparser* p, const char* start, const char* end)
+static int nsvg__parseNameValue(NSVGparser* p, const char* start, const char* end, int colorOnly)
 {
   const char* str;
   const char* val;
@@ -1801,6 +2573,8 @@ static int nsvg__parseNameValue(NSVGparser* p, const char* start, const char* en
   if (n > 511) n = 511;
   if (n) memcpy(name, start, n);
   name[n] = 0;
+  if (colorOnly && strcmp(name, "color") != 0)
+    return 0;
 
   while (val < end && (*val == ':' || nsvg__isspace(*val))) ++val;
 
@@ -1812,7 +2586,8 @@ static int nsvg__parseNameValue(NSVGparser* p, const char* start, const char* en
   return nsvg__parseAttr(p, name, value);
 }
 
-static void nsvg__parseStyle(NSVGparser* p, const char* str)
+// Parses the declarations of a style, or only its 'color' when colorOnly is set.
+static void nsvg__parseStyle(NSVGparser* p, const char* str, int colorOnly)
 {
   const char* start;
   const char* end;
@@ -1828,7 +2603,7 @@ static void nsvg__parseStyle(NSVGparser* p, const char* str)
     while (end > start &&  (*end == ';' || nsvg__isspace(*end))) --end;
     ++end;
 
-    nsvg__parseNameValue(p, start, end);
+    nsvg__parseNameValue(p, start, end, colorOnly);
     if (*str) ++str;
   }
 }
@@ -1839,7 +2614,7 @@ static void nsvg__parseAttribs(NSVGparser* p, const char** attr)
   for (i = 0; attr[i]; i += 2)
   {
     if (strcmp(attr[i], "style") == 0)
-      nsvg__parseStyle(p, attr[i + 1]);
+      nsvg__parseStyle(p, attr[i + 1], 0);
     else
       nsvg__parseAttr(p, attr[i], attr[i + 1]);
   }
@@ -2488,52 +3263,306 @@ static void nsvg__parsePoly(NSVGparser* p, const char** attr, int closeFlag)
   nsvg__addShape(p);
 }
 
-static void nsvg__parseSVG(NSVGparser* p, const char** attr)
+static void nsvg__parseAspectRatio(const char* str, int* alignX, int* alignY, int* alignType)
+{
+  if (strstr(str, "none") != 0) {
+    // No uniform scaling
+    *alignType = NSVG_ALIGN_NONE;
+  } else {
+    // Parse X align
+    if (strstr(str, "xMin") != 0)
+      *alignX = NSVG_ALIGN_MIN;
+    else if (strstr(str, "xMid") != 0)
+      *alignX = NSVG_ALIGN_MID;
+    else if (strstr(str, "xMax") != 0)
+      *alignX = NSVG_ALIGN_MAX;
+    // Parse Y align
+    if (strstr(str, "YMin") != 0)
+      *alignY = NSVG_ALIGN_MIN;
+    else if (strstr(str, "YMid") != 0)
+      *alignY = NSVG_ALIGN_MID;
+    else if (strstr(str, "YMax") != 0)
+      *alignY = NSVG_ALIGN_MAX;
+    // Parse meet/slice
+    *alignType = NSVG_ALIGN_MEET;
+    if (strstr(str, "slice") != 0)
+      *alignType = NSVG_ALIGN_SLICE;
+  }
+}
+
+static void nsvg__parseImage(NSVGparser* p, const char** attr)
 {
+  float x = 0.0f, y = 0.0f, w = 0.0f, h = 0.0f;
+  int alignX = NSVG_ALIGN_MID, alignY = NSVG_ALIGN_MID, alignType = NSVG_ALIGN_MEET;
+  const char* href = NULL;
+  NSVGbitmap* bitmap;
   int i;
+
   for (i = 0; attr[i]; i += 2) {
     if (!nsvg__parseAttr(p, attr[i], attr[i + 1])) {
-      if (strcmp(attr[i], "width") == 0) {
-        p->image->width = nsvg__parseCoordinate(p, attr[i + 1], 0.0f, 1.0f);
-      } else if (strcmp(attr[i], "height") == 0) {
-        p->image->height = nsvg__parseCoordinate(p, attr[i + 1], 0.0f, 1.0f);
-      } else if (strcmp(attr[i], "viewBox") == 0) {
-        sscanf(attr[i + 1], "%f%*[%%, \t]%f%*[%%, \t]%f%*[%%, \t]%f", &p->viewMinx, &p->viewMiny, &p->viewWidth, &p->viewHeight);
-      } else if (strcmp(attr[i], "preserveAspectRatio") == 0) {
-        if (strstr(attr[i + 1], "none") != 0) {
-          // No uniform scaling
-          p->alignType = NSVG_ALIGN_NONE;
-        } else {
-          // Parse X align
-          if (strstr(attr[i + 1], "xMin") != 0)
-            p->alignX = NSVG_ALIGN_MIN;
-          else if (strstr(attr[i + 1], "xMid") != 0)
-            p->alignX = NSVG_ALIGN_MID;
-          else if (strstr(attr[i + 1], "xMax") != 0)
-            p->alignX = NSVG_ALIGN_MAX;
-          // Parse X align
-          if (strstr(attr[i + 1], "yMin") != 0)
-            p->alignY = NSVG_ALIGN_MIN;
-          else if (strstr(attr[i + 1], "yMid") != 0)
-            p->alignY = NSVG_ALIGN_MID;
-          else if (strstr(attr[i + 1], "yMax") != 0)
-            p->alignY = NSVG_ALIGN_MAX;
-          // Parse meet/slice
-          p->alignType = NSVG_ALIGN_MEET;
-          if (strstr(attr[i + 1], "slice") != 0)
-            p->alignType = NSVG_ALIGN_SLICE;
-        }
-      }
+      if (strcmp(attr[i], "x") == 0) x = nsvg__parseCoordinate(p, attr[i+1], nsvg__actualOrigX(p), nsvg__actualWidth(p));
+      if (strcmp(attr[i], "y") == 0) y = nsvg__parseCoordinate(p, attr[i+1], nsvg__actualOrigY(p), nsvg__actualHeight(p));
+      if (strcmp(attr[i], "width") == 0) w = nsvg__parseCoordinate(p, attr[i+1], 0.0f, nsvg__actualWidth(p));
+      if (strcmp(attr[i], "height") == 0) h = nsvg__parseCoordinate(p, attr[i+1], 0.0f, nsvg__actualHeight(p));
+      if (strcmp(attr[i], "preserveAspectRatio") == 0) nsvg__parseAspectRatio(attr[i+1], &alignX, &alignY, &alignType);
+      if (strcmp(attr[i], "href") == 0 || strcmp(attr[i], "xlink:href") == 0) href = attr[i+1];
     }
   }
+
+  // The size of the bitmap isn't known yet, so the viewport has to be given
+  if (w <= 0.0f || h <= 0.0f || href == NULL)
+    return;
+
+  bitmap = (NSVGbitmap*)malloc(sizeof(NSVGbitmap));
+  if (bitmap == NULL) return;
+  memset(bitmap, 0, sizeof(NSVGbitmap));
+  bitmap->href = (char*)malloc(strlen(href) + 1);
+  if (bitmap->href == NULL) {
+    free(bitmap);
+    return;
+  }
+  strcpy(bitmap->href, href);
+  memcpy(bitmap->placement, nsvg__getAttr(p)->xform, sizeof(float)*6);
+  bitmap->viewport[0] = x;
+  bitmap->viewport[1] = y;
+  bitmap->viewport[2] = w;
+  bitmap->viewport[3] = h;
+  bitmap->alignX = (char)alignX;
+  bitmap->alignY = (char)alignY;
+  bitmap->alignType = (char)alignType;
+
+  nsvg__resetPath(p);
+  nsvg__moveTo(p, x, y);
+  nsvg__lineTo(p, x+w, y);
+  nsvg__lineTo(p, x+w, y+h);
+  nsvg__lineTo(p, x, y+h);
+  nsvg__addPath(p, 1);
+
+  p->bitmap = bitmap;
+  nsvg__addShape(p);
+  nsvg__deleteBitmap(p->bitmap);
+  p->bitmap = NULL;
 }
 
-static void nsvg__parseGradient(NSVGparser* p, const char** attr, char type)
+// Only the first of a list of per character positions is used.
+static float nsvg__parseFirstCoordinate(NSVGparser* p, const char* str, float orig, float length)
 {
-  int i;
-  NSVGgradientData* grad = (NSVGgradientData*)malloc(sizeof(NSVGgradientData));
-  if (grad == NULL) return;
-  memset(grad, 0, sizeof(NSVGgradientData));
+  char first[64];
+  int n = 0;
+  while (*str && nsvg__isspace(*str)) str++;
+  while (str[n] && !nsvg__isspace(str[n]) && str[n] != ',' && n < 63) {
+    first[n] = str[n];
+    n++;
+  }
+  first[n] = '\0';
+  return nsvg__parseCoordinate(p, first, orig, length);
+}
+
+static void nsvg__parseText(NSVGparser* p, const char** attr, int start)
+{
+  int i;
+
+  if (start) {
+    // Every text element starts a new chunk, at the origin by default
+    p->textX = p->textY = 0.0f;
+    p->textDx = p->textDy = 0.0f;
+    p->textFlags = NSVG_TEXT_X | NSVG_TEXT_Y;
+    p->textSpace = 1;
+    p->textPen = 0.0f;
+  }
+  p->textDepth++;
+
+  for (i = 0; attr[i]; i += 2) {
+    if (!nsvg__parseAttr(p, attr[i], attr[i + 1])) {
+      if (strcmp(attr[i], "x") == 0) {
+        p->textX = nsvg__parseFirstCoordinate(p, attr[i+1], nsvg__actualOrigX(p), nsvg__actualWidth(p));
+        p->textFlags |= NSVG_TEXT_X;
+      } else if (strcmp(attr[i], "y") == 0) {
+        p->textY = nsvg__parseFirstCoordinate(p, attr[i+1], nsvg__actualOrigY(p), nsvg__actualHeight(p));
+        p->textFlags |= NSVG_TEXT_Y;
+      } else if (strcmp(attr[i], "dx") == 0) {
+        p->textDx += nsvg__parseFirstCoordinate(p, attr[i+1], 0.0f, nsvg__actualWidth(p));
+      } else if (strcmp(attr[i], "dy") == 0) {
+        p->textDy += nsvg__parseFirstCoordinate(p, attr[i+1], 0.0f, nsvg__actualHeight(p));
+      }
+    }
+  }
+}
+
+static int nsvg__putUtf8(char* out, unsigned int c)
+{
+  if (c < 0x80) {
+    out[0] = (char)c;
+    return 1;
+  } else if (c < 0x800) {
+    out[0] = (char)(0xc0 | (c >> 6));
+    out[1] = (char)(0x80 | (c & 0x3f));
+    return 2;
+  } else if (c < 0x10000) {
+    out[0] = (char)(0xe0 | (c >> 12));
+    out[1] = (char)(0x80 | ((c >> 6) & 0x3f));
+    out[2] = (char)(0x80 | (c & 0x3f));
+    return 3;
+  } else if (c < 0x110000) {
+    out[0] = (char)(0xf0 | (c >> 18));
+    out[1] = (char)(0x80 | ((c >> 12) & 0x3f));
+    out[2] = (char)(0x80 | ((c >> 6) & 0x3f));
+    out[3] = (char)(0x80 | (c & 0x3f));
+    return 4;
+  }
+  return 0;
+}
+
+// Decodes the character reference at s into out, returning how much of s it took, or 0.
+static int nsvg__parseEntity(const char* s, char* out, int* len)
+{
+  static const char* names[] = { "amp;", "lt;", "gt;", "quot;", "apos;" };
+  static const char chars[] = { '&', '<', '>', '"', '\'' };
+  const char* digits;
+  const char* end;
+  unsigned long c;
+  int i, hex;
+
+  if (s[1] == '#') {
+    hex = s[2] == 'x' || s[2] == 'X';
+    digits = s + (hex ? 3 : 2);
+    // strtoul would also take white space and a sign before the digits
+    if (!nsvg__isdigit(*digits) && !(hex && ((*digits >= 'a' && *digits <= 'f') || (*digits >= 'A' && *digits <= 'F'))))
+      return 0;
+    c = strtoul(digits, (char**)&end, hex ? 16 : 10);
+    // Leave references to characters which can't be in a document as they are
+    if (*end != ';' || c == 0 || (c >= 0xD800 && c <= 0xDFFF) || c > 0x10FFFF)
+      return 0;
+    *len = nsvg__putUtf8(out, (unsigned int)c);
+    return (int)(end - s) + 1;
+  }
+  for (i = 0; i < 5; i++) {
+    if (strncmp(s + 1, names[i], strlen(names[i])) == 0) {
+      out[0] = chars[i];
+      *len = 1;
+      return (int)strlen(names[i]) + 1;
+    }
+  }
+  return 0;
+}
+
+// Adds character data inside a text element as a run, styled by the innermost element.
+static void nsvg__addText(NSVGparser* p, const char* s)
+{
+  NSVGattrib* attr = nsvg__getAttr(p);
+  NSVGtext* text;
+  char* content;
+  char decoded[4];
+  int n = 0, len, used, chars = 0;
+
+  // Clip paths and masks are resolved while parsing, before text can be outlined
+  if (p->clipPath != NULL || p->maskDef != NULL)
+    return;
+
+  content = (char*)malloc(strlen(s) + 2);
+  if (content == NULL) return;
+  while (*s) {
+    if (nsvg__isspace(*s)) {
+      // White space collapses into a single space before the next character,
+      // so there is none at the start or end of the text
+      if (p->textSpace == 0)
+        p->textSpace = 2;
+      s++;
+      continue;
+    }
+    if (p->textSpace == 2)
+      content[n++] = ' ';
+    used = *s == '&' ? nsvg__parseEntity(s, decoded, &len) : 0;
+    if (used > 0) {
+      memcpy(&content[n], decoded, len);
+      n += len;
+      s += used;
+    } else {
+      content[n++] = *s++;
+    }
+    p->textSpace = 0;
+  }
+  content[n] = '\0';
+  if (n == 0) {
+    free(content);
+    return;
+  }
+
+  text = (NSVGtext*)malloc(sizeof(NSVGtext));
+  if (text == NULL) {
+    free(content);
+    return;
+  }
+  memset(text, 0, sizeof(NSVGtext));
+  memcpy(text->xform, attr->xform, sizeof(float)*6);
+  text->x = p->textX;
+  text->y = p->textY;
+  text->dx = p->textDx;
+  text->dy = p->textDy;
+  text->flags = p->textFlags;
+  text->fontSize = attr->fontSize;
+  memcpy(text->fontFamily, attr->fontFamily, sizeof text->fontFamily);
+  text->anchor = attr->textAnchor;
+  text->content = content;
+  p->textDx = p->textDy = 0.0f;
+  p->textFlags = 0;
+
+  // Estimate the line box, until the run is outlined, for paints in object space
+  for (len = 0; len < n; len++) {
+    if ((content[len] & 0xc0) != 0x80) chars++;
+  }
+  if (text->flags & NSVG_TEXT_X) p->textPen = text->x;
+  p->textPen += text->dx;
+  nsvg__resetPath(p);
+  nsvg__moveTo(p, p->textPen, text->y - text->fontSize);
+  nsvg__lineTo(p, p->textPen + chars * text->fontSize * 0.5f, text->y - text->fontSize);
+  nsvg__lineTo(p, p->textPen + chars * text->fontSize * 0.5f, text->y);
+  nsvg__lineTo(p, p->textPen, text->y);
+  nsvg__addPath(p, 1);
+  p->textPen += chars * text->fontSize * 0.5f;
+
+  p->text = text;
+  nsvg__addShape(p);
+  nsvg__deletePaths(p->plist);
+  p->plist = NULL;
+  if (text->shape == NULL) {
+    p->text = NULL;
+    free(content);
+    free(text);
+    return;
+  }
+
+  if (p->image->texts == NULL)
+    p->image->texts = text;
+  else
+    p->textsTail->next = text;
+  p->textsTail = text;
+}
+
+static void nsvg__parseSVG(NSVGparser* p, const char** attr)
+{
+  int i;
+  for (i = 0; attr[i]; i += 2) {
+    if (!nsvg__parseAttr(p, attr[i], attr[i + 1])) {
+      if (strcmp(attr[i], "width") == 0) {
+        p->image->width = nsvg__parseCoordinate(p, attr[i + 1], 0.0f, 1.0f);
+      } else if (strcmp(attr[i], "height") == 0) {
+        p->image->height = nsvg__parseCoordinate(p, attr[i + 1], 0.0f, 1.0f);
+      } else if (strcmp(attr[i], "viewBox") == 0) {
+        sscanf(attr[i + 1], "%f%*[%%, \t]%f%*[%%, \t]%f%*[%%, \t]%f", &p->viewMinx, &p->viewMiny, &p->viewWidth, &p->viewHeight);
+      } else if (strcmp(attr[i], "preserveAspectRatio") == 0) {
+        nsvg__parseAspectRatio(attr[i + 1], &p->alignX, &p->alignY, &p->alignType);
+      }
+    }
+  }
+}
+
+static void nsvg__parseGradient(NSVGparser* p, const char** attr, char type)
+{
+  int i;
+  NSVGgradientData* grad = (NSVGgradientData*)malloc(sizeof(NSVGgradientData));
+  if (grad == NULL) return;
+  memset(grad, 0, sizeof(NSVGgradientData));
   grad->units = NSVG_OBJECT_SPACE;
   grad->type = type;
   if (grad->type == NSVG_PAINT_LINEAR_GRADIENT) {
@@ -2562,23 +3591,23 @@ static void nsvg__parseGradient(NSVGparser* p, const char** attr, char type)
       } else if (strcmp(attr[i], "gradientTransform") == 0) {
         nsvg__parseTransform(grad->xform, attr[i + 1]);
       } else if (strcmp(attr[i], "cx") == 0) {
-        grad->radial.cx = nsvg__parseCoordinateRaw(attr[i + 1]);
+        grad->radial.cx = nsvg__parseCoordinateRaw(p, attr[i + 1]);
       } else if (strcmp(attr[i], "cy") == 0) {
-        grad->radial.cy = nsvg__parseCoordinateRaw(attr[i + 1]);
+        grad->radial.cy = nsvg__parseCoordinateRaw(p, attr[i + 1]);
       } else if (strcmp(attr[i], "r") == 0) {
-        grad->radial.r = nsvg__parseCoordinateRaw(attr[i + 1]);
+        grad->radial.r = nsvg__parseCoordinateRaw(p, attr[i + 1]);
       } else if (strcmp(attr[i], "fx") == 0) {
-        grad->radial.fx = nsvg__parseCoordinateRaw(attr[i + 1]);
+        grad->radial.fx = nsvg__parseCoordinateRaw(p, attr[i + 1]);
       } else if (strcmp(attr[i], "fy") == 0) {
-        grad->radial.fy = nsvg__parseCoordinateRaw(attr[i + 1]);
+        grad->radial.fy = nsvg__parseCoordinateRaw(p, attr[i + 1]);
       } else if (strcmp(attr[i], "x1") == 0) {
-        grad->linear.x1 = nsvg__parseCoordinateRaw(attr[i + 1]);
+        grad->linear.x1 = nsvg__parseCoordinateRaw(p, attr[i + 1]);
       } else if (strcmp(attr[i], "y1") == 0) {
-        grad->linear.y1 = nsvg__parseCoordinateRaw(attr[i + 1]);
+        grad->linear.y1 = nsvg__parseCoordinateRaw(p, attr[i + 1]);
       } else if (strcmp(attr[i], "x2") == 0) {
-        grad->linear.x2 = nsvg__parseCoordinateRaw(attr[i + 1]);
+        grad->linear.x2 = nsvg__parseCoordinateRaw(p, attr[i + 1]);
       } else if (strcmp(attr[i], "y2") == 0) {
-        grad->linear.y2 = nsvg__parseCoordinateRaw(attr[i + 1]);
+        grad->linear.y2 = nsvg__parseCoordinateRaw(p, attr[i + 1]);
       } else if (strcmp(attr[i], "spreadMethod") == 0) {
         if (strcmp(attr[i+1], "pad") == 0)
           grad->spread = NSVG_SPREAD_PAD;
@@ -2640,9 +3669,552 @@ static void nsvg__parseGradientStop(NSVGparser* p, const char** attr)
   stop->offset = curAttr->stopOffset;
 }
 
+static void nsvg__addStyleRule(NSVGparser* p, const char* selector, size_t selectorLen,
+                 const char* declarations, size_t declarationsLen)
+{
+  NSVGstyleRule* rule;
+  size_t i;
+  int specificity = 0;
+
+  // Trim the selector, and only accept simple selectors like rect, .cls, #id or rect.cls
+  while (selectorLen > 0 && nsvg__isspace(*selector)) { selector++; selectorLen--; }
+  while (selectorLen > 0 && nsvg__isspace(selector[selectorLen-1])) selectorLen--;
+  if (selectorLen == 0 || selectorLen > 63)
+    return;
+  for (i = 0; i < selectorLen; i++) {
+    if (nsvg__isspace(selector[i]) || strchr(">+~[]:()", selector[i]) != NULL)
+      return;
+    if (selector[i] == '#') specificity += 100;
+    else if (selector[i] == '.') specificity += 10;
+  }
+  if (selector[0] != '.' && selector[0] != '#' && selector[0] != '*')
+    specificity += 1;
+
+  rule = (NSVGstyleRule*)malloc(sizeof(NSVGstyleRule));
+  if (rule == NULL) return;
+  memset(rule, 0, sizeof(NSVGstyleRule));
+  rule->declarations = (char*)malloc(declarationsLen + 1);
+  if (rule->declarations == NULL) {
+    free(rule);
+    return;
+  }
+  memcpy(rule->declarations, declarations, declarationsLen);
+  rule->declarations[declarationsLen] = '\0';
+  memcpy(rule->selector, selector, selectorLen);
+  rule->selector[selectorLen] = '\0';
+  rule->specificity = specificity;
+
+  if (p->styleRulesTail != NULL)
+    p->styleRulesTail->next = rule;
+  else
+    p->styleRules = rule;
+  p->styleRulesTail = rule;
+}
+
+static void nsvg__parseStylesheet(NSVGparser* p, char* str)
+{
+  char *s, *d, *selector, *block, *end, *comma;
+  int depth;
+
+  // Strip comments
+  for (s = d = str; *s; ) {
+    if (s[0] == '/' && s[1] == '*') {
+      s = strstr(s + 2, "*/");
+      if (s == NULL) break;
+      s += 2;
+    } else {
+      *d++ = *s++;
+    }
+  }
+  *d = '\0';
+
+  s = str;
+  while (*s) {
+    while (*s && nsvg__isspace(*s)) s++;
+    if (!*s) break;
+
+    if (*s == '@') {
+      // Skip at-rules, along with any block they have
+      while (*s && *s != ';' && *s != '{') s++;
+      if (*s == '{') {
+        for (depth = 0; *s; s++) {
+          if (*s == '{') depth++;
+          if (*s == '}' && --depth == 0) break;
+        }
+      }
+      if (*s) s++;
+      continue;
+    }
+
+    selector = s;
+    block = strchr(s, '{');
+    if (block == NULL) break;
+    end = strchr(block, '}');
+    if (end == NULL) end = block + strlen(block);
+
+    // Grouped selectors share the declarations
+    while (selector < block) {
+      comma = selector;
+      while (comma < block && *comma != ',') comma++;
+      nsvg__addStyleRule(p, selector, (size_t)(comma - selector), block + 1, (size_t)(end - block - 1));
+      selector = comma + 1;
+    }
+
+    s = *end ? end + 1 : end;
+  }
+}
+
+static int nsvg__hasClass(const char* classes, const char* name, size_t len)
+{
+  const char* start;
+  while (classes != NULL && *classes) {
+    while (*classes && nsvg__isspace(*classes)) classes++;
+    start = classes;
+    while (*classes && !nsvg__isspace(*classes)) classes++;
+    if ((size_t)(classes - start) == len && strncmp(start, name, len) == 0)
+      return 1;
+  }
+  return 0;
+}
+
+static int nsvg__matchSelector(const char* selector, const char* el, const char* id, const char* classes)
+{
+  const char* s = selector;
+  const char* start;
+  size_t len;
+
+  // Type, or universal selector
+  start = s;
+  while (*s && *s != '.' && *s != '#') s++;
+  len = (size_t)(s - start);
+  if (len > 0 && !(len == 1 && *start == '*') && (strlen(el) != len || strncmp(el, start, len) != 0))
+    return 0;
+
+  // Classes and ids
+  while (*s) {
+    char kind = *s++;
+    start = s;
+    while (*s && *s != '.' && *s != '#') s++;
+    len = (size_t)(s - start);
+    if (kind == '.' && !nsvg__hasClass(classes, start, len))
+      return 0;
+    if (kind == '#' && (id == NULL || strlen(id) != len || strncmp(id, start, len) != 0))
+      return 0;
+  }
+  return 1;
+}
+
+// Returns the attributes of an element with the declarations of matching style rules
+// added as style attributes, after presentation attributes and before inline styles,
+// so they override each other in that order.
+static const char** nsvg__applyStyleRules(NSVGparser* p, const char* el, const char** attr, const char** out, int size)
+{
+  NSVGstyleRule* matches[NSVG_MAX_STYLE_MATCHES];
+  NSVGstyleRule* rule;
+  const char* id = NULL;
+  const char* classes = NULL;
+  int i, j, nmatches = 0, n = 0;
+
+  if (p->styleRules == NULL)
+    return attr;
+
+  for (i = 0; attr[i]; i += 2) {
+    if (strcmp(attr[i], "id") == 0) id = attr[i + 1];
+    else if (strcmp(attr[i], "class") == 0) classes = attr[i + 1];
+  }
+
+  for (rule = p->styleRules; rule != NULL && nmatches < NSVG_MAX_STYLE_MATCHES; rule = rule->next) {
+    if (!nsvg__matchSelector(rule->selector, el, id, classes))
+      continue;
+    // Keep matches ordered by specificity, then by their order in the stylesheet
+    for (j = nmatches; j > 0 && matches[j-1]->specificity > rule->specificity; j--)
+      matches[j] = matches[j-1];
+    matches[j] = rule;
+    nmatches++;
+  }
+  if (nmatches == 0)
+    return attr;
+
+  for (i = 0; attr[i] && n < size - 2; i += 2) {
+    if (strcmp(attr[i], "style") == 0) continue;
+    out[n++] = attr[i];
+    out[n++] = attr[i + 1];
+  }
+  for (j = 0; j < nmatches && n < size - 2; j++) {
+    out[n++] = "style";
+    out[n++] = matches[j]->declarations;
+  }
+  for (i = 0; attr[i] && n < size - 2; i += 2) {
+    if (strcmp(attr[i], "style") != 0) continue;
+    out[n++] = attr[i];
+    out[n++] = attr[i + 1];
+  }
+  out[n++] = 0;
+  out[n++] = 0;
+  return out;
+}
+
+// Moves 'color' ahead of the other attributes, followed by the color declared
+// by each style, so currentColor doesn't depend on their order.
+static const char** nsvg__colorFirst(const char** attr, const char** out, int size)
+{
+  int i, n = 0, found = 0;
+
+  for (i = 0; attr[i]; i += 2) {
+    if (strcmp(attr[i], "color") == 0 || (strcmp(attr[i], "style") == 0 && strstr(attr[i + 1], "color") != NULL))
+      found = 1;
+  }
+  if (!found)
+    return attr;
+
+  for (i = 0; attr[i] && n < size - 2; i += 2) {
+    if (strcmp(attr[i], "color") != 0) continue;
+    out[n++] = attr[i];
+    out[n++] = attr[i + 1];
+  }
+  for (i = 0; attr[i] && n < size - 2; i += 2) {
+    if (strcmp(attr[i], "style") != 0) continue;
+    out[n++] = " style color";
+    out[n++] = attr[i + 1];
+  }
+  for (i = 0; attr[i] && n < size - 2; i += 2) {
+    if (strcmp(attr[i], "color") == 0) continue;
+    out[n++] = attr[i];
+    out[n++] = attr[i + 1];
+  }
+  out[n++] = 0;
+  out[n++] = 0;
+  return out;
+}
+
+static void nsvg__appendStyleText(NSVGparser* p, const char* s)
+{
+  size_t len = strlen(s);
+  char* text = (char*)realloc(p->styleText, p->styleLength + len + 2);
+  if (text == NULL) return;
+  p->styleText = text;
+  memcpy(p->styleText + p->styleLength, s, len);
+  p->styleLength += len;
+  // Separate chunks, in case they were split by CDATA sections
+  p->styleText[p->styleLength++] = ' ';
+  p->styleText[p->styleLength] = '\0';
+}
+
+static float nsvg__viewAlign(float content, float container, int type);
+
+// Draws the contents of a translucent group into a layer of their own, so that
+// overlapping shapes don't show through each other.
+static void nsvg__pushLayer(NSVGparser* p)
+{
+  NSVGattrib* attr = nsvg__getAttr(p);
+  NSVGlayer* layer;
+  NSVGlayer* l;
+  int depth = 1;
+
+  // Clip path and mask contents only have their shapes' own opacity
+  if (attr->opacity >= 1.0f || p->clipPath != NULL || p->maskDef != NULL)
+    return;
+
+  // The rasterizer applies the opacity of groups nested deeper than its
+  // layers to each of their shapes instead
+  for (l = attr->layer; l != NULL; l = l->parent)
+    depth++;
+  if (depth > NSVG_MAX_LAYERS)
+    nsvg__warnElement(p, NSVG_WARNING_LAYER_LIMIT, NULL);
+
+  layer = (NSVGlayer*)malloc(sizeof(NSVGlayer));
+  if (layer == NULL) return;
+  layer->opacity = attr->opacity;
+  layer->parent = attr->layer;
+  layer->next = p->image->layers;
+  p->image->layers = layer;
+
+  attr->layer = layer;
+  attr->opacity = 1.0f;
+}
+
+static void nsvg__parseUse(NSVGparser* p, const char** attr)
+{
+  float x = 0.0f, y = 0.0f, t[6];
+  int i;
+
+  p->useWidth = -1.0f;
+  p->useHeight = -1.0f;
+  for (i = 0; attr[i]; i += 2) {
+    if (!nsvg__parseAttr(p, attr[i], attr[i + 1])) {
+      if (strcmp(attr[i], "x") == 0) x = nsvg__parseCoordinate(p, attr[i+1], nsvg__actualOrigX(p), nsvg__actualWidth(p));
+      if (strcmp(attr[i], "y") == 0) y = nsvg__parseCoordinate(p, attr[i+1], nsvg__actualOrigY(p), nsvg__actualHeight(p));
+      if (strcmp(attr[i], "width") == 0) p->useWidth = nsvg__parseCoordinate(p, attr[i+1], 0.0f, nsvg__actualWidth(p));
+      if (strcmp(attr[i], "height") == 0) p->useHeight = nsvg__parseCoordinate(p, attr[i+1], 0.0f, nsvg__actualHeight(p));
+    }
+  }
+
+  // x and y apply inside the element's own transform
+  nsvg__xformSetTranslation(t, x, y);
+  nsvg__xformPremultiply(nsvg__getAttr(p)->xform, t);
+}
+
+static void nsvg__parseSymbol(NSVGparser* p, const char** attr)
+{
+  float vx = 0.0f, vy = 0.0f, vw = 0.0f, vh = 0.0f, w, h, sx, sy, t[6];
+  int alignX = NSVG_ALIGN_MID, alignY = NSVG_ALIGN_MID, alignType = NSVG_ALIGN_MEET;
+  float* xform = nsvg__getAttr(p)->xform;
+  int i;
+
+  for (i = 0; attr[i]; i += 2) {
+    if (!nsvg__parseAttr(p, attr[i], attr[i + 1])) {
+      if (strcmp(attr[i], "viewBox") == 0)
+        sscanf(attr[i + 1], "%f%*[%%, \t]%f%*[%%, \t]%f%*[%%, \t]%f", &vx, &vy, &vw, &vh);
+      else if (strcmp(attr[i], "preserveAspectRatio") == 0)
+        nsvg__parseAspectRatio(attr[i + 1], &alignX, &alignY, &alignType);
+    }
+  }
+  if (vw <= 0.0f || vh <= 0.0f)
+    return;
+
+  // Map the viewBox onto the area given by <use>, which defaults to 100%
+  w = p->useWidth >= 0.0f ? p->useWidth : nsvg__actualWidth(p);
+  h = p->useHeight >= 0.0f ? p->useHeight : nsvg__actualHeight(p);
+  sx = w / vw;
+  sy = h / vh;
+  if (alignType == NSVG_ALIGN_MEET)
+    sx = sy = nsvg__minf(sx, sy);
+  else if (alignType == NSVG_ALIGN_SLICE)
+    sx = sy = nsvg__maxf(sx, sy);
+
+  nsvg__xformSetTranslation(t, nsvg__viewAlign(vw*sx, w, alignX), nsvg__viewAlign(vh*sy, h, alignY));
+  nsvg__xformPremultiply(xform, t);
+  nsvg__xformSetScale(t, sx, sy);
+  nsvg__xformPremultiply(xform, t);
+  nsvg__xformSetTranslation(t, -vx, -vy);
+  nsvg__xformPremultiply(xform, t);
+}
+
+static NSVGshape* nsvg__createRectShape(NSVGparser* p, const float* rect, float* xform);
+
+// Places the contents of a nested <svg> in its viewport, which they are clipped to.
+static void nsvg__parseNestedSVG(NSVGparser* p, const char** attr)
+{
+  NSVGattrib* curAttr = nsvg__getAttr(p);
+  NSVGclipPathData* clip;
+  NSVGshape* shape;
+  float x = 0.0f, y = 0.0f, w = nsvg__actualWidth(p), h = nsvg__actualHeight(p);
+  float vx = 0.0f, vy = 0.0f, vw = 0.0f, vh = 0.0f, sx, sy, rect[4], t[6];
+  int alignX = NSVG_ALIGN_MID, alignY = NSVG_ALIGN_MID, alignType = NSVG_ALIGN_MEET;
+  int overflow = 0, i;
+
+  for (i = 0; attr[i]; i += 2) {
+    if (!nsvg__parseAttr(p, attr[i], attr[i + 1])) {
+      if (strcmp(attr[i], "x") == 0) x = nsvg__parseCoordinate(p, attr[i+1], nsvg__actualOrigX(p), nsvg__actualWidth(p));
+      if (strcmp(attr[i], "y") == 0) y = nsvg__parseCoordinate(p, attr[i+1], nsvg__actualOrigY(p), nsvg__actualHeight(p));
+      if (strcmp(attr[i], "width") == 0) w = nsvg__parseCoordinate(p, attr[i+1], 0.0f, nsvg__actualWidth(p));
+      if (strcmp(attr[i], "height") == 0) h = nsvg__parseCoordinate(p, attr[i+1], 0.0f, nsvg__actualHeight(p));
+      if (strcmp(attr[i], "viewBox") == 0)
+        sscanf(attr[i + 1], "%f%*[%%, \t]%f%*[%%, \t]%f%*[%%, \t]%f", &vx, &vy, &vw, &vh);
+      if (strcmp(attr[i], "preserveAspectRatio") == 0) nsvg__parseAspectRatio(attr[i+1], &alignX, &alignY, &alignType);
+      if (strcmp(attr[i], "overflow") == 0) overflow = strcmp(attr[i+1], "visible") == 0 || strcmp(attr[i+1], "auto") == 0;
+    }
+  }
+
+  // An empty viewport shows nothing
+  if (w <= 0.0f || h <= 0.0f) {
+    curAttr->visible = 0;
+    return;
+  }
+
+  // Clip to the viewport in the parent's user space, as a clip path of its own
+  nsvg__finishClipPaths(p);
+  if (!overflow && curAttr->clipCount >= NSVG_MAX_CLIP_PATHS) {
+    nsvg__warnElement(p, NSVG_WARNING_CLIP_LIMIT, NULL);
+  } else if (!overflow) {
+    clip = (NSVGclipPathData*)malloc(sizeof(NSVGclipPathData));
+    if (clip != NULL) {
+      memset(clip, 0, sizeof(NSVGclipPathData));
+      clip->units = NSVG_USER_SPACE;
+      // Element ids can't contain spaces, so this can't be referred to
+      snprintf(clip->id, sizeof clip->id, " viewport %d", ++p->clipSerial);
+      rect[0] = x; rect[1] = y;
+      rect[2] = x + w; rect[3] = y + h;
+      nsvg__xformIdentity(t);
+      shape = nsvg__createRectShape(p, rect, t);
+      clip->shapes = clip->shapesTail = shape;
+      clip->next = p->clipDefs;
+      p->clipDefs = clip;
+
+      memcpy(curAttr->clipIds[curAttr->clipCount], clip->id, sizeof clip->id);
+      memcpy(curAttr->clipXforms[curAttr->clipCount], curAttr->xform, sizeof(float)*6);
+      curAttr->clipSerials[curAttr->clipCount] = p->clipSerial;
+      curAttr->clipCount++;
+    }
+  }
+
+  nsvg__xformSetTranslation(t, x, y);
+  nsvg__xformPremultiply(curAttr->xform, t);
+  if (vw <= 0.0f || vh <= 0.0f)
+    return;
+
+  // Fit the viewBox into the viewport
+  sx = w / vw;
+  sy = h / vh;
+  if (alignType == NSVG_ALIGN_MEET)
+    sx = sy = nsvg__minf(sx, sy);
+  else if (alignType == NSVG_ALIGN_SLICE)
+    sx = sy = nsvg__maxf(sx, sy);
+  nsvg__xformSetTranslation(t, nsvg__viewAlign(vw*sx, w, alignX), nsvg__viewAlign(vh*sy, h, alignY));
+  nsvg__xformPremultiply(curAttr->xform, t);
+  nsvg__xformSetScale(t, sx, sy);
+  nsvg__xformPremultiply(curAttr->xform, t);
+  nsvg__xformSetTranslation(t, -vx, -vy);
+  nsvg__xformPremultiply(curAttr->xform, t);
+}
+
+static void nsvg__parseClipPath(NSVGparser* p, const char** attr)
+{
+  NSVGattrib* curAttr;
+  NSVGclipPathData* clip;
+  int i;
+
+  clip = (NSVGclipPathData*)malloc(sizeof(NSVGclipPathData));
+  if (clip == NULL) return;
+  memset(clip, 0, sizeof(NSVGclipPathData));
+  clip->units = NSVG_USER_SPACE;
+  clip->next = p->clipDefs;
+  p->clipDefs = clip;
+
+  // The contents are in the user space of the referring element, which is
+  // applied when the clip path is resolved.
+  nsvg__pushAttr(p);
+  curAttr = nsvg__getAttr(p);
+  nsvg__xformIdentity(curAttr->xform);
+  curAttr->clipCount = 0;
+  curAttr->clipPending = 0;
+  curAttr->maskId[0] = '\0';
+  for (i = 0; attr[i]; i += 2) {
+    if (strcmp(attr[i], "id") == 0) {
+      strncpy(clip->id, attr[i+1], 63);
+      clip->id[63] = '\0';
+    } else if (strcmp(attr[i], "clipPathUnits") == 0) {
+      if (strcmp(attr[i+1], "objectBoundingBox") == 0)
+        clip->units = NSVG_OBJECT_SPACE;
+    } else {
+      nsvg__parseAttr(p, attr[i], attr[i + 1]);
+    }
+  }
+
+  p->clipPath = clip;
+  p->clipDefsFlag = p->defsFlag;
+  p->defsFlag = 0;
+}
+
+static void nsvg__parseMask(NSVGparser* p, const char** attr)
+{
+  NSVGattrib* curAttr;
+  NSVGmaskData* mask;
+  int i;
+
+  mask = (NSVGmaskData*)malloc(sizeof(NSVGmaskData));
+  if (mask == NULL) return;
+  memset(mask, 0, sizeof(NSVGmaskData));
+  mask->units = NSVG_OBJECT_SPACE;
+  mask->contentUnits = NSVG_USER_SPACE;
+  mask->x = nsvg__coord(-10.0f, NSVG_UNITS_PERCENT);
+  mask->y = nsvg__coord(-10.0f, NSVG_UNITS_PERCENT);
+  mask->width = nsvg__coord(120.0f, NSVG_UNITS_PERCENT);
+  mask->height = nsvg__coord(120.0f, NSVG_UNITS_PERCENT);
+  mask->next = p->maskDefs;
+  p->maskDefs = mask;
+
+  // Like clip paths, the contents are placed when the mask is resolved.
+  nsvg__pushAttr(p);
+  curAttr = nsvg__getAttr(p);
+  nsvg__xformIdentity(curAttr->xform);
+  curAttr->clipCount = 0;
+  curAttr->clipPending = 0;
+  curAttr->maskId[0] = '\0';
+  curAttr->opacity = 1.0f;
+  for (i = 0; attr[i]; i += 2) {
+    if (strcmp(attr[i], "id") == 0) {
+      strncpy(mask->id, attr[i+1], 63);
+      mask->id[63] = '\0';
+    } else if (strcmp(attr[i], "maskUnits") == 0) {
+      if (strcmp(attr[i+1], "userSpaceOnUse") == 0)
+        mask->units = NSVG_USER_SPACE;
+    } else if (strcmp(attr[i], "maskContentUnits") == 0) {
+      if (strcmp(attr[i+1], "objectBoundingBox") == 0)
+        mask->contentUnits = NSVG_OBJECT_SPACE;
+    } else if (strcmp(attr[i], "x") == 0) {
+      mask->x = nsvg__parseCoordinateRaw(p, attr[i+1]);
+    } else if (strcmp(attr[i], "y") == 0) {
+      mask->y = nsvg__parseCoordinateRaw(p, attr[i+1]);
+    } else if (strcmp(attr[i], "width") == 0) {
+      mask->width = nsvg__parseCoordinateRaw(p, attr[i+1]);
+    } else if (strcmp(attr[i], "height") == 0) {
+      mask->height = nsvg__parseCoordinateRaw(p, attr[i+1]);
+    } else if (strcmp(attr[i], "transform") != 0) {
+      // Masks have no transform of their own
+      nsvg__parseAttr(p, attr[i], attr[i + 1]);
+    }
+  }
+
+  p->maskDef = mask;
+  p->maskDefsFlag = p->defsFlag;
+  p->defsFlag = 0;
+}
+
+static int nsvg__isKnownElement(const char* el)
+{
+  static const char* known[] = {
+    "svg", "g", "defs", "path", "rect", "circle", "ellipse", "line", "polyline", "polygon",
+    "linearGradient", "radialGradient", "stop", "style", "use", "symbol", "clipPath", "mask", "image",
+    "text", "tspan",
+    // Descriptive elements which are never rendered.
+    "title", "desc", "metadata"
+  };
+  int i;
+  // Namespaced elements belong to other applications, such as editors.
+  if (strchr(el, ':') != NULL)
+    return 1;
+  for (i = 0; i < (int)(sizeof(known) / sizeof(known[0])); i++) {
+    if (strcmp(el, known[i]) == 0)
+      return 1;
+  }
+  return 0;
+}
+
 static void nsvg__startElement(void* ud, const char* el, const char** attr)
 {
   NSVGparser* p = (NSVGparser*)ud;
+  const char* styled[NSVG_XML_MAX_ATTRIBS * 2];
+  const char* ordered[NSVG_XML_MAX_ATTRIBS * 3];
+
+  p->elementOffset = nsvg__tagOffset(p, el);
+  strncpy(p->elementName, el, 63);
+  p->elementName[63] = '\0';
+  nsvg__openElement(p, el);
+
+  if (!nsvg__isKnownElement(el))
+    nsvg__warnElement(p, NSVG_WARNING_UNSUPPORTED_ELEMENT, NULL);
+
+  if (strcmp(el, "style") == 0) {
+    p->styleFlag = 1;
+    p->styleLength = 0;
+    if (p->styleText != NULL) p->styleText[0] = '\0';
+    return;
+  }
+  attr = nsvg__applyStyleRules(p, el, attr, styled, NSVG_XML_MAX_ATTRIBS * 2);
+  attr = nsvg__colorFirst(attr, ordered, NSVG_XML_MAX_ATTRIBS * 3);
+
+  if (strcmp(el, "clipPath") == 0) {
+    // Clip paths are usually in defs, but their shapes are collected anywhere
+    if (p->clipPathDepth++ == 0)
+      nsvg__parseClipPath(p, attr);
+    return;
+  }
+  if (strcmp(el, "mask") == 0) {
+    if (p->maskDepth++ == 0)
+      nsvg__parseMask(p, attr);
+    return;
+  }
 
   if (p->defsFlag) {
     // Skip everything but gradients in defs
@@ -2659,6 +4231,17 @@ static void nsvg__startElement(void* ud, const char* el, const char** attr)
   if (strcmp(el, "g") == 0) {
     nsvg__pushAttr(p);
     nsvg__parseAttribs(p, attr);
+    nsvg__pushGroup(p, attr);
+    nsvg__pushLayer(p);
+  } else if (strcmp(el, "use") == 0) {
+    nsvg__pushAttr(p);
+    nsvg__parseUse(p, attr);
+    nsvg__pushLayer(p);
+  } else if (strcmp(el, "symbol") == 0) {
+    // Only reached when a symbol is instanced by <use>
+    nsvg__pushAttr(p);
+    nsvg__parseSymbol(p, attr);
+    nsvg__pushLayer(p);
   } else if (strcmp(el, "path") == 0) {
     if (p->pathFlag)  // Do not allow nested paths.
       return;
@@ -2669,6 +4252,13 @@ static void nsvg__startElement(void* ud, const char* el, const char** attr)
     nsvg__pushAttr(p);
     nsvg__parseRect(p, attr);
     nsvg__popAttr(p);
+  } else if (strcmp(el, "image") == 0) {
+    nsvg__pushAttr(p);
+    nsvg__parseImage(p, attr);
+    nsvg__popAttr(p);
+  } else if (strcmp(el, "text") == 0 || (strcmp(el, "tspan") == 0 && p->textDepth > 0)) {
+    nsvg__pushAttr(p);
+    nsvg__parseText(p, attr, strcmp(el, "text") == 0);
   } else if (strcmp(el, "circle") == 0) {
     nsvg__pushAttr(p);
     nsvg__parseCircle(p, attr);
@@ -2698,28 +4288,208 @@ static void nsvg__startElement(void* ud, const char* el, const char** attr)
   } else if (strcmp(el, "defs") == 0) {
     p->defsFlag = 1;
   } else if (strcmp(el, "svg") == 0) {
-    nsvg__parseSVG(p, attr);
+    if (p->svgDepth++ == 0) {
+      nsvg__parseSVG(p, attr);
+    } else {
+      nsvg__pushAttr(p);
+      nsvg__parseNestedSVG(p, attr);
+      nsvg__pushLayer(p);
+    }
+  }
+  nsvg__finishClipPaths(p);
+}
+
+static void nsvg__endElement(void* ud, const char* el)
+{
+  NSVGparser* p = (NSVGparser*)ud;
+
+  nsvg__closeElement(p, el);
+
+  if (strcmp(el, "style") == 0) {
+    if (p->styleFlag && p->styleText != NULL)
+      nsvg__parseStylesheet(p, p->styleText);
+    p->styleFlag = 0;
+    p->styleLength = 0;
+  } else if (strcmp(el, "clipPath") == 0) {
+    if (p->clipPathDepth > 0 && --p->clipPathDepth == 0) {
+      nsvg__popAttr(p);
+      p->clipPath = NULL;
+      p->defsFlag = p->clipDefsFlag;
+    }
+  } else if (strcmp(el, "mask") == 0) {
+    if (p->maskDepth > 0 && --p->maskDepth == 0) {
+      nsvg__popAttr(p);
+      p->maskDef = NULL;
+      p->defsFlag = p->maskDefsFlag;
+    }
+  } else if (strcmp(el, "g") == 0) {
+    nsvg__popAttr(p);
+    if (!p->defsFlag && p->groupCount > 0)
+      p->groupCount--;
+  } else if ((strcmp(el, "use") == 0 || strcmp(el, "symbol") == 0) && !p->defsFlag) {
+    nsvg__popAttr(p);
+  } else if (strcmp(el, "path") == 0) {
+    p->pathFlag = 0;
+  } else if ((strcmp(el, "text") == 0 || strcmp(el, "tspan") == 0) && p->textDepth > 0) {
+    nsvg__popAttr(p);
+    p->textDepth--;
+  } else if (strcmp(el, "defs") == 0) {
+    p->defsFlag = 0;
+  } else if (strcmp(el, "svg") == 0 && !p->defsFlag) {
+    if (p->svgDepth > 0 && --p->svgDepth > 0)
+      nsvg__popAttr(p);
+  }
+}
+
+static void nsvg__content(void* ud, const char* s)
+{
+  NSVGparser* p = (NSVGparser*)ud;
+  if (p->styleFlag)
+    nsvg__appendStyleText(p, s);
+  else if (p->textDepth > 0)
+    nsvg__addText(p, s);
+}
+
+static NSVGevent* nsvg__addEvent(NSVGparser* p, char type, const char* name)
+{
+  NSVGevent* events;
+  NSVGevent* e;
+  int cevents;
+  if (p->nevents + 1 > p->cevents) {
+    cevents = p->cevents ? p->cevents * 2 : 64;
+    events = (NSVGevent*)realloc(p->events, sizeof(NSVGevent) * cevents);
+    if (events == NULL) return NULL;
+    p->events = events;
+    p->cevents = cevents;
   }
+  e = &p->events[p->nevents++];
+  memset(e, 0, sizeof(NSVGevent));
+  e->type = type;
+  e->name = name;
+  e->end = -1;
+  return e;
 }
 
-static void nsvg__endElement(void* ud, const char* el)
+static void nsvg__recordStart(void* ud, const char* el, const char** attr)
 {
   NSVGparser* p = (NSVGparser*)ud;
+  NSVGevent* e;
+  int n = 0;
 
-  if (strcmp(el, "g") == 0) {
-    nsvg__popAttr(p);
-  } else if (strcmp(el, "path") == 0) {
-    p->pathFlag = 0;
-  } else if (strcmp(el, "defs") == 0) {
-    p->defsFlag = 0;
+  while (attr[n]) n += 2;
+  e = nsvg__addEvent(p, NSVG_EVENT_START, el);
+  if (e == NULL) return;
+  // The strings live in the input, only the array needs copying
+  e->attr = (const char**)malloc(sizeof(const char*) * (n + 2));
+  if (e->attr == NULL) {
+    p->nevents--;
+    return;
   }
+  memcpy((void*)e->attr, attr, sizeof(const char*) * (n + 2));
+
+  if (p->openEventCount < NSVG_MAX_DEPTH)
+    p->openEvents[p->openEventCount] = p->nevents - 1;
+  p->openEventCount++;
 }
 
-static void nsvg__content(void* ud, const char* s)
+static void nsvg__recordEnd(void* ud, const char* el)
+{
+  NSVGparser* p = (NSVGparser*)ud;
+  int i, depth = p->openEventCount < NSVG_MAX_DEPTH ? p->openEventCount : NSVG_MAX_DEPTH;
+
+  if (nsvg__addEvent(p, NSVG_EVENT_END, el) == NULL) return;
+
+  // Close the innermost matching element, along with any left open inside it
+  for (i = depth - 1; i >= 0; i--) {
+    if (strcmp(p->events[p->openEvents[i]].name, el) == 0)
+      break;
+  }
+  if (i < 0) return;
+  for (; depth > i; depth--)
+    p->events[p->openEvents[depth - 1]].end = p->nevents - 1;
+  p->openEventCount = i;
+}
+
+static void nsvg__recordContent(void* ud, const char* s)
+{
+  nsvg__addEvent((NSVGparser*)ud, NSVG_EVENT_CONTENT, s);
+}
+
+static int nsvg__findEvent(NSVGparser* p, const char* href)
+{
+  int i, j;
+  if (href == NULL || *href != '#')
+    return -1;
+  href++;
+  for (i = 0; i < p->nevents; i++) {
+    if (p->events[i].type != NSVG_EVENT_START) continue;
+    for (j = 0; p->events[i].attr[j]; j += 2) {
+      if (strcmp(p->events[i].attr[j], "id") == 0 && strcmp(p->events[i].attr[j + 1], href) == 0)
+        return i;
+    }
+  }
+  return -1;
+}
+
+static void nsvg__playEvents(NSVGparser* p, int from, int to);
+
+// Parses a copy of the element <use> refers to, as if it were the only child of the <use>.
+static void nsvg__instanceUse(NSVGparser* p, int use)
 {
-  NSVG_NOTUSED(ud);
-  NSVG_NOTUSED(s);
-  // empty
+  const char* href = NULL;
+  const char** attr = p->events[use].attr;
+  char defsFlag = p->defsFlag;
+  int i, target, end;
+
+  for (i = 0; attr[i]; i += 2) {
+    if (strcmp(attr[i], "href") == 0 || strcmp(attr[i], "xlink:href") == 0)
+      href = attr[i + 1];
+  }
+  target = nsvg__findEvent(p, href);
+  if (target < 0 || p->replayDepth >= 8 || p->replayedEvents > NSVG_MAX_REPLAYED_EVENTS)
+    return;
+  end = p->events[target].end >= 0 ? p->events[target].end : p->nevents - 1;
+  // Don't let an element instance itself
+  if (use >= target && use <= end)
+    return;
+
+  // Nested instances multiply, a small document could otherwise expand without bound
+  p->replayedEvents += end + 1 - target;
+  if (p->replayedEvents > NSVG_MAX_REPLAYED_EVENTS) {
+    // Reported once, even from inside an instance
+    nsvg__report(p, NSVG_WARNING_USE_LIMIT, p->elementOffset, p->elementName, NULL);
+    return;
+  }
+
+  p->defsFlag = 0;
+  p->replayDepth++;
+  nsvg__playEvents(p, target, end + 1);
+  p->replayDepth--;
+  p->defsFlag = defsFlag;
+}
+
+static void nsvg__playEvents(NSVGparser* p, int from, int to)
+{
+  NSVGevent* e;
+  int i;
+
+  for (i = from; i < to; i++) {
+    e = &p->events[i];
+    if (e->type == NSVG_EVENT_START) {
+      // Symbols are only drawn when instanced
+      if (strcmp(e->name, "symbol") == 0 && !(p->replayDepth > 0 && i == from)) {
+        i = e->end >= 0 ? e->end : to;
+        continue;
+      }
+      nsvg__startElement(p, e->name, e->attr);
+      if (strcmp(e->name, "use") == 0 && !p->defsFlag)
+        nsvg__instanceUse(p, i);
+    } else if (e->type == NSVG_EVENT_END) {
+      nsvg__endElement(p, e->name);
+    } else {
+      nsvg__content(p, e->name);
+    }
+  }
 }
 
 static void nsvg__imageBounds(NSVGparser* p, float* bounds)
@@ -2762,13 +4532,59 @@ static void nsvg__scaleGradient(NSVGgradient* grad, float tx, float ty, float sx
   nsvg__xformMultiply (grad->xform, t);
 }
 
-static void nsvg__scaleToViewbox(NSVGparser* p, const char* units)
+static void nsvg__scaleShape(NSVGshape* shape, float tx, float ty, float sx, float sy)
 {
-  NSVGshape* shape;
   NSVGpath* path;
-  float tx, ty, sx, sy, us, bounds[4], t[6], avgs;
-  int i;
+  float t[6], avgs = (sx+sy) / 2.0f;
   float* pt;
+  int i;
+
+  shape->bounds[0] = (shape->bounds[0] + tx) * sx;
+  shape->bounds[1] = (shape->bounds[1] + ty) * sy;
+  shape->bounds[2] = (shape->bounds[2] + tx) * sx;
+  shape->bounds[3] = (shape->bounds[3] + ty) * sy;
+  for (path = shape->paths; path != NULL; path = path->next) {
+    path->bounds[0] = (path->bounds[0] + tx) * sx;
+    path->bounds[1] = (path->bounds[1] + ty) * sy;
+    path->bounds[2] = (path->bounds[2] + tx) * sx;
+    path->bounds[3] = (path->bounds[3] + ty) * sy;
+    for (i =0; i < path->npts; i++) {
+      pt = &path->pts[i*2];
+      pt[0] = (pt[0] + tx) * sx;
+      pt[1] = (pt[1] + ty) * sy;
+    }
+  }
+
+  if (shape->fill.type == NSVG_PAINT_LINEAR_GRADIENT || shape->fill.type == NSVG_PAINT_RADIAL_GRADIENT) {
+    nsvg__scaleGradient(shape->fill.gradient, tx,ty, sx,sy);
+    memcpy(t, shape->fill.gradient->xform, sizeof(float)*6);
+    nsvg__xformInverse(shape->fill.gradient->xform, t);
+  }
+  if (shape->stroke.type == NSVG_PAINT_LINEAR_GRADIENT || shape->stroke.type == NSVG_PAINT_RADIAL_GRADIENT) {
+    nsvg__scaleGradient(shape->stroke.gradient, tx,ty, sx,sy);
+    memcpy(t, shape->stroke.gradient->xform, sizeof(float)*6);
+    nsvg__xformInverse(shape->stroke.gradient->xform, t);
+  }
+  if (shape->fill.type == NSVG_PAINT_IMAGE) {
+    nsvg__xformSetTranslation(t, tx, ty);
+    nsvg__xformMultiply(shape->fill.bitmap->placement, t);
+    nsvg__xformSetScale(t, sx, sy);
+    nsvg__xformMultiply(shape->fill.bitmap->placement, t);
+  }
+
+  shape->strokeWidth *= avgs;
+  shape->strokeDashOffset *= avgs;
+  for (i = 0; i < shape->strokeDashCount; i++)
+    shape->strokeDashArray[i] *= avgs;
+}
+
+static void nsvg__scaleToViewbox(NSVGparser* p, const char* units)
+{
+  NSVGshape* shape;
+  NSVGclipPath* clip;
+  NSVGmask* mask;
+  NSVGtext* text;
+  float tx, ty, sx, sy, us, bounds[4], t[6];
 
   // Guess image size if not set completely.
   nsvg__imageBounds(p, bounds);
@@ -2814,57 +4630,483 @@ static void nsvg__scaleToViewbox(NSVGparser* p, const char* units)
     ty += nsvg__viewAlign(p->viewHeight*sy, p->image->height, p->alignY) / sy;
   }
 
+  p->image->viewBox[0] = p->viewMinx;
+  p->image->viewBox[1] = p->viewMiny;
+  p->image->viewBox[2] = p->viewWidth;
+  p->image->viewBox[3] = p->viewHeight;
+  p->image->alignX = (char)p->alignX;
+  p->image->alignY = (char)p->alignY;
+  p->image->alignType = (char)p->alignType;
+
   // Transform
   sx *= us;
   sy *= us;
-  avgs = (sx+sy) / 2.0f;
-  for (shape = p->image->shapes; shape != NULL; shape = shape->next) {
-    shape->bounds[0] = (shape->bounds[0] + tx) * sx;
-    shape->bounds[1] = (shape->bounds[1] + ty) * sy;
-    shape->bounds[2] = (shape->bounds[2] + tx) * sx;
-    shape->bounds[3] = (shape->bounds[3] + ty) * sy;
-    for (path = shape->paths; path != NULL; path = path->next) {
-      path->bounds[0] = (path->bounds[0] + tx) * sx;
-      path->bounds[1] = (path->bounds[1] + ty) * sy;
-      path->bounds[2] = (path->bounds[2] + tx) * sx;
-      path->bounds[3] = (path->bounds[3] + ty) * sy;
-      for (i =0; i < path->npts; i++) {
-        pt = &path->pts[i*2];
-        pt[0] = (pt[0] + tx) * sx;
-        pt[1] = (pt[1] + ty) * sy;
+  for (clip = p->image->clipPaths; clip != NULL; clip = clip->next) {
+    for (shape = clip->shapes; shape != NULL; shape = shape->next)
+      nsvg__scaleShape(shape, tx, ty, sx, sy);
+  }
+  for (mask = p->image->masks; mask != NULL; mask = mask->next) {
+    if (mask->region != NULL)
+      nsvg__scaleShape(mask->region, tx, ty, sx, sy);
+    for (shape = mask->shapes; shape != NULL; shape = shape->next)
+      nsvg__scaleShape(shape, tx, ty, sx, sy);
+  }
+  for (shape = p->image->shapes; shape != NULL; shape = shape->next)
+    nsvg__scaleShape(shape, tx, ty, sx, sy);
+  for (text = p->image->texts; text != NULL; text = text->next) {
+    nsvg__xformSetTranslation(t, tx, ty);
+    nsvg__xformMultiply(text->xform, t);
+    nsvg__xformSetScale(t, sx, sy);
+    nsvg__xformMultiply(text->xform, t);
+  }
+}
+
+static NSVGclipPathData* nsvg__findClipPath(NSVGparser* p, const char* id)
+{
+  NSVGclipPathData* clip;
+  for (clip = p->clipDefs; clip != NULL; clip = clip->next) {
+    if (strcmp(clip->id, id) == 0)
+      return clip;
+  }
+  return NULL;
+}
+
+static NSVGgradient* nsvg__copyGradient(NSVGgradient* src, float* xform)
+{
+  size_t size = sizeof(NSVGgradient) + sizeof(NSVGgradientStop)*(src->nstops-1);
+  NSVGgradient* grad = (NSVGgradient*)malloc(size);
+  if (grad == NULL) return NULL;
+  memcpy(grad, src, size);
+  nsvg__xformMultiply(grad->xform, xform);
+  return grad;
+}
+
+static NSVGbitmap* nsvg__copyBitmap(NSVGbitmap* src, float* xform)
+{
+  NSVGbitmap* bitmap = (NSVGbitmap*)malloc(sizeof(NSVGbitmap));
+  if (bitmap == NULL) return NULL;
+  memcpy(bitmap, src, sizeof(NSVGbitmap));
+  bitmap->pixels = NULL;
+  bitmap->width = bitmap->height = 0;
+  bitmap->href = (char*)malloc(strlen(src->href) + 1);
+  if (bitmap->href == NULL) {
+    free(bitmap);
+    return NULL;
+  }
+  strcpy(bitmap->href, src->href);
+  nsvg__xformMultiply(bitmap->placement, xform);
+  return bitmap;
+}
+
+static void nsvg__copyPaint(NSVGpaint* paint, float* xform)
+{
+  if (paint->type == NSVG_PAINT_LINEAR_GRADIENT || paint->type == NSVG_PAINT_RADIAL_GRADIENT) {
+    paint->gradient = nsvg__copyGradient(paint->gradient, xform);
+    if (paint->gradient == NULL)
+      paint->type = NSVG_PAINT_NONE;
+  } else if (paint->type == NSVG_PAINT_IMAGE) {
+    paint->bitmap = nsvg__copyBitmap(paint->bitmap, xform);
+    if (paint->bitmap == NULL)
+      paint->type = NSVG_PAINT_NONE;
+  }
+}
+
+static void nsvg__resolveRef(NSVGparser* p, NSVGclipRef* ref, NSVGshape* shape, float* placement);
+
+// Places the clip paths and masks of a shape in a clip path or mask along with its copy.
+static void nsvg__resolveContentRefs(NSVGparser* p, NSVGshape* src, NSVGshape* dst, float* xform)
+{
+  int i;
+  for (i = 0; i < p->nclipRefs; i++) {
+    if (p->clipRefs[i].content && p->clipRefs[i].shape == src)
+      nsvg__resolveRef(p, &p->clipRefs[i], dst, xform);
+  }
+}
+
+// Copies the visible shapes of a clip path or mask, placed by xform.
+static NSVGshape* nsvg__copyShapes(NSVGparser* p, NSVGshape* shapes, float* xform, char hasRefs)
+{
+  NSVGshape* first = NULL;
+  NSVGshape* tail = NULL;
+  NSVGshape* shape;
+  NSVGpath* src;
+  NSVGpath* path;
+  float bounds[4];
+  float scale = nsvg__getAverageScale(xform);
+  int i;
+
+  for (; shapes != NULL; shapes = shapes->next) {
+    if (!(shapes->flags & NSVG_FLAGS_VISIBLE))
+      continue;
+    shape = (NSVGshape*)malloc(sizeof(NSVGshape));
+    if (shape == NULL) break;
+    memcpy(shape, shapes, sizeof(NSVGshape));
+    shape->groups = NULL;
+    shape->paths = NULL;
+    shape->next = NULL;
+    memset(shape->clip, 0, sizeof shape->clip);
+    shape->mask = NULL;
+    shape->layer = NULL;
+    p->copiedShapes++;
+    nsvg__copyPaint(&shape->fill, xform);
+    nsvg__copyPaint(&shape->stroke, xform);
+    shape->strokeWidth *= scale;
+    shape->strokeDashOffset *= scale;
+    for (i = 0; i < shape->strokeDashCount; i++)
+      shape->strokeDashArray[i] *= scale;
+    if (first == NULL)
+      first = shape;
+    else
+      tail->next = shape;
+    tail = shape;
+
+    for (src = shapes->paths; src != NULL; src = src->next) {
+      path = (NSVGpath*)malloc(sizeof(NSVGpath));
+      if (path == NULL) break;
+      memcpy(path, src, sizeof(NSVGpath));
+      path->pts = (float*)malloc(src->npts*2*sizeof(float));
+      if (path->pts == NULL) {
+        free(path);
+        break;
+      }
+      for (i = 0; i < src->npts; i++)
+        nsvg__xformPoint(&path->pts[i*2], &path->pts[i*2+1], src->pts[i*2], src->pts[i*2+1], xform);
+      for (i = 0; i < path->npts-1; i += 3) {
+        nsvg__curveBounds(bounds, &path->pts[i*2]);
+        if (i == 0) {
+          memcpy(path->bounds, bounds, sizeof bounds);
+        } else {
+          path->bounds[0] = nsvg__minf(path->bounds[0], bounds[0]);
+          path->bounds[1] = nsvg__minf(path->bounds[1], bounds[1]);
+          path->bounds[2] = nsvg__maxf(path->bounds[2], bounds[2]);
+          path->bounds[3] = nsvg__maxf(path->bounds[3], bounds[3]);
+        }
+      }
+      path->next = shape->paths;
+      shape->paths = path;
+    }
+
+    if (shape->paths != NULL) {
+      memcpy(shape->bounds, shape->paths->bounds, sizeof shape->bounds);
+      for (path = shape->paths->next; path != NULL; path = path->next) {
+        shape->bounds[0] = nsvg__minf(shape->bounds[0], path->bounds[0]);
+        shape->bounds[1] = nsvg__minf(shape->bounds[1], path->bounds[1]);
+        shape->bounds[2] = nsvg__maxf(shape->bounds[2], path->bounds[2]);
+        shape->bounds[3] = nsvg__maxf(shape->bounds[3], path->bounds[3]);
       }
     }
+    if (hasRefs)
+      nsvg__resolveContentRefs(p, shapes, shape, xform);
+  }
+  return first;
+}
+
+// Starts placing the contents of a clip path or mask, unless that would
+// recurse forever or expand without bound.
+static int nsvg__beginResolve(NSVGparser* p, const void* def)
+{
+  int i;
+  if (p->resolveDepth >= NSVG_MAX_RESOLVE_DEPTH || p->copiedShapes > NSVG_MAX_REPLAYED_EVENTS)
+    return 0;
+  for (i = 0; i < p->resolveDepth; i++) {
+    if (p->resolving[i] == def)
+      return 0;
+  }
+  p->resolving[p->resolveDepth++] = def;
+  return 1;
+}
+
+// Finds the bounding box of the element with a clip path or mask, in its user space.
+static void nsvg__referenceBounds(NSVGparser* p, NSVGclipRef* ref, float* bounds)
+{
+  float inv[6], shapeBounds[4];
+  int j, found = 0;
+
+  // The bounding box covers every shape of the element
+  nsvg__xformInverse(inv, ref->xform);
+  for (j = 0; j < p->nclipRefs; j++) {
+    if (p->clipRefs[j].serial != ref->serial)
+      continue;
+    nsvg__getLocalBounds(shapeBounds, p->clipRefs[j].shape, inv);
+    if (!found) {
+      memcpy(bounds, shapeBounds, sizeof(float)*4);
+      found = 1;
+    } else {
+      bounds[0] = nsvg__minf(bounds[0], shapeBounds[0]);
+      bounds[1] = nsvg__minf(bounds[1], shapeBounds[1]);
+      bounds[2] = nsvg__maxf(bounds[2], shapeBounds[2]);
+      bounds[3] = nsvg__maxf(bounds[3], shapeBounds[3]);
+    }
+  }
+}
+
+// Sets xform to map the unit square onto bounds, and then into the user space.
+static void nsvg__boundsXform(float* xform, const float* bounds, float* userXform)
+{
+  xform[0] = bounds[2] - bounds[0]; xform[1] = 0.0f;
+  xform[2] = 0.0f; xform[3] = bounds[3] - bounds[1];
+  xform[4] = bounds[0]; xform[5] = bounds[1];
+  nsvg__xformMultiply(xform, userXform);
+}
+
+static NSVGmaskData* nsvg__findMask(NSVGparser* p, const char* id)
+{
+  NSVGmaskData* mask;
+  for (mask = p->maskDefs; mask != NULL; mask = mask->next) {
+    if (strcmp(mask->id, id) == 0)
+      return mask;
+  }
+  return NULL;
+}
+
+// Creates a filled rectangle shape, placed by xform.
+static NSVGshape* nsvg__createRectShape(NSVGparser* p, const float* rect, float* xform)
+{
+  NSVGattrib* attr = nsvg__getAttr(p);
+  NSVGshape* shape;
+  float saved[6];
+
+  memcpy(saved, attr->xform, sizeof saved);
+  memcpy(attr->xform, xform, sizeof saved);
+  nsvg__resetPath(p);
+  nsvg__moveTo(p, rect[0], rect[1]);
+  nsvg__lineTo(p, rect[2], rect[1]);
+  nsvg__lineTo(p, rect[2], rect[3]);
+  nsvg__lineTo(p, rect[0], rect[3]);
+  nsvg__addPath(p, 1);
+  memcpy(attr->xform, saved, sizeof saved);
+  if (p->plist == NULL)
+    return NULL;
+
+  shape = (NSVGshape*)malloc(sizeof(NSVGshape));
+  if (shape == NULL) {
+    nsvg__deletePaths(p->plist);
+    p->plist = NULL;
+    return NULL;
+  }
+  memset(shape, 0, sizeof(NSVGshape));
+  shape->paths = p->plist;
+  p->plist = NULL;
+  memcpy(shape->bounds, shape->paths->bounds, sizeof shape->bounds);
+  shape->fill.type = NSVG_PAINT_COLOR;
+  shape->fill.color = 0xff000000;
+  shape->stroke.type = NSVG_PAINT_NONE;
+  shape->opacity = 1.0f;
+  shape->fillRule = NSVG_FILLRULE_NONZERO;
+  shape->flags = NSVG_FLAGS_VISIBLE;
+  return shape;
+}
+
+// Places a referenced mask in the coordinates of the shape using it, further
+// placed by placement for the contents of another mask, or NULL.
+static void nsvg__resolveMask(NSVGparser* p, NSVGclipRef* ref, NSVGshape* shape, float* placement)
+{
+  NSVGmaskData* def;
+  NSVGmaskInstance* instance;
+  NSVGmask* mask;
+  float userXform[6], xform[6], bounds[4], region[4];
+
+  def = nsvg__findMask(p, ref->id);
+  if (def == NULL)
+    return;
+  memcpy(userXform, ref->xform, sizeof userXform);
+  if (placement != NULL)
+    nsvg__xformMultiply(userXform, placement);
+
+  if (def->units == NSVG_OBJECT_SPACE || def->contentUnits == NSVG_OBJECT_SPACE)
+    nsvg__referenceBounds(p, ref, bounds);
+  if (def->units == NSVG_OBJECT_SPACE) {
+    region[0] = bounds[0] + nsvg__convertToPixels(p, def->x, 0.0f, 1.0f) * (bounds[2] - bounds[0]);
+    region[1] = bounds[1] + nsvg__convertToPixels(p, def->y, 0.0f, 1.0f) * (bounds[3] - bounds[1]);
+    region[2] = region[0] + nsvg__convertToPixels(p, def->width, 0.0f, 1.0f) * (bounds[2] - bounds[0]);
+    region[3] = region[1] + nsvg__convertToPixels(p, def->height, 0.0f, 1.0f) * (bounds[3] - bounds[1]);
+  } else {
+    region[0] = nsvg__convertToPixels(p, def->x, nsvg__actualOrigX(p), nsvg__actualWidth(p));
+    region[1] = nsvg__convertToPixels(p, def->y, nsvg__actualOrigY(p), nsvg__actualHeight(p));
+    region[2] = region[0] + nsvg__convertToPixels(p, def->width, 0.0f, nsvg__actualWidth(p));
+    region[3] = region[1] + nsvg__convertToPixels(p, def->height, 0.0f, nsvg__actualHeight(p));
+  }
+  if (def->contentUnits == NSVG_OBJECT_SPACE)
+    nsvg__boundsXform(xform, bounds, userXform);
+  else
+    memcpy(xform, userXform, sizeof xform);
+
+  for (instance = p->maskInstances; instance != NULL; instance = instance->next) {
+    if (instance->def == def && memcmp(instance->xform, xform, sizeof xform) == 0 &&
+      memcmp(instance->region, region, sizeof region) == 0)
+      break;
+  }
+  if (instance == NULL) {
+    // A mask which contains itself is an error, and hides what it masks
+    if (!nsvg__beginResolve(p, def)) {
+      shape->flags &= ~NSVG_FLAGS_VISIBLE;
+      return;
+    }
+    instance = (NSVGmaskInstance*)malloc(sizeof(NSVGmaskInstance));
+    mask = (NSVGmask*)malloc(sizeof(NSVGmask));
+    if (instance == NULL || mask == NULL) {
+      free(instance);
+      free(mask);
+      p->resolveDepth--;
+      return;
+    }
+    memset(mask, 0, sizeof(NSVGmask));
+    memcpy(mask->id, def->id, sizeof mask->id);
+    mask->region = nsvg__createRectShape(p, region, userXform);
+    mask->shapes = nsvg__copyShapes(p, def->shapes, xform, def->hasRefs);
+    p->resolveDepth--;
+    mask->next = p->image->masks;
+    p->image->masks = mask;
+
+    instance->mask = mask;
+    instance->def = def;
+    memcpy(instance->xform, xform, sizeof xform);
+    memcpy(instance->region, region, sizeof region);
+    instance->next = p->maskInstances;
+    p->maskInstances = instance;
+  }
+  shape->mask = instance->mask;
+}
+
+// Places a referenced clip path in the coordinates of the shape using it,
+// further placed by placement for the contents of a clip path or mask, or NULL.
+static void nsvg__resolveClip(NSVGparser* p, NSVGclipRef* ref, NSVGshape* shape, float* placement)
+{
+  NSVGclipPathData* def;
+  NSVGclipInstance* instance;
+  NSVGclipPath* clip;
+  float userXform[6], xform[6], bounds[4];
+  int k;
+
+  def = nsvg__findClipPath(p, ref->id);
+  if (def == NULL)
+    return;
+  memcpy(userXform, ref->xform, sizeof userXform);
+  if (placement != NULL)
+    nsvg__xformMultiply(userXform, placement);
 
-    if (shape->fill.type == NSVG_PAINT_LINEAR_GRADIENT || shape->fill.type == NSVG_PAINT_RADIAL_GRADIENT) {
-      nsvg__scaleGradient(shape->fill.gradient, tx,ty, sx,sy);
-      memcpy(t, shape->fill.gradient->xform, sizeof(float)*6);
-      nsvg__xformInverse(shape->fill.gradient->xform, t);
+  if (def->units == NSVG_OBJECT_SPACE) {
+    nsvg__referenceBounds(p, ref, bounds);
+    nsvg__boundsXform(xform, bounds, userXform);
+  } else {
+    memcpy(xform, userXform, sizeof xform);
+  }
+
+  for (instance = p->clipInstances; instance != NULL; instance = instance->next) {
+    if (instance->def == def && memcmp(instance->xform, xform, sizeof xform) == 0)
+      break;
+  }
+  if (instance == NULL) {
+    // A clip path which contains itself is an error, and hides what it clips
+    if (!nsvg__beginResolve(p, def)) {
+      shape->flags &= ~NSVG_FLAGS_VISIBLE;
+      return;
+    }
+    instance = (NSVGclipInstance*)malloc(sizeof(NSVGclipInstance));
+    clip = (NSVGclipPath*)malloc(sizeof(NSVGclipPath));
+    if (instance == NULL || clip == NULL) {
+      free(instance);
+      free(clip);
+      p->resolveDepth--;
+      return;
     }
-    if (shape->stroke.type == NSVG_PAINT_LINEAR_GRADIENT || shape->stroke.type == NSVG_PAINT_RADIAL_GRADIENT) {
-      nsvg__scaleGradient(shape->stroke.gradient, tx,ty, sx,sy);
-      memcpy(t, shape->stroke.gradient->xform, sizeof(float)*6);
-      nsvg__xformInverse(shape->stroke.gradient->xform, t);
+    memset(clip, 0, sizeof(NSVGclipPath));
+    memcpy(clip->id, def->id, sizeof clip->id);
+    clip->shapes = nsvg__copyShapes(p, def->shapes, xform, def->hasRefs);
+    p->resolveDepth--;
+    clip->next = p->image->clipPaths;
+    p->image->clipPaths = clip;
+
+    instance->clip = clip;
+    instance->def = def;
+    memcpy(instance->xform, xform, sizeof xform);
+    instance->next = p->clipInstances;
+    p->clipInstances = instance;
+  }
+
+  for (k = 0; k < NSVG_MAX_CLIP_PATHS; k++) {
+    if (shape->clip[k] == NULL) {
+      shape->clip[k] = instance->clip;
+      break;
     }
+  }
+}
 
-    shape->strokeWidth *= avgs;
-    shape->strokeDashOffset *= avgs;
-    for (i = 0; i < shape->strokeDashCount; i++)
-      shape->strokeDashArray[i] *= avgs;
+static void nsvg__resolveRef(NSVGparser* p, NSVGclipRef* ref, NSVGshape* shape, float* placement)
+{
+  if (ref->mask)
+    nsvg__resolveMask(p, ref, shape, placement);
+  else
+    nsvg__resolveClip(p, ref, shape, placement);
+}
+
+// Places each referenced clip path and mask in the coordinates of the shapes using it.
+static void nsvg__resolveClipPaths(NSVGparser* p)
+{
+  int i;
+
+  // Those of clip path and mask contents are placed with each instance of them
+  for (i = 0; i < p->nclipRefs; i++) {
+    if (!p->clipRefs[i].content)
+      nsvg__resolveRef(p, &p->clipRefs[i], p->clipRefs[i].shape, NULL);
   }
 }
 
 NSVGimage* nsvgParse(char* input, const char* units, float dpi)
+{
+  return nsvgParseWithWarnings(input, units, dpi, NULL, NULL);
+}
+
+NSVGimage* nsvgParseWithWarnings(char* input, const char* units, float dpi, NSVGwarningCallback cb, void* ud)
+{
+  NSVGparseOptions options;
+  memset(&options, 0, sizeof options);
+  options.warningCb = cb;
+  options.warningUd = ud;
+  return nsvgParseWithOptions(input, units, dpi, &options);
+}
+
+NSVGimage* nsvgParseWithOptions(char* input, const char* units, float dpi, const NSVGparseOptions* options)
 {
   NSVGparser* p;
   NSVGimage* ret = 0;
+  char* unterminated;
+  char name[64];
+  int i;
 
   p = nsvg__createParser();
   if (p == NULL) {
     return NULL;
   }
   p->dpi = dpi;
+  p->input = input;
+  if (options != NULL) {
+    p->warningCb = options->warningCb;
+    p->warningUd = options->warningUd;
+    p->attr[0].color = options->currentColor & 0xffffff;
+    p->variableCount = options->variableCount;
+    p->variableNames = options->variableNames;
+    p->variableValues = options->variableValues;
+  }
+  if (p->warningCb != NULL)
+    nsvg__indexLines(p);
+
+  // Record the document first, so <use> can refer to elements defined after it
+  unterminated = nsvg__parseXML(input, nsvg__recordStart, nsvg__recordEnd, nsvg__recordContent, p);
+  nsvg__playEvents(p, 0, p->nevents);
+
+  if (unterminated != NULL) {
+    // Report the tag by its name, without any attributes.
+    while (*unterminated && (*unterminated == '/' || nsvg__isspace(*unterminated))) unterminated++;
+    for (i = 0; i < 63 && unterminated[i] && !nsvg__isspace(unterminated[i]) && unterminated[i] != '/'; i++)
+      name[i] = unterminated[i];
+    name[i] = '\0';
+    nsvg__warn(p, NSVG_WARNING_UNCLOSED_TAG, nsvg__tagOffset(p, unterminated), name, NULL);
+  }
+  for (i = 0; i < p->openCount && i < NSVG_MAX_DEPTH; i++)
+    nsvg__warn(p, NSVG_WARNING_UNCLOSED_TAG, p->openOffsets[i], p->openNames[i], NULL);
 
-  nsvg__parseXML(input, nsvg__startElement, nsvg__endElement, nsvg__content, p);
+  nsvg__resolveClipPaths(p);
 
   // Scale to viewBox
   nsvg__scaleToViewbox(p, units);
@@ -2906,18 +5148,157 @@ error:
   return NULL;
 }
 
+static void nsvg__fitBitmap(NSVGbitmap* bitmap, unsigned char* pixels, int width, int height)
+{
+  float* viewport = bitmap->viewport;
+  float sx, sy, t[6];
+
+  nsvg__releasePixels(bitmap->pixels);
+  bitmap->pixels = pixels;
+  bitmap->width = width;
+  bitmap->height = height;
+
+  // Fit the bitmap into the viewport, then place it in the image
+  sx = viewport[2] / width;
+  sy = viewport[3] / height;
+  if (bitmap->alignType == NSVG_ALIGN_MEET)
+    sx = sy = nsvg__minf(sx, sy);
+  else if (bitmap->alignType == NSVG_ALIGN_SLICE)
+    sx = sy = nsvg__maxf(sx, sy);
+  t[0] = sx; t[1] = 0.0f;
+  t[2] = 0.0f; t[3] = sy;
+  t[4] = viewport[0] + nsvg__viewAlign(width*sx, viewport[2], bitmap->alignX);
+  t[5] = viewport[1] + nsvg__viewAlign(height*sy, viewport[3], bitmap->alignY);
+  nsvg__xformMultiply(t, bitmap->placement);
+  nsvg__xformInverse(bitmap->xform, t);
+}
+
+int nsvgSetBitmapPixels(NSVGbitmap* bitmap, const unsigned char* rgba, int width, int height)
+{
+  unsigned char* pixels;
+  size_t i, n;
+  int a;
+
+  if (width <= 0 || height <= 0)
+    return 1;
+  n = (size_t)width * (size_t)height;
+  if (n > ((size_t)-1 - sizeof(size_t)) / 4)
+    return 0;
+  pixels = nsvg__allocPixels(n*4);
+  if (pixels == NULL)
+    return 0;
+  for (i = 0; i < n; i++) {
+    a = rgba[i*4+3];
+    pixels[i*4+0] = (unsigned char)(rgba[i*4+0] * a / 255);
+    pixels[i*4+1] = (unsigned char)(rgba[i*4+1] * a / 255);
+    pixels[i*4+2] = (unsigned char)(rgba[i*4+2] * a / 255);
+    pixels[i*4+3] = (unsigned char)a;
+  }
+  nsvg__fitBitmap(bitmap, pixels, width, height);
+  return 1;
+}
+
+void nsvgShareBitmapPixels(NSVGbitmap* bitmap, const NSVGbitmap* source)
+{
+  if (bitmap == source || source->pixels == NULL)
+    return;
+  nsvg__fitBitmap(bitmap, nsvg__retainPixels(source->pixels), source->width, source->height);
+}
+
+int nsvgSetTextOutline(NSVGtext* text, const float* pts, const int* npts, const char* closed, int npaths)
+{
+  NSVGshape* shape = text->shape;
+  NSVGpath* paths = NULL;
+  NSVGpath** tail = &paths;
+  NSVGpath* path;
+  float bounds[4];
+  int i, j;
+
+  for (i = 0; i < npaths; pts += npts[i]*2, i++) {
+    if (npts[i] < 4) continue;
+    path = (NSVGpath*)malloc(sizeof(NSVGpath));
+    if (path == NULL) goto error;
+    memset(path, 0, sizeof(NSVGpath));
+    *tail = path;
+    tail = &path->next;
+    path->pts = (float*)malloc(npts[i]*2*sizeof(float));
+    if (path->pts == NULL) goto error;
+    path->npts = npts[i];
+    path->closed = closed[i];
+    for (j = 0; j < npts[i]; j++)
+      nsvg__xformPoint(&path->pts[j*2], &path->pts[j*2+1], pts[j*2], pts[j*2+1], text->xform);
+    for (j = 0; j < path->npts-1; j += 3) {
+      nsvg__curveBounds(bounds, &path->pts[j*2]);
+      if (j == 0) {
+        memcpy(path->bounds, bounds, sizeof(float)*4);
+      } else {
+        path->bounds[0] = nsvg__minf(path->bounds[0], bounds[0]);
+        path->bounds[1] = nsvg__minf(path->bounds[1], bounds[1]);
+        path->bounds[2] = nsvg__maxf(path->bounds[2], bounds[2]);
+        path->bounds[3] = nsvg__maxf(path->bounds[3], bounds[3]);
+      }
+    }
+  }
+
+  nsvg__deletePaths(shape->paths);
+  shape->paths = paths;
+  if (paths == NULL)
+    memset(shape->bounds, 0, sizeof(float)*4);
+  for (path = paths; path != NULL; path = path->next) {
+    if (path == paths) {
+      memcpy(shape->bounds, path->bounds, sizeof(float)*4);
+    } else {
+      shape->bounds[0] = nsvg__minf(shape->bounds[0], path->bounds[0]);
+      shape->bounds[1] = nsvg__minf(shape->bounds[1], path->bounds[1]);
+      shape->bounds[2] = nsvg__maxf(shape->bounds[2], path->bounds[2]);
+      shape->bounds[3] = nsvg__maxf(shape->bounds[3], path->bounds[3]);
+    }
+  }
+  return 1;
+
+error:
+  nsvg__deletePaths(paths);
+  return 0;
+}
+
+void nsvgSetPaintColor(NSVGpaint* paint, unsigned int color)
+{
+  nsvg__deletePaint(paint);
+  paint->type = NSVG_PAINT_COLOR;
+  paint->color = color;
+}
+
 void nsvgDelete(NSVGimage* image)
 {
-  NSVGshape *snext, *shape;
+  NSVGclipPath* clip;
+  NSVGmask* mask;
+  NSVGlayer* layer;
+  NSVGtext* text;
   if (image == NULL) return;
-  shape = image->shapes;
-  while (shape != NULL) {
-    snext = shape->next;
-    nsvg__deletePaths(shape->paths);
-    nsvg__deletePaint(&shape->fill);
-    nsvg__deletePaint(&shape->stroke);
-    free(shape);
-    shape = snext;
+  nsvg__deleteShapes(image->shapes);
+  while (image->clipPaths != NULL) {
+    clip = image->clipPaths->next;
+    nsvg__deleteShapes(image->clipPaths->shapes);
+    free(image->clipPaths);
+    image->clipPaths = clip;
+  }
+  while (image->masks != NULL) {
+    mask = image->masks->next;
+    nsvg__deleteShapes(image->masks->region);
+    nsvg__deleteShapes(image->masks->shapes);
+    free(image->masks);
+    image->masks = mask;
+  }
+  while (image->layers != NULL) {
+    layer = image->layers->next;
+    free(image->layers);
+    image->layers = layer;
+  }
+  while (image->texts != NULL) {
+    text = image->texts->next;
+    free(image->texts->content);
+    free(image->texts);
+    image->texts = text;
   }
   free(image);
 }
@@ -59,6 +59,30 @@ void nsvgRasterize(NSVGrasterizer* r,
            NSVGimage* image, float tx, float ty, float scale,
            unsigned char* dst, int w, int h, int stride);
 
+// As nsvgRasterize, but with separate horizontal and vertical scale.
+//   sx,sy - image scale along the x and y axes
+void nsvgRasterizeXY(NSVGrasterizer* r,
+           NSVGimage* image, float tx, float ty, float sx, float sy,
+           unsigned char* dst, int w, int h, int stride);
+
+// Sets the rasterization quality.
+//   subsamples - number of vertical samples per pixel, between 1 and 32 (default 5)
+//   tessTol - tolerance used when flattening curves, smaller is smoother (default 0.25)
+//   distTol - distance below which points are merged (default 0.01)
+//   aliased - when non-zero, pixels are either fully covered or not at all
+void nsvgSetQuality(NSVGrasterizer* r, int subsamples, float tessTol, float distTol, int aliased);
+
+// Sets whether nsvgRasterize returns premultiplied alpha, skipping unpremultiplying and
+// bleeding edge colors into transparent pixels. Off by default.
+void nsvgSetPremultiplied(NSVGrasterizer* r, int premultiplied);
+
+// Limits which shapes are drawn, by their position in the image's shape list. Shapes
+// with a zero entry are skipped, as are those past the end of a non-NULL mask.
+// The mask is not copied, and must outlive rasterizing. NULL draws every visible shape.
+//   mask - one byte per shape
+//   count - number of bytes in mask
+void nsvgSetShapeMask(NSVGrasterizer* r, const unsigned char* mask, int count);
+
 // Deletes rasterizer context.
 void nsvgDeleteRasterizer(NSVGrasterizer*);
 
@@ -74,6 +98,8 @@ void nsvgDeleteRasterizer(NSVGrasterizer*);
 #include <math.h>
 
 #define NSVG__SUBSAMPLES  5
+#define NSVG__MAX_SUBSAMPLES  32
+#define NSVG__MAX_LAYERS  NSVG_MAX_LAYERS
 #define NSVG__FIXSHIFT    10
 #define NSVG__FIX     (1 << NSVG__FIXSHIFT)
 #define NSVG__FIXMASK   (NSVG__FIX-1)
@@ -111,6 +137,8 @@ typedef struct NSVGcachedPaint {
   char spread;
   float xform[6];
   unsigned int colors[256];
+  const NSVGbitmap* bitmap;
+  int alpha;
 } NSVGcachedPaint;
 
 struct NSVGrasterizer
@@ -119,6 +147,8 @@ struct NSVGrasterizer
 
   float tessTol;
   float distTol;
+  int subsamples;
+  int aliased;
 
   NSVGedge* edges;
   int nedges;
@@ -141,6 +171,37 @@ struct NSVGrasterizer
 
   unsigned char* bitmap;
   int width, height, stride;
+  // Area of the image bitmap covers, [x0,y0,x1,y1]. Painting outside it is skipped.
+  int bitmapRect[4];
+
+  int premultiplied;
+
+  const unsigned char* shapeMask;
+  int shapeMaskCount;
+
+  // Coverage of the clip paths and mask of the current shape, one byte per pixel
+  unsigned char* clipMask;
+  unsigned char* clipScratch;
+  int cclipMask;
+  NSVGclipPath* clipKey[NSVG_MAX_CLIP_PATHS];
+  NSVGmask* maskKey;
+  // Premultiplied RGBA rendering of the current mask
+  unsigned char* maskImage;
+  int cmaskImage;
+  int maskDepth;
+
+  // Translucent groups being drawn, outermost first, and their images
+  NSVGlayer* layers[NSVG__MAX_LAYERS];
+  unsigned char* layerImages[NSVG__MAX_LAYERS];
+  int clayerImages[NSVG__MAX_LAYERS];
+  // Area of the image each layer covers, only as large as its shapes
+  int layerRects[NSVG__MAX_LAYERS][4];
+  int nlayers;
+  // Opacity of groups nested too deep to get a layer of their own
+  float layerOpacity;
+  const unsigned char* clip;
+  // When set, coverage is accumulated here instead of painted
+  unsigned char* coverage;
 };
 
 NSVGrasterizer* nsvgCreateRasterizer()
@@ -151,6 +212,7 @@ NSVGrasterizer* nsvgCreateRasterizer()
 
   r->tessTol = 0.25f;
   r->distTol = 0.01f;
+  r->subsamples = NSVG__SUBSAMPLES;
 
   return r;
 
@@ -159,9 +221,31 @@ error:
   return NULL;
 }
 
+void nsvgSetQuality(NSVGrasterizer* r, int subsamples, float tessTol, float distTol, int aliased)
+{
+  if (subsamples < 1) subsamples = 1;
+  if (subsamples > NSVG__MAX_SUBSAMPLES) subsamples = NSVG__MAX_SUBSAMPLES;
+  r->subsamples = subsamples;
+  r->tessTol = tessTol;
+  r->distTol = distTol;
+  r->aliased = aliased;
+}
+
+void nsvgSetPremultiplied(NSVGrasterizer* r, int premultiplied)
+{
+  r->premultiplied = premultiplied;
+}
+
+void nsvgSetShapeMask(NSVGrasterizer* r, const unsigned char* mask, int count)
+{
+  r->shapeMask = mask;
+  r->shapeMaskCount = count;
+}
+
 void nsvgDeleteRasterizer(NSVGrasterizer* r)
 {
   NSVGmemPage* p;
+  int i;
 
   if (r == NULL) return;
 
@@ -176,6 +260,11 @@ void nsvgDeleteRasterizer(NSVGrasterizer* r)
   if (r->points) free(r->points);
   if (r->points2) free(r->points2);
   if (r->scanline) free(r->scanline);
+  if (r->clipMask) free(r->clipMask);
+  if (r->clipScratch) free(r->clipScratch);
+  if (r->maskImage) free(r->maskImage);
+  for (i = 0; i < NSVG__MAX_LAYERS; i++)
+    free(r->layerImages[i]);
 
   free(r);
 }
@@ -363,7 +452,7 @@ static void nsvg__flattenCubicBez(NSVGrasterizer* r,
   nsvg__flattenCubicBez(r, x1234,y1234, x234,y234, x34,y34, x4,y4, level+1, type);
 }
 
-static void nsvg__flattenShape(NSVGrasterizer* r, NSVGshape* shape, float scale)
+static void nsvg__flattenShape(NSVGrasterizer* r, NSVGshape* shape, float sx, float sy)
 {
   int i, j;
   NSVGpath* path;
@@ -371,13 +460,13 @@ static void nsvg__flattenShape(NSVGrasterizer* r, NSVGshape* shape, float scale)
   for (path = shape->paths; path != NULL; path = path->next) {
     r->npoints = 0;
     // Flatten path
-    nsvg__addPathPoint(r, path->pts[0]*scale, path->pts[1]*scale, 0);
+    nsvg__addPathPoint(r, path->pts[0]*sx, path->pts[1]*sy, 0);
     for (i = 0; i < path->npts-1; i += 3) {
       float* p = &path->pts[i*2];
-      nsvg__flattenCubicBez(r, p[0]*scale,p[1]*scale, p[2]*scale,p[3]*scale, p[4]*scale,p[5]*scale, p[6]*scale,p[7]*scale, 0, 0);
+      nsvg__flattenCubicBez(r, p[0]*sx,p[1]*sy, p[2]*sx,p[3]*sy, p[4]*sx,p[5]*sy, p[6]*sx,p[7]*sy, 0, 0);
     }
     // Close path
-    nsvg__addPathPoint(r, path->pts[0]*scale, path->pts[1]*scale, 0);
+    nsvg__addPathPoint(r, path->pts[0]*sx, path->pts[1]*sy, 0);
     // Build edges
     for (i = 0, j = r->npoints-1; i < r->npoints; j = i++)
       nsvg__addEdge(r, r->points[j].x, r->points[j].y, r->points[i].x, r->points[i].y);
@@ -727,7 +816,7 @@ static void nsvg__prepareStroke(NSVGrasterizer* r, float miterLimit, int lineJoi
   }
 }
 
-static void nsvg__flattenShapeStroke(NSVGrasterizer* r, NSVGshape* shape, float scale)
+static void nsvg__flattenShapeStroke(NSVGrasterizer* r, NSVGshape* shape, float sx, float sy)
 {
   int i, j, closed;
   NSVGpath* path;
@@ -735,15 +824,16 @@ static void nsvg__flattenShapeStroke(NSVGrasterizer* r, NSVGshape* shape, float
   float miterLimit = shape->miterLimit;
   int lineJoin = shape->strokeLineJoin;
   int lineCap = shape->strokeLineCap;
+  float scale = (sx + sy) * 0.5f; // average scaling factor
   float lineWidth = shape->strokeWidth * scale;
 
   for (path = shape->paths; path != NULL; path = path->next) {
     // Flatten path
     r->npoints = 0;
-    nsvg__addPathPoint(r, path->pts[0]*scale, path->pts[1]*scale, NSVG_PT_CORNER);
+    nsvg__addPathPoint(r, path->pts[0]*sx, path->pts[1]*sy, NSVG_PT_CORNER);
     for (i = 0; i < path->npts-1; i += 3) {
       float* p = &path->pts[i*2];
-      nsvg__flattenCubicBez(r, p[0]*scale,p[1]*scale, p[2]*scale,p[3]*scale, p[4]*scale,p[5]*scale, p[6]*scale,p[7]*scale, 0, NSVG_PT_CORNER);
+      nsvg__flattenCubicBez(r, p[0]*sx,p[1]*sy, p[2]*sx,p[3]*sy, p[4]*sx,p[5]*sy, p[6]*sx,p[7]*sy, 0, NSVG_PT_CORNER);
     }
     if (r->npoints < 2)
       continue;
@@ -980,7 +1070,7 @@ static inline int nsvg__div255(int x)
 }
 
 static void nsvg__scanlineSolid(unsigned char* dst, int count, unsigned char* cover, int x, int y,
-                float tx, float ty, float scale, NSVGcachedPaint* cache)
+                float tx, float ty, float sx, float sy, NSVGcachedPaint* cache)
 {
 
   if (cache->type == NSVG_PAINT_COLOR) {
@@ -1021,9 +1111,9 @@ static void nsvg__scanlineSolid(unsigned char* dst, int count, unsigned char* co
     int i, cr, cg, cb, ca;
     unsigned int c;
 
-    fx = ((float)x - tx) / scale;
-    fy = ((float)y - ty) / scale;
-    dx = 1.0f / scale;
+    fx = ((float)x - tx) / sx;
+    fy = ((float)y - ty) / sy;
+    dx = 1.0f / sx;
 
     for (i = 0; i < count; i++) {
       int r,g,b,a,ia;
@@ -1066,9 +1156,9 @@ static void nsvg__scanlineSolid(unsigned char* dst, int count, unsigned char* co
     int i, cr, cg, cb, ca;
     unsigned int c;
 
-    fx = ((float)x - tx) / scale;
-    fy = ((float)y - ty) / scale;
-    dx = 1.0f / scale;
+    fx = ((float)x - tx) / sx;
+    fy = ((float)y - ty) / sy;
+    dx = 1.0f / sx;
 
     for (i = 0; i < count; i++) {
       int r,g,b,a,ia;
@@ -1104,24 +1194,74 @@ static void nsvg__scanlineSolid(unsigned char* dst, int count, unsigned char* co
       dst += 4;
       fx += dx;
     }
+  } else if (cache->type == NSVG_PAINT_IMAGE) {
+    // Bilinear sampling at the pixel centers
+    const NSVGbitmap* bitmap = cache->bitmap;
+    float fx, fy, dx, u, v, fu, fv;
+    float* t = cache->xform;
+    int i, j, k, iu, iv, cx, cy, a, ia, c[4];
+    const unsigned char* px;
+
+    fx = ((float)x + 0.5f - tx) / sx;
+    fy = ((float)y + 0.5f - ty) / sy;
+    dx = 1.0f / sx;
+
+    for (i = 0; i < count; i++, cover++, dst += 4, fx += dx) {
+      u = fx*t[0] + fy*t[2] + t[4];
+      v = fx*t[1] + fy*t[3] + t[5];
+      // Outside of the bitmap, such as when it doesn't fill its whole viewport
+      if (u < 0.0f || v < 0.0f || u >= (float)bitmap->width || v >= (float)bitmap->height)
+        continue;
+      u -= 0.5f;
+      v -= 0.5f;
+      iu = (int)floorf(u);
+      iv = (int)floorf(v);
+      fu = u - (float)iu;
+      fv = v - (float)iv;
+
+      c[0] = c[1] = c[2] = c[3] = 0;
+      for (j = 0; j < 4; j++) {
+        float w = ((j & 1) ? fu : 1.0f - fu) * ((j & 2) ? fv : 1.0f - fv);
+        cx = iu + (j & 1);
+        cy = iv + (j >> 1);
+        if (cx < 0) cx = 0;
+        if (cy < 0) cy = 0;
+        if (cx > bitmap->width-1) cx = bitmap->width-1;
+        if (cy > bitmap->height-1) cy = bitmap->height-1;
+        px = &bitmap->pixels[((size_t)cy*bitmap->width + cx)*4];
+        for (k = 0; k < 4; k++)
+          c[k] += (int)(px[k] * w * 256.0f);
+      }
+
+      // Already premultiplied, so every channel is scaled by coverage and opacity
+      a = nsvg__div255((int)cover[0] * cache->alpha);
+      for (k = 0; k < 4; k++)
+        c[k] = nsvg__div255((c[k] >> 8) * a);
+      ia = 255 - c[3];
+
+      // Blend over
+      for (k = 0; k < 4; k++)
+        dst[k] = (unsigned char)(c[k] + nsvg__div255(ia * (int)dst[k]));
+    }
   }
 }
 
-static void nsvg__rasterizeSortedEdges(NSVGrasterizer *r, float tx, float ty, float scale, NSVGcachedPaint* cache, char fillRule)
+static void nsvg__rasterizeSortedEdges(NSVGrasterizer *r, float tx, float ty, float sx, float sy, NSVGcachedPaint* cache, char fillRule)
 {
   NSVGactiveEdge *active = NULL;
   int y, s;
   int e = 0;
-  int maxWeight = (255 / NSVG__SUBSAMPLES);  // weight per vertical scanline
-  int xmin, xmax;
+  int maxWeight = (255 / r->subsamples);  // weight per vertical scanline
+  int fullWeight = maxWeight * r->subsamples;
+  int xmin, xmax, x;
 
   for (y = 0; y < r->height; y++) {
     memset(r->scanline, 0, r->width);
     xmin = r->width;
     xmax = 0;
-    for (s = 0; s < NSVG__SUBSAMPLES; ++s) {
+    for (s = 0; s < r->subsamples; ++s) {
       // find center of pixel for this scanline
-      float scany = (float)(y*NSVG__SUBSAMPLES + s) + 0.5f;
+      float scany = (float)(y*r->subsamples + s) + 0.5f;
       NSVGactiveEdge **step = &active;
 
       // update all active edges;
@@ -1188,13 +1328,57 @@ static void nsvg__rasterizeSortedEdges(NSVGrasterizer *r, float tx, float ty, fl
     // Blit
     if (xmin < 0) xmin = 0;
     if (xmax > r->width-1) xmax = r->width-1;
-    if (xmin <= xmax) {
-      nsvg__scanlineSolid(&r->bitmap[y * r->stride] + xmin*4, xmax-xmin+1, &r->scanline[xmin], xmin, y, tx,ty, scale, cache);
+    if (r->aliased) {
+      // Fully cover pixels which are at least half covered
+      for (x = xmin; x <= xmax; x++)
+        r->scanline[x] = r->scanline[x] * 2 >= fullWeight ? 255 : 0;
+    } else if (fullWeight != 255) {
+      // Stretch coverage when the weights don't add up to 255
+      for (x = xmin; x <= xmax; x++)
+        r->scanline[x] = (unsigned char)(r->scanline[x] * 255 / fullWeight);
+    }
+    if (xmin <= xmax && r->coverage != NULL) {
+      unsigned char* row = &r->coverage[y * r->width];
+      for (x = xmin; x <= xmax; x++)
+        row[x] = (unsigned char)(row[x] + r->scanline[x] - row[x] * r->scanline[x] / 255);
+      continue;
+    }
+    if (xmin <= xmax && r->clip != NULL) {
+      const unsigned char* row = &r->clip[y * r->width];
+      for (x = xmin; x <= xmax; x++)
+        r->scanline[x] = (unsigned char)(r->scanline[x] * row[x] / 255);
+    }
+    if (xmin < r->bitmapRect[0]) xmin = r->bitmapRect[0];
+    if (xmax > r->bitmapRect[2]-1) xmax = r->bitmapRect[2]-1;
+    if (xmin <= xmax && y >= r->bitmapRect[1] && y < r->bitmapRect[3]) {
+      unsigned char* row = &r->bitmap[(y - r->bitmapRect[1]) * r->stride];
+      nsvg__scanlineSolid(row + (xmin - r->bitmapRect[0])*4, xmax-xmin+1, &r->scanline[xmin], xmin, y, tx,ty, sx,sy, cache);
     }
   }
 
 }
 
+static void nsvg__rasterizeEdges(NSVGrasterizer* r, float tx, float ty, float sx, float sy, NSVGcachedPaint* cache, char fillRule)
+{
+  NSVGedge* e;
+  int i;
+
+  // Scale and translate edges
+  for (i = 0; i < r->nedges; i++) {
+    e = &r->edges[i];
+    e->x0 = tx + e->x0;
+    e->y0 = (ty + e->y0) * r->subsamples;
+    e->x1 = tx + e->x1;
+    e->y1 = (ty + e->y1) * r->subsamples;
+  }
+
+  // Rasterize edges
+  qsort(r->edges, r->nedges, sizeof(NSVGedge), nsvg__cmpEdge);
+
+  // now, traverse the scanlines and find the intersections on each scanline
+  nsvg__rasterizeSortedEdges(r, tx,ty, sx,sy, cache, fillRule);
+}
+
 static void nsvg__unpremultiplyAlpha(unsigned char* image, int w, int h, int stride)
 {
   int x,y;
@@ -1266,6 +1450,12 @@ static void nsvg__initPaint(NSVGcachedPaint* cache, NSVGpaint* paint, float opac
     cache->colors[0] = nsvg__applyOpacity(paint->color, opacity);
     return;
   }
+  if (paint->type == NSVG_PAINT_IMAGE) {
+    cache->bitmap = paint->bitmap;
+    cache->alpha = (int)(nsvg__clampf(opacity, 0.0f, 1.0f) * 255.0f);
+    memcpy(cache->xform, paint->bitmap->xform, sizeof(float)*6);
+    return;
+  }
 
   grad = paint->gradient;
 
@@ -1315,6 +1505,229 @@ static void nsvg__initPaint(NSVGcachedPaint* cache, NSVGpaint* paint, float opac
 
 }
 
+static void nsvg__rasterizeShape(NSVGrasterizer* r, NSVGshape* shape, float tx, float ty, float sx, float sy);
+
+static void nsvg__rasterizeCoverage(NSVGrasterizer* r, NSVGshape* shape, float tx, float ty, float sx, float sy)
+{
+  nsvg__resetPool(r);
+  r->freelist = NULL;
+  r->nedges = 0;
+  nsvg__flattenShape(r, shape, sx, sy);
+  nsvg__rasterizeEdges(r, tx,ty, sx,sy, NULL, shape->fillRule);
+}
+
+static void nsvg__setBitmapRect(NSVGrasterizer* r, int x0, int y0, int x1, int y1)
+{
+  r->bitmapRect[0] = x0;
+  r->bitmapRect[1] = y0;
+  r->bitmapRect[2] = x1;
+  r->bitmapRect[3] = y1;
+}
+
+// Renders the luminance of a mask times its alpha into r->clipMask.
+static int nsvg__renderMask(NSVGrasterizer* r, NSVGmask* mask, float tx, float ty, float sx, float sy)
+{
+  NSVGshape* shape;
+  unsigned char* bitmap = r->bitmap;
+  unsigned char* image;
+  unsigned char* px;
+  float layerOpacity = r->layerOpacity;
+  int stride = r->stride;
+  int bitmapRect[4];
+  int n = r->width * r->height;
+  int i, lum;
+
+  if (r->maskDepth > 0) {
+    // Masks used inside the contents of another draw into an image of their own
+    image = (unsigned char*)malloc(n*4);
+    if (image == NULL)
+      return 0;
+  } else {
+    if (n > r->cmaskImage) {
+      free(r->maskImage);
+      r->maskImage = (unsigned char*)malloc(n*4);
+      if (r->maskImage == NULL) {
+        r->cmaskImage = 0;
+        return 0;
+      }
+      r->cmaskImage = n;
+    }
+    image = r->maskImage;
+  }
+
+  // Draw the contents like an image of their own
+  memset(image, 0, n*4);
+  memcpy(bitmapRect, r->bitmapRect, sizeof bitmapRect);
+  r->bitmap = image;
+  r->stride = r->width*4;
+  nsvg__setBitmapRect(r, 0, 0, r->width, r->height);
+  r->layerOpacity = 1.0f;
+  r->maskDepth++;
+  for (shape = mask->shapes; shape != NULL; shape = shape->next) {
+    if (shape->flags & NSVG_FLAGS_VISIBLE)
+      nsvg__rasterizeShape(r, shape, tx, ty, sx, sy);
+  }
+  r->maskDepth--;
+  r->bitmap = bitmap;
+  r->stride = stride;
+  memcpy(r->bitmapRect, bitmapRect, sizeof bitmapRect);
+  r->layerOpacity = layerOpacity;
+  r->clip = NULL;
+  // Clipped or masked contents leave their own in r->clipMask, which is about to be replaced
+  memset(r->clipKey, 0, sizeof r->clipKey);
+  r->maskKey = NULL;
+
+  memset(r->clipScratch, 0, n);
+  if (mask->region != NULL) {
+    r->coverage = r->clipScratch;
+    nsvg__rasterizeCoverage(r, mask->region, tx, ty, sx, sy);
+    r->coverage = NULL;
+  }
+
+  // Colors are premultiplied, so the luminance already includes alpha
+  for (i = 0; i < n; i++) {
+    px = &image[i*4];
+    lum = (px[0]*13933 + px[1]*46884 + px[2]*4725) >> 16;
+    if (lum > 255) lum = 255;
+    r->clipMask[i] = (unsigned char)(lum * r->clipScratch[i] / 255);
+  }
+  if (image != r->maskImage)
+    free(image);
+  return 1;
+}
+
+// Renders the coverage of a clip path into coverage, the union of its shapes
+// each intersected with their own clip paths.
+static int nsvg__renderClipPath(NSVGrasterizer* r, NSVGclipPath* clip, unsigned char* coverage, float tx, float ty, float sx, float sy)
+{
+  NSVGshape* shape;
+  unsigned char* own = NULL;
+  unsigned char* nested = NULL;
+  int n = r->width * r->height;
+  int i, k, ok = 1;
+
+  memset(coverage, 0, n);
+  for (shape = clip->shapes; shape != NULL && ok; shape = shape->next) {
+    if (!(shape->flags & NSVG_FLAGS_VISIBLE))
+      continue;
+    if (shape->clip[0] == NULL) {
+      r->coverage = coverage;
+      nsvg__rasterizeCoverage(r, shape, tx, ty, sx, sy);
+      continue;
+    }
+
+    if (own == NULL) {
+      own = (unsigned char*)malloc(n);
+      nested = (unsigned char*)malloc(n);
+      if (own == NULL || nested == NULL) {
+        ok = 0;
+        break;
+      }
+    }
+    memset(own, 0, n);
+    r->coverage = own;
+    nsvg__rasterizeCoverage(r, shape, tx, ty, sx, sy);
+    for (k = 0; k < NSVG_MAX_CLIP_PATHS && shape->clip[k] != NULL && ok; k++) {
+      ok = nsvg__renderClipPath(r, shape->clip[k], nested, tx, ty, sx, sy);
+      for (i = 0; i < n; i++)
+        own[i] = (unsigned char)(own[i] * nested[i] / 255);
+    }
+    for (i = 0; i < n; i++)
+      coverage[i] = (unsigned char)(coverage[i] + own[i] - coverage[i] * own[i] / 255);
+  }
+  r->coverage = NULL;
+  free(own);
+  free(nested);
+  return ok;
+}
+
+// Renders the mask and the intersection of the clip paths of a shape into
+// r->clipMask, reusing the previous one when they are the same.
+static int nsvg__updateClipMask(NSVGrasterizer* r, NSVGshape* shape, float tx, float ty, float sx, float sy)
+{
+  int n = r->width * r->height;
+  int i, k;
+
+  if (memcmp(r->clipKey, shape->clip, sizeof r->clipKey) == 0 && r->maskKey == shape->mask)
+    return 1;
+
+  if (n > r->cclipMask) {
+    free(r->clipMask);
+    free(r->clipScratch);
+    r->clipMask = (unsigned char*)malloc(n);
+    r->clipScratch = (unsigned char*)malloc(n);
+    if (r->clipMask == NULL || r->clipScratch == NULL) {
+      free(r->clipMask);
+      free(r->clipScratch);
+      r->clipMask = r->clipScratch = NULL;
+      r->cclipMask = 0;
+      return 0;
+    }
+    r->cclipMask = n;
+  }
+
+  // Invalidate the cache while the buffers are being overwritten
+  memset(r->clipKey, 0, sizeof r->clipKey);
+  r->maskKey = NULL;
+  r->clip = NULL;
+  if (shape->mask != NULL) {
+    if (!nsvg__renderMask(r, shape->mask, tx, ty, sx, sy))
+      return 0;
+  } else {
+    memset(r->clipMask, 255, n);
+  }
+
+  for (k = 0; k < NSVG_MAX_CLIP_PATHS && shape->clip[k] != NULL; k++) {
+    if (!nsvg__renderClipPath(r, shape->clip[k], r->clipScratch, tx, ty, sx, sy))
+      return 0;
+    for (i = 0; i < n; i++)
+      r->clipMask[i] = (unsigned char)(r->clipMask[i] * r->clipScratch[i] / 255);
+  }
+
+  memcpy(r->clipKey, shape->clip, sizeof r->clipKey);
+  r->maskKey = shape->mask;
+  return 1;
+}
+
+static void nsvg__rasterizeShape(NSVGrasterizer* r, NSVGshape* shape, float tx, float ty, float sx, float sy)
+{
+  NSVGcachedPaint cache;
+
+  if (shape->clip[0] != NULL || shape->mask != NULL) {
+    if (!nsvg__updateClipMask(r, shape, tx, ty, sx, sy))
+      return;
+    r->clip = r->clipMask;
+  } else {
+    r->clip = NULL;
+  }
+
+  // Images aren't drawn until their bitmap is loaded
+  if (shape->fill.type != NSVG_PAINT_NONE && (shape->fill.type != NSVG_PAINT_IMAGE || shape->fill.bitmap->pixels != NULL)) {
+    nsvg__resetPool(r);
+    r->freelist = NULL;
+    r->nedges = 0;
+
+    nsvg__flattenShape(r, shape, sx, sy);
+
+    nsvg__initPaint(&cache, &shape->fill, shape->opacity * r->layerOpacity);
+
+    nsvg__rasterizeEdges(r, tx,ty, sx,sy, &cache, shape->fillRule);
+  }
+  if (shape->stroke.type != NSVG_PAINT_NONE && (shape->strokeWidth * (sx + sy) * 0.5f) > 0.01f) {
+    nsvg__resetPool(r);
+    r->freelist = NULL;
+    r->nedges = 0;
+
+    nsvg__flattenShapeStroke(r, shape, sx, sy);
+
+//    dumpEdges(r, "edge.svg");
+
+    nsvg__initPaint(&cache, &shape->stroke, shape->opacity * r->layerOpacity);
+
+    nsvg__rasterizeEdges(r, tx,ty, sx,sy, &cache, NSVG_FILLRULE_NONZERO);
+  }
+}
+
 /*
 static void dumpEdges(NSVGrasterizer* r, const char* name)
 {
@@ -1357,19 +1770,163 @@ static void dumpEdges(NSVGrasterizer* r, const char* name)
 }
 */
 
+// Composites the innermost layer into the one below it, or the image.
+static void nsvg__popLayer(NSVGrasterizer* r, unsigned char* dst, int stride)
+{
+  unsigned char* src = r->layerImages[r->nlayers-1];
+  int* rect = r->layerRects[r->nlayers-1];
+  int a = (int)(r->layers[r->nlayers-1]->opacity * 255.0f + 0.5f);
+  int w = rect[2] - rect[0];
+  int x, y, i, sa, dx = 0, dy = 0;
+  unsigned char* s;
+  unsigned char* d;
+
+  r->nlayers--;
+  if (r->nlayers > 0) {
+    dst = r->layerImages[r->nlayers-1];
+    dx = r->layerRects[r->nlayers-1][0];
+    dy = r->layerRects[r->nlayers-1][1];
+    stride = (r->layerRects[r->nlayers-1][2] - dx)*4;
+  }
+  // A layer lies within the one below it, as it holds a subset of its shapes
+  for (y = rect[1]; y < rect[3]; y++) {
+    s = &src[(y - rect[1])*w*4];
+    d = &dst[(y - dy)*stride + (rect[0] - dx)*4];
+    for (x = 0; x < w; x++, s += 4, d += 4) {
+      if (s[3] == 0) continue;
+      // Both are premultiplied
+      sa = s[3] * a / 255;
+      for (i = 0; i < 4; i++)
+        d[i] = (unsigned char)(s[i] * a / 255 + d[i] * (255 - sa) / 255);
+    }
+  }
+}
+
+static int nsvg__inLayer(NSVGlayer* layer, NSVGlayer* group)
+{
+  for (; layer != NULL; layer = layer->parent) {
+    if (layer == group)
+      return 1;
+  }
+  return 0;
+}
+
+// Finds the pixels covered by the shapes of a layer, which are those from the
+// shape entering it up to the first one outside of it.
+static void nsvg__layerRect(NSVGrasterizer* r, NSVGlayer* layer, NSVGshape* shape, float tx, float ty, float sx, float sy, int* rect)
+{
+  float bounds[4] = { 0, 0, 0, 0 }, pad;
+  int found = 0;
+
+  for (; shape != NULL && nsvg__inLayer(shape->layer, layer); shape = shape->next) {
+    if (!(shape->flags & NSVG_FLAGS_VISIBLE))
+      continue;
+    // Miter joins reach at most miterLimit half stroke widths out, square caps less than 1.5
+    pad = 0;
+    if (shape->stroke.type != NSVG_PAINT_NONE)
+      pad = shape->strokeWidth * 0.5f * nsvg__maxf(shape->miterLimit, 1.5f);
+    if (!found) {
+      bounds[0] = shape->bounds[0] - pad;
+      bounds[1] = shape->bounds[1] - pad;
+      bounds[2] = shape->bounds[2] + pad;
+      bounds[3] = shape->bounds[3] + pad;
+      found = 1;
+    } else {
+      bounds[0] = nsvg__minf(bounds[0], shape->bounds[0] - pad);
+      bounds[1] = nsvg__minf(bounds[1], shape->bounds[1] - pad);
+      bounds[2] = nsvg__maxf(bounds[2], shape->bounds[2] + pad);
+      bounds[3] = nsvg__maxf(bounds[3], shape->bounds[3] + pad);
+    }
+  }
+
+  // Leave a pixel on each side for anti-aliasing
+  rect[0] = (int)nsvg__clampf(floorf(bounds[0]*sx + tx) - 1, 0, (float)r->width);
+  rect[1] = (int)nsvg__clampf(floorf(bounds[1]*sy + ty) - 1, 0, (float)r->height);
+  rect[2] = (int)nsvg__clampf(ceilf(bounds[2]*sx + tx) + 1, (float)rect[0], (float)r->width);
+  rect[3] = (int)nsvg__clampf(ceilf(bounds[3]*sy + ty) + 1, (float)rect[1], (float)r->height);
+  if (!found || rect[2] == rect[0] || rect[3] == rect[1]) {
+    rect[2] = rect[0];
+    rect[3] = rect[1];
+  }
+}
+
+// Switches drawing to the layers of the translucent groups containing a shape,
+// compositing the layers of groups which have ended.
+static int nsvg__enterLayers(NSVGrasterizer* r, NSVGshape* shape, unsigned char* dst, int stride, float tx, float ty, float sx, float sy)
+{
+  NSVGlayer* chain[NSVG__MAX_LAYERS];
+  NSVGlayer* layer = shape != NULL ? shape->layer : NULL;
+  NSVGlayer* l;
+  int n = 0, depth, i, size;
+  int* rect;
+
+  // Groups nested deeper than the layers available have their opacity
+  // applied to each shape instead
+  r->layerOpacity = 1.0f;
+  for (l = layer; l != NULL; l = l->parent)
+    n++;
+  for (l = layer; n > NSVG__MAX_LAYERS; l = l->parent, n--)
+    r->layerOpacity *= l->opacity;
+  depth = n;
+  for (i = depth-1; l != NULL; l = l->parent, i--)
+    chain[i] = l;
+
+  for (i = 0; i < r->nlayers && i < depth && r->layers[i] == chain[i]; i++);
+  while (r->nlayers > i)
+    nsvg__popLayer(r, dst, stride);
+
+  while (r->nlayers < depth) {
+    i = r->nlayers;
+    rect = r->layerRects[i];
+    nsvg__layerRect(r, chain[i], shape, tx, ty, sx, sy, rect);
+    size = (rect[2] - rect[0]) * (rect[3] - rect[1]) * 4;
+    if (size < 4) size = 4;
+    if (size > r->clayerImages[i]) {
+      free(r->layerImages[i]);
+      r->layerImages[i] = (unsigned char*)malloc(size);
+      if (r->layerImages[i] == NULL) {
+        r->clayerImages[i] = 0;
+        return 0;
+      }
+      r->clayerImages[i] = size;
+    }
+    memset(r->layerImages[i], 0, size);
+    r->layers[i] = chain[i];
+    r->nlayers++;
+  }
+
+  if (r->nlayers > 0) {
+    rect = r->layerRects[r->nlayers-1];
+    r->bitmap = r->layerImages[r->nlayers-1];
+    r->stride = (rect[2] - rect[0])*4;
+    nsvg__setBitmapRect(r, rect[0], rect[1], rect[2], rect[3]);
+  } else {
+    r->bitmap = dst;
+    r->stride = stride;
+    nsvg__setBitmapRect(r, 0, 0, r->width, r->height);
+  }
+  return 1;
+}
+
 void nsvgRasterize(NSVGrasterizer* r,
            NSVGimage* image, float tx, float ty, float scale,
            unsigned char* dst, int w, int h, int stride)
+{
+  nsvgRasterizeXY(r, image, tx, ty, scale, scale, dst, w, h, stride);
+}
+
+void nsvgRasterizeXY(NSVGrasterizer* r,
+           NSVGimage* image, float tx, float ty, float sx, float sy,
+           unsigned char* dst, int w, int h, int stride)
 {
   NSVGshape *shape = NULL;
-  NSVGedge *e = NULL;
-  NSVGcachedPaint cache;
-  int i;
+  int i, index;
 
   r->bitmap = dst;
   r->width = w;
   r->height = h;
   r->stride = stride;
+  nsvg__setBitmapRect(r, 0, 0, w, h);
 
   if (w > r->cscanline) {
     r->cscanline = w;
@@ -1380,63 +1937,27 @@ void nsvgRasterize(NSVGrasterizer* r,
   for (i = 0; i < h; i++)
     memset(&dst[i*stride], 0, w*4);
 
-  for (shape = image->shapes; shape != NULL; shape = shape->next) {
+  // Clip masks from an earlier image are stale
+  memset(r->clipKey, 0, sizeof r->clipKey);
+  r->maskKey = NULL;
+  r->nlayers = 0;
+  r->layerOpacity = 1.0f;
+
+  for (shape = image->shapes, index = 0; shape != NULL; shape = shape->next, index++) {
     if (!(shape->flags & NSVG_FLAGS_VISIBLE))
       continue;
+    if (r->shapeMask != NULL && (index >= r->shapeMaskCount || !r->shapeMask[index]))
+      continue;
 
-    if (shape->fill.type != NSVG_PAINT_NONE) {
-      nsvg__resetPool(r);
-      r->freelist = NULL;
-      r->nedges = 0;
-
-      nsvg__flattenShape(r, shape, scale);
-
-      // Scale and translate edges
-      for (i = 0; i < r->nedges; i++) {
-        e = &r->edges[i];
-        e->x0 = tx + e->x0;
-        e->y0 = (ty + e->y0) * NSVG__SUBSAMPLES;
-        e->x1 = tx + e->x1;
-        e->y1 = (ty + e->y1) * NSVG__SUBSAMPLES;
-      }
-
-      // Rasterize edges
-      qsort(r->edges, r->nedges, sizeof(NSVGedge), nsvg__cmpEdge);
-
-      // now, traverse the scanlines and find the intersections on each scanline, use non-zero rule
-      nsvg__initPaint(&cache, &shape->fill, shape->opacity);
-
-      nsvg__rasterizeSortedEdges(r, tx,ty,scale, &cache, shape->fillRule);
-    }
-    if (shape->stroke.type != NSVG_PAINT_NONE && (shape->strokeWidth * scale) > 0.01f) {
-      nsvg__resetPool(r);
-      r->freelist = NULL;
-      r->nedges = 0;
-
-      nsvg__flattenShapeStroke(r, shape, scale);
-
-//      dumpEdges(r, "edge.svg");
-
-      // Scale and translate edges
-      for (i = 0; i < r->nedges; i++) {
-        e = &r->edges[i];
-        e->x0 = tx + e->x0;
-        e->y0 = (ty + e->y0) * NSVG__SUBSAMPLES;
-        e->x1 = tx + e->x1;
-        e->y1 = (ty + e->y1) * NSVG__SUBSAMPLES;
-      }
-
-      // Rasterize edges
-      qsort(r->edges, r->nedges, sizeof(NSVGedge), nsvg__cmpEdge);
-
-      // now, traverse the scanlines and find the intersections on each scanline, use non-zero rule
-      nsvg__initPaint(&cache, &shape->stroke, shape->opacity);
-
-      nsvg__rasterizeSortedEdges(r, tx,ty,scale, &cache, NSVG_FILLRULE_NONZERO);
-    }
+    if (!nsvg__enterLayers(r, shape, dst, stride, tx, ty, sx, sy))
+      continue;
+    nsvg__rasterizeShape(r, shape, tx, ty, sx, sy);
   }
+  nsvg__enterLayers(r, NULL, dst, stride, tx, ty, sx, sy);
+  r->clip = NULL;
 
-  nsvg__unpremultiplyAlpha(dst, w, h, stride);
+  if (!r->premultiplied)
+    nsvg__unpremultiplyAlpha(dst, w, h, stride);
 
   r->bitmap = NULL;
   r->width = 0;
@@ -0,0 +1,113 @@
+use bindings;
+
+/**
+ * Where a viewBox is placed along one axis of its viewport, when it is scaled
+ * uniformly and doesn't fill it exactly.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Align {
+  Min,
+  Mid,
+  Max,
+}
+
+impl Align {
+  fn from_raw(raw: std::os::raw::c_char) -> Align {
+    match raw as u32 {
+      x if x == bindings::NSVGalign::NSVG_ALIGN_MIN as u32 => Align::Min,
+      x if x == bindings::NSVGalign::NSVG_ALIGN_MAX as u32 => Align::Max,
+      _ => Align::Mid,
+    }
+  }
+
+  fn offset(self, content: f32, container: f32) -> f32 {
+    match self {
+      Align::Min => 0.0,
+      Align::Mid => (container - content) * 0.5,
+      Align::Max => container - content,
+    }
+  }
+}
+
+/**
+ * How a viewBox is scaled into its viewport.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AspectMode {
+  /**
+   * Scale each axis independently so the viewBox exactly fills the viewport.
+   */
+  None,
+  /**
+   * Scale uniformly so the whole viewBox fits inside the viewport.
+   */
+  Meet,
+  /**
+   * Scale uniformly so the viewBox covers the whole viewport, cropping it.
+   */
+  Slice,
+}
+
+impl AspectMode {
+  fn from_raw(raw: std::os::raw::c_char) -> AspectMode {
+    match raw as u32 {
+      x if x == bindings::NSVGalignType::NSVG_ALIGN_NONE as u32 => AspectMode::None,
+      x if x == bindings::NSVGalignType::NSVG_ALIGN_SLICE as u32 => AspectMode::Slice,
+      _ => AspectMode::Meet,
+    }
+  }
+}
+
+/**
+ * The `preserveAspectRatio` of an `<svg>`, which decides how its viewBox is
+ * fitted into the area it is drawn in. Defaults to centering the viewBox and
+ * fitting it inside, `xMidYMid meet`.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AspectRatio {
+  /**
+   * Horizontal placement of the viewBox.
+   */
+  pub x: Align,
+  /**
+   * Vertical placement of the viewBox.
+   */
+  pub y: Align,
+  /**
+   * How the viewBox is scaled.
+   */
+  pub mode: AspectMode,
+}
+
+impl AspectRatio {
+  pub(crate) fn from_raw(x: std::os::raw::c_char, y: std::os::raw::c_char, mode: std::os::raw::c_char) -> AspectRatio {
+    AspectRatio { x: Align::from_raw(x), y: Align::from_raw(y), mode: AspectMode::from_raw(mode) }
+  }
+
+  /**
+   * The transform fitting `view_box`, as `[min_x, min_y, width, height]`, into
+   * a `width` by `height` viewport at the origin, as `[a, b, c, d, e, f]`.
+   */
+  pub fn transform(&self, view_box: [f32; 4], width: f32, height: f32) -> [f32; 6] {
+    let [min_x, min_y, view_width, view_height] = view_box;
+    if view_width <= 0.0 || view_height <= 0.0 {
+      return [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+    }
+
+    let (mut sx, mut sy) = (width / view_width, height / view_height);
+    match self.mode {
+      AspectMode::None => {},
+      AspectMode::Meet => { sx = sx.min(sy); sy = sx; },
+      AspectMode::Slice => { sx = sx.max(sy); sy = sx; },
+    }
+    let tx = self.x.offset(view_width * sx, width) - min_x * sx;
+    let ty = self.y.offset(view_height * sy, height) - min_y * sy;
+    [sx, 0.0, 0.0, sy, tx, ty]
+  }
+}
+
+impl Default for AspectRatio {
+  fn default() -> AspectRatio {
+    AspectRatio { x: Align::Mid, y: Align::Mid, mode: AspectMode::Meet }
+  }
+}
@@ -0,0 +1,365 @@
+extern crate image;
+extern crate nsvg;
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Mutex;
+use std::thread;
+
+use nsvg::{Fit, RasterizeOptions, Rasterizer, Rgba, SvgImage, Units};
+
+const USAGE: &str = "\
+Converts SVG files to PNG or raw RGBA.
+
+Usage: nsvg [OPTIONS] <INPUT>...
+
+Each input is an SVG file, or a directory whose .svg and .svgz files are all
+converted. Output files are named after their input, with its .svg or .svgz
+extension replaced by .png or .rgba.
+
+Options:
+  -o, --output <DIR>       Directory to write to, defaults to next to each input
+  -f, --format <FORMAT>    png or raw [default: png]
+  -s, --scale <SCALE>      Scale factor [default: 1]
+      --width <PIXELS>     Output width, scaling to fit
+      --height <PIXELS>    Output height, scaling to fit
+      --fit <FIT>          contain, cover or stretch, when both width and height
+                           are given [default: contain]
+      --dpi <DPI>          Dots per inch used to convert units [default: 96]
+      --units <UNITS>      px, pt, pc, mm, cm or in [default: px]
+  -b, --background <HEX>   Background color as #rrggbb or #rrggbbaa
+  -j, --jobs <COUNT>       Number of files to convert at once, defaults to the
+                           number of CPUs
+      --help               Print this message
+";
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Format {
+  Png,
+  Raw,
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+  inputs: Vec<PathBuf>,
+  output: Option<PathBuf>,
+  format: Format,
+  scale: f32,
+  width: Option<u32>,
+  height: Option<u32>,
+  fit: Fit,
+  dpi: f32,
+  units: Units,
+  background: Option<Rgba>,
+  jobs: usize,
+}
+
+impl Default for Options {
+  fn default() -> Options {
+    Options {
+      inputs: Vec::new(),
+      output: None,
+      format: Format::Png,
+      scale: 1.0,
+      width: None,
+      height: None,
+      fit: Fit::Contain,
+      dpi: 96.0,
+      units: Units::Pixel,
+      background: None,
+      jobs: thread::available_parallelism().map_or(1, |jobs| jobs.get()),
+    }
+  }
+}
+
+fn main() {
+  let options = match parse_args(env::args().skip(1)) {
+    Ok(Some(options)) => options,
+    Ok(None) => {
+      print!("{}", USAGE);
+      return;
+    },
+    Err(message) => {
+      eprintln!("error: {}\n\n{}", message, USAGE);
+      process::exit(2);
+    },
+  };
+
+  let files = match collect_files(&options.inputs) {
+    Ok(files) => files,
+    Err(message) => {
+      eprintln!("error: {}", message);
+      process::exit(1);
+    },
+  };
+
+  let outputs = match output_paths(&files, &options) {
+    Ok(outputs) => outputs,
+    Err(messages) => {
+      for message in messages {
+        eprintln!("error: {}", message);
+      }
+      process::exit(1);
+    },
+  };
+
+  if let Some(ref output) = options.output {
+    if let Err(e) = fs::create_dir_all(output) {
+      eprintln!("error: can't create {}: {}", output.display(), e);
+      process::exit(1);
+    }
+  }
+
+  if !convert_all(&files, &outputs, &options) {
+    process::exit(1);
+  }
+}
+
+/**
+ * Parses command line arguments, returning `None` when help was asked for.
+ */
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Option<Options>, String> {
+  let mut options = Options::default();
+
+  while let Some(arg) = args.next() {
+    let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+
+    match arg.as_str() {
+      "--help" => return Ok(None),
+      "-o" | "--output" => options.output = Some(PathBuf::from(value(&arg)?)),
+      "-f" | "--format" => options.format = match value(&arg)?.as_str() {
+        "png" => Format::Png,
+        "raw" => Format::Raw,
+        other => return Err(format!("unknown format '{}'", other)),
+      },
+      "-s" | "--scale" => options.scale = parse_number(&arg, &value(&arg)?)?,
+      "--width" => options.width = Some(parse_number(&arg, &value(&arg)?)?),
+      "--height" => options.height = Some(parse_number(&arg, &value(&arg)?)?),
+      "--fit" => options.fit = match value(&arg)?.as_str() {
+        "contain" => Fit::Contain,
+        "cover" => Fit::Cover,
+        "stretch" => Fit::Stretch,
+        other => return Err(format!("unknown fit '{}'", other)),
+      },
+      "--dpi" => options.dpi = parse_number(&arg, &value(&arg)?)?,
+      "--units" => options.units = match value(&arg)?.as_str() {
+        "px" => Units::Pixel,
+        "pt" => Units::Point,
+        "pc" => Units::Percent,
+        "mm" => Units::Millimeter,
+        "cm" => Units::Centimeter,
+        "in" => Units::Inch,
+        other => return Err(format!("unknown units '{}'", other)),
+      },
+      "-b" | "--background" => options.background = Some(parse_color(&value(&arg)?)?),
+      "-j" | "--jobs" => options.jobs = parse_number::<usize>(&arg, &value(&arg)?)?.max(1),
+      _ if arg.starts_with('-') && arg.len() > 1 => return Err(format!("unknown option '{}'", arg)),
+      _ => options.inputs.push(PathBuf::from(arg)),
+    }
+  }
+
+  if options.inputs.is_empty() {
+    return Err("no input files".to_string());
+  }
+
+  Ok(Some(options))
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+  value.parse().map_err(|_| format!("{} expects a number, got '{}'", name, value))
+}
+
+/**
+ * Parses a `#rrggbb` or `#rrggbbaa` color.
+ */
+fn parse_color(value: &str) -> Result<Rgba, String> {
+  let hex = value.trim_start_matches('#');
+  let channel = |i: usize| hex.get(i..i + 2).and_then(|c| u8::from_str_radix(c, 16).ok());
+
+  match (hex.len(), channel(0), channel(2), channel(4)) {
+    (6, Some(r), Some(g), Some(b)) => Ok(Rgba::new(r, g, b, 255)),
+    (8, Some(r), Some(g), Some(b)) => channel(6)
+      .map(|a| Rgba::new(r, g, b, a))
+      .ok_or_else(|| format!("invalid color '{}'", value)),
+    _ => Err(format!("invalid color '{}'", value)),
+  }
+}
+
+/**
+ * Expands directories into the SVG files they contain.
+ */
+fn collect_files(inputs: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+  let mut files = Vec::new();
+
+  for input in inputs {
+    if input.is_dir() {
+      let entries = fs::read_dir(input).map_err(|e| format!("can't read {}: {}", input.display(), e))?;
+      let mut svgs: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && is_svg(path))
+        .collect();
+      svgs.sort();
+      files.extend(svgs);
+    } else {
+      files.push(input.clone());
+    }
+  }
+
+  Ok(files)
+}
+
+fn is_svg(path: &Path) -> bool {
+  match path.extension().and_then(|extension| extension.to_str()) {
+    Some(extension) => extension.eq_ignore_ascii_case("svg") || (cfg!(feature = "svgz") && extension.eq_ignore_ascii_case("svgz")),
+    None => false,
+  }
+}
+
+/**
+ * The file each input is written to. Inputs which would overwrite each
+ * other's output, such as `a.svg` and `a.svgz`, are reported as errors.
+ */
+fn output_paths(files: &[PathBuf], options: &Options) -> Result<Vec<PathBuf>, Vec<String>> {
+  let extension = match options.format {
+    Format::Png => "png",
+    Format::Raw => "rgba",
+  };
+  let mut outputs = Vec::with_capacity(files.len());
+  let mut sources: HashMap<PathBuf, &Path> = HashMap::new();
+  let mut errors = Vec::new();
+
+  for input in files {
+    let directory = match options.output {
+      Some(ref output) => output.as_path(),
+      None => input.parent().unwrap_or_else(|| Path::new("")),
+    };
+    // Only the .svg or .svgz is replaced, so logo.dark.svg becomes logo.dark.png
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+    let output = directory.join(format!("{}.{}", stem, extension));
+
+    match sources.get(&output) {
+      Some(source) => errors.push(format!("{} and {} would both be written to {}", source.display(), input.display(), output.display())),
+      None => { sources.insert(output.clone(), input); },
+    }
+    outputs.push(output);
+  }
+
+  if errors.is_empty() { Ok(outputs) } else { Err(errors) }
+}
+
+/**
+ * Converts every file using `options.jobs` threads, each with its own
+ * rasterizer. Returns whether they all succeeded.
+ */
+fn convert_all(files: &[PathBuf], outputs: &[PathBuf], options: &Options) -> bool {
+  let queue = Mutex::new(files.iter().zip(outputs));
+  let jobs = options.jobs.min(files.len()).max(1);
+
+  thread::scope(|scope| {
+    let workers: Vec<_> = (0..jobs).map(|_| scope.spawn(|| {
+      let mut rasterizer = match Rasterizer::new() {
+        Ok(rasterizer) => rasterizer,
+        Err(e) => {
+          eprintln!("error: {}", e);
+          return false;
+        },
+      };
+      let mut ok = true;
+
+      loop {
+        let next = queue.lock().unwrap().next();
+        let (input, output) = match next {
+          Some(next) => next,
+          None => return ok,
+        };
+
+        match convert(&mut rasterizer, input, output, options) {
+          Ok(()) => println!("{} -> {}", input.display(), output.display()),
+          Err(e) => {
+            eprintln!("error: {}: {}", input.display(), e);
+            ok = false;
+          },
+        }
+      }
+    })).collect();
+
+    // Join every worker before checking, so none are left running
+    let results: Vec<bool> = workers.into_iter().map(|worker| worker.join().unwrap_or(false)).collect();
+    results.into_iter().all(|ok| ok)
+  })
+}
+
+fn convert(rasterizer: &mut Rasterizer, input: &Path, output: &Path, options: &Options) -> Result<(), String> {
+  let svg = SvgImage::parse_file(input, options.units, options.dpi).map_err(|e| e.to_string())?;
+
+  if (options.width.is_some() || options.height.is_some()) && (svg.width() <= 0.0 || svg.height() <= 0.0) {
+    return Err(format!("can't fit a document of {} by {} to a size", svg.width(), svg.height()));
+  }
+
+  let size_options = match (options.width, options.height) {
+    (Some(width), Some(height)) => RasterizeOptions::fit(&svg, width, height, options.fit),
+    (Some(width), None) => {
+      let height = (svg.height() * width as f32 / svg.width()).round() as u32;
+      RasterizeOptions::fit(&svg, width, height, Fit::Width)
+    },
+    (None, Some(height)) => {
+      let width = (svg.width() * height as f32 / svg.height()).round() as u32;
+      RasterizeOptions::fit(&svg, width, height, Fit::Height)
+    },
+    (None, None) => RasterizeOptions::with_scale(options.scale),
+  };
+  let rasterize_options = RasterizeOptions { background: options.background, ..size_options };
+
+  let (width, height, raw) = rasterizer.rasterize_to_raw_with_options(&svg, &rasterize_options).map_err(|e| e.to_string())?;
+
+  match options.format {
+    Format::Png => image::save_buffer(output, &raw, width, height, image::ColorType::RGBA(8)).map_err(|e| e.to_string()),
+    Format::Raw => fs::write(output, &raw).map_err(|e| e.to_string()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn args(args: &[&str]) -> std::vec::IntoIter<String> {
+    args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>().into_iter()
+  }
+
+  #[test]
+  fn can_parse_args() {
+    let options = parse_args(args(&["-s", "2", "--width", "64", "--units", "mm", "-b", "#ff000080", "-o", "out", "a.svg", "icons"]))
+      .unwrap()
+      .unwrap();
+
+    assert_eq!(options.scale, 2.0);
+    assert_eq!(options.width, Some(64));
+    assert_eq!(options.units, Units::Millimeter);
+    assert_eq!(options.background, Some(Rgba::new(255, 0, 0, 128)));
+    assert_eq!(options.output, Some(PathBuf::from("out")));
+    assert_eq!(options.inputs, vec![PathBuf::from("a.svg"), PathBuf::from("icons")]);
+
+    assert_eq!(parse_args(args(&["--help"])), Ok(None));
+    assert!(parse_args(args(&[])).is_err());
+    assert!(parse_args(args(&["--scale"])).is_err());
+    assert!(parse_args(args(&["--bogus", "a.svg"])).is_err());
+  }
+
+  #[test]
+  fn can_name_outputs() {
+    let files = vec![PathBuf::from("icons/logo.dark.svg"), PathBuf::from("icons/logo.light.svg")];
+    let outputs = output_paths(&files, &Options::default()).unwrap();
+    assert_eq!(outputs, vec![PathBuf::from("icons/logo.dark.png"), PathBuf::from("icons/logo.light.png")]);
+
+    let files = vec![PathBuf::from("a.svg"), PathBuf::from("a.svgz")];
+    assert_eq!(output_paths(&files, &Options::default()).unwrap_err().len(), 1);
+
+    let files = vec![PathBuf::from("light/a.svg"), PathBuf::from("dark/a.svg")];
+    let options = Options { output: Some(PathBuf::from("out")), format: Format::Raw, ..Options::default() };
+    assert!(output_paths(&files, &options).is_err());
+    assert!(output_paths(&files, &Options::default()).is_ok());
+  }
+}
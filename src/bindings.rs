@@ -31,6 +31,7 @@ pub enum NSVGpaintType {
     NSVG_PAINT_COLOR = 1,
     NSVG_PAINT_LINEAR_GRADIENT = 2,
     NSVG_PAINT_RADIAL_GRADIENT = 3,
+    NSVG_PAINT_IMAGE = 4,
 }
 #[repr(u32)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -62,6 +63,42 @@ pub enum NSVGfillRule {
 #[repr(u32)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum NSVGflags { NSVG_FLAGS_VISIBLE = 1, }
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum NSVGalign {
+    NSVG_ALIGN_MIN = 0,
+    NSVG_ALIGN_MID = 1,
+    NSVG_ALIGN_MAX = 2,
+}
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum NSVGalignType {
+    NSVG_ALIGN_NONE = 0,
+    NSVG_ALIGN_MEET = 1,
+    NSVG_ALIGN_SLICE = 2,
+}
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum NSVGtextAnchor {
+    NSVG_ANCHOR_START = 0,
+    NSVG_ANCHOR_MIDDLE = 1,
+    NSVG_ANCHOR_END = 2,
+}
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum NSVGtextFlags { NSVG_TEXT_X = 1, NSVG_TEXT_Y = 2, }
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum NSVGwarning {
+    NSVG_WARNING_UNCLOSED_TAG = 1,
+    NSVG_WARNING_MISMATCHED_END_TAG = 2,
+    NSVG_WARNING_BAD_NUMBER = 3,
+    NSVG_WARNING_UNKNOWN_UNIT = 4,
+    NSVG_WARNING_UNSUPPORTED_ELEMENT = 5,
+    NSVG_WARNING_USE_LIMIT = 6,
+    NSVG_WARNING_CLIP_LIMIT = 7,
+    NSVG_WARNING_LAYER_LIMIT = 8,
+}
 #[repr(C)]
 #[derive(Debug, Copy)]
 pub struct NSVGgradientStop {
@@ -74,14 +111,10 @@ fn bindgen_test_layout_NSVGgradientStop() {
                "Size of: " , stringify ! ( NSVGgradientStop ) ));
     assert_eq! (::std::mem::align_of::<NSVGgradientStop>() , 4usize , concat !
                 ( "Alignment of " , stringify ! ( NSVGgradientStop ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGgradientStop ) ) . color as * const _
-                as usize } , 0usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGgradientStop, color) , 0usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGgradientStop ) ,
                 "::" , stringify ! ( color ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGgradientStop ) ) . offset as * const
-                _ as usize } , 4usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGgradientStop, offset) , 4usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGgradientStop ) ,
                 "::" , stringify ! ( offset ) ));
 }
@@ -104,34 +137,22 @@ fn bindgen_test_layout_NSVGgradient() {
                "Size of: " , stringify ! ( NSVGgradient ) ));
     assert_eq! (::std::mem::align_of::<NSVGgradient>() , 4usize , concat ! (
                 "Alignment of " , stringify ! ( NSVGgradient ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGgradient ) ) . xform as * const _ as
-                usize } , 0usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGgradient, xform) , 0usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGgradient ) , "::" ,
                 stringify ! ( xform ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGgradient ) ) . spread as * const _ as
-                usize } , 24usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGgradient, spread) , 24usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGgradient ) , "::" ,
                 stringify ! ( spread ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGgradient ) ) . fx as * const _ as
-                usize } , 28usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGgradient, fx) , 28usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGgradient ) , "::" ,
                 stringify ! ( fx ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGgradient ) ) . fy as * const _ as
-                usize } , 32usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGgradient, fy) , 32usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGgradient ) , "::" ,
                 stringify ! ( fy ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGgradient ) ) . nstops as * const _ as
-                usize } , 36usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGgradient, nstops) , 36usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGgradient ) , "::" ,
                 stringify ! ( nstops ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGgradient ) ) . stops as * const _ as
-                usize } , 40usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGgradient, stops) , 40usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGgradient ) , "::" ,
                 stringify ! ( stops ) ));
 }
@@ -140,6 +161,57 @@ impl Clone for NSVGgradient {
 }
 #[repr(C)]
 #[derive(Debug, Copy)]
+pub struct NSVGbitmap {
+    pub xform: [f32; 6usize],
+    pub placement: [f32; 6usize],
+    pub viewport: [f32; 4usize],
+    pub alignX: ::std::os::raw::c_char,
+    pub alignY: ::std::os::raw::c_char,
+    pub alignType: ::std::os::raw::c_char,
+    pub href: *mut ::std::os::raw::c_char,
+    pub width: ::std::os::raw::c_int,
+    pub height: ::std::os::raw::c_int,
+    pub pixels: *mut ::std::os::raw::c_uchar,
+}
+#[test]
+fn bindgen_test_layout_NSVGbitmap() {
+    assert_eq!(::std::mem::size_of::<NSVGbitmap>() , 96usize , concat ! (
+               "Size of: " , stringify ! ( NSVGbitmap ) ));
+    assert_eq! (::std::mem::align_of::<NSVGbitmap>() , 8usize , concat ! (
+                "Alignment of " , stringify ! ( NSVGbitmap ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGbitmap, xform) , 0usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGbitmap ) , "::" ,
+                stringify ! ( xform ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGbitmap, placement) , 24usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGbitmap ) , "::" ,
+                stringify ! ( placement ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGbitmap, viewport) , 48usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGbitmap ) , "::" ,
+                stringify ! ( viewport ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGbitmap, alignX) , 64usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGbitmap ) , "::" ,
+                stringify ! ( alignX ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGbitmap, alignType) , 66usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGbitmap ) , "::" ,
+                stringify ! ( alignType ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGbitmap, href) , 72usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGbitmap ) , "::" ,
+                stringify ! ( href ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGbitmap, width) , 80usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGbitmap ) , "::" ,
+                stringify ! ( width ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGbitmap, height) , 84usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGbitmap ) , "::" ,
+                stringify ! ( height ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGbitmap, pixels) , 88usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGbitmap ) , "::" ,
+                stringify ! ( pixels ) ));
+}
+impl Clone for NSVGbitmap {
+    fn clone(&self) -> Self { *self }
+}
+#[repr(C)]
+#[derive(Debug, Copy)]
 pub struct NSVGpaint {
     pub type_: ::std::os::raw::c_char,
     pub __bindgen_anon_1: NSVGpaint__bindgen_ty_1,
@@ -149,6 +221,7 @@ pub struct NSVGpaint {
 pub struct NSVGpaint__bindgen_ty_1 {
     pub color: __BindgenUnionField<::std::os::raw::c_uint>,
     pub gradient: __BindgenUnionField<*mut NSVGgradient>,
+    pub bitmap: __BindgenUnionField<*mut NSVGbitmap>,
     pub bindgen_union_field: u64,
 }
 #[test]
@@ -159,16 +232,15 @@ fn bindgen_test_layout_NSVGpaint__bindgen_ty_1() {
     assert_eq! (::std::mem::align_of::<NSVGpaint__bindgen_ty_1>() , 8usize ,
                 concat ! (
                 "Alignment of " , stringify ! ( NSVGpaint__bindgen_ty_1 ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGpaint__bindgen_ty_1 ) ) . color as *
-                const _ as usize } , 0usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGpaint__bindgen_ty_1, color) , 0usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGpaint__bindgen_ty_1
                 ) , "::" , stringify ! ( color ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGpaint__bindgen_ty_1 ) ) . gradient as
-                * const _ as usize } , 0usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGpaint__bindgen_ty_1, gradient) , 0usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGpaint__bindgen_ty_1
                 ) , "::" , stringify ! ( gradient ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGpaint__bindgen_ty_1, bitmap) , 0usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGpaint__bindgen_ty_1
+                ) , "::" , stringify ! ( bitmap ) ));
 }
 impl Clone for NSVGpaint__bindgen_ty_1 {
     fn clone(&self) -> Self { *self }
@@ -179,9 +251,7 @@ fn bindgen_test_layout_NSVGpaint() {
                "Size of: " , stringify ! ( NSVGpaint ) ));
     assert_eq! (::std::mem::align_of::<NSVGpaint>() , 8usize , concat ! (
                 "Alignment of " , stringify ! ( NSVGpaint ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGpaint ) ) . type_ as * const _ as
-                usize } , 0usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGpaint, type_) , 0usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGpaint ) , "::" ,
                 stringify ! ( type_ ) ));
 }
@@ -203,29 +273,19 @@ fn bindgen_test_layout_NSVGpath() {
                "Size of: " , stringify ! ( NSVGpath ) ));
     assert_eq! (::std::mem::align_of::<NSVGpath>() , 8usize , concat ! (
                 "Alignment of " , stringify ! ( NSVGpath ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGpath ) ) . pts as * const _ as usize
-                } , 0usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGpath, pts) , 0usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGpath ) , "::" ,
                 stringify ! ( pts ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGpath ) ) . npts as * const _ as usize
-                } , 8usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGpath, npts) , 8usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGpath ) , "::" ,
                 stringify ! ( npts ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGpath ) ) . closed as * const _ as
-                usize } , 12usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGpath, closed) , 12usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGpath ) , "::" ,
                 stringify ! ( closed ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGpath ) ) . bounds as * const _ as
-                usize } , 16usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGpath, bounds) , 16usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGpath ) , "::" ,
                 stringify ! ( bounds ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGpath ) ) . next as * const _ as usize
-                } , 32usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGpath, next) , 32usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGpath ) , "::" ,
                 stringify ! ( next ) ));
 }
@@ -233,6 +293,32 @@ impl Clone for NSVGpath {
     fn clone(&self) -> Self { *self }
 }
 #[repr(C)]
+#[derive(Debug, Copy)]
+pub struct NSVGlayer {
+    pub opacity: f32,
+    pub parent: *mut NSVGlayer,
+    pub next: *mut NSVGlayer,
+}
+#[test]
+fn bindgen_test_layout_NSVGlayer() {
+    assert_eq!(::std::mem::size_of::<NSVGlayer>() , 24usize , concat ! (
+               "Size of: " , stringify ! ( NSVGlayer ) ));
+    assert_eq! (::std::mem::align_of::<NSVGlayer>() , 8usize , concat ! (
+                "Alignment of " , stringify ! ( NSVGlayer ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGlayer, opacity) , 0usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGlayer ) , "::" ,
+                stringify ! ( opacity ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGlayer, parent) , 8usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGlayer ) , "::" ,
+                stringify ! ( parent ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGlayer, next) , 16usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGlayer ) , "::" ,
+                stringify ! ( next ) ));
+}
+impl Clone for NSVGlayer {
+    fn clone(&self) -> Self { *self }
+}
+#[repr(C)]
 pub struct NSVGshape {
     pub id: [::std::os::raw::c_char; 64usize],
     pub fill: NSVGpaint,
@@ -249,123 +335,251 @@ pub struct NSVGshape {
     pub flags: ::std::os::raw::c_uchar,
     pub bounds: [f32; 4usize],
     pub paths: *mut NSVGpath,
+    pub groups: *mut ::std::os::raw::c_char,
+    pub clip: [*mut NSVGclipPath; 4usize],
+    pub mask: *mut NSVGmask,
+    pub layer: *mut NSVGlayer,
     pub next: *mut NSVGshape,
 }
 #[test]
 fn bindgen_test_layout_NSVGshape() {
-    assert_eq!(::std::mem::size_of::<NSVGshape>() , 184usize , concat ! (
+    assert_eq!(::std::mem::size_of::<NSVGshape>() , 240usize , concat ! (
                "Size of: " , stringify ! ( NSVGshape ) ));
     assert_eq! (::std::mem::align_of::<NSVGshape>() , 8usize , concat ! (
                 "Alignment of " , stringify ! ( NSVGshape ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGshape ) ) . id as * const _ as usize
-                } , 0usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGshape, id) , 0usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGshape ) , "::" ,
                 stringify ! ( id ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGshape ) ) . fill as * const _ as
-                usize } , 64usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGshape, fill) , 64usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGshape ) , "::" ,
                 stringify ! ( fill ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGshape ) ) . stroke as * const _ as
-                usize } , 80usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGshape, stroke) , 80usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGshape ) , "::" ,
                 stringify ! ( stroke ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGshape ) ) . opacity as * const _ as
-                usize } , 96usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGshape, opacity) , 96usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGshape ) , "::" ,
                 stringify ! ( opacity ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGshape ) ) . strokeWidth as * const _
-                as usize } , 100usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGshape, strokeWidth) , 100usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGshape ) , "::" ,
                 stringify ! ( strokeWidth ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGshape ) ) . strokeDashOffset as *
-                const _ as usize } , 104usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGshape, strokeDashOffset) , 104usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGshape ) , "::" ,
                 stringify ! ( strokeDashOffset ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGshape ) ) . strokeDashArray as *
-                const _ as usize } , 108usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGshape, strokeDashArray) , 108usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGshape ) , "::" ,
                 stringify ! ( strokeDashArray ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGshape ) ) . strokeDashCount as *
-                const _ as usize } , 140usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGshape, strokeDashCount) , 140usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGshape ) , "::" ,
                 stringify ! ( strokeDashCount ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGshape ) ) . strokeLineJoin as * const
-                _ as usize } , 141usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGshape, strokeLineJoin) , 141usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGshape ) , "::" ,
                 stringify ! ( strokeLineJoin ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGshape ) ) . strokeLineCap as * const
-                _ as usize } , 142usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGshape, strokeLineCap) , 142usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGshape ) , "::" ,
                 stringify ! ( strokeLineCap ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGshape ) ) . miterLimit as * const _
-                as usize } , 144usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGshape, miterLimit) , 144usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGshape ) , "::" ,
                 stringify ! ( miterLimit ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGshape ) ) . fillRule as * const _ as
-                usize } , 148usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGshape, fillRule) , 148usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGshape ) , "::" ,
                 stringify ! ( fillRule ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGshape ) ) . flags as * const _ as
-                usize } , 149usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGshape, flags) , 149usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGshape ) , "::" ,
                 stringify ! ( flags ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGshape ) ) . bounds as * const _ as
-                usize } , 152usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGshape, bounds) , 152usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGshape ) , "::" ,
                 stringify ! ( bounds ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGshape ) ) . paths as * const _ as
-                usize } , 168usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGshape, paths) , 168usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGshape ) , "::" ,
                 stringify ! ( paths ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGshape ) ) . next as * const _ as
-                usize } , 176usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGshape, groups) , 176usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGshape ) , "::" ,
+                stringify ! ( groups ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGshape, clip) , 184usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGshape ) , "::" ,
+                stringify ! ( clip ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGshape, mask) , 216usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGshape ) , "::" ,
+                stringify ! ( mask ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGshape, layer) , 224usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGshape ) , "::" ,
+                stringify ! ( layer ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGshape, next) , 232usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGshape ) , "::" ,
+                stringify ! ( next ) ));
+}
+#[repr(C)]
+#[derive(Debug, Copy)]
+pub struct NSVGclipPath {
+    pub id: [::std::os::raw::c_char; 64usize],
+    pub shapes: *mut NSVGshape,
+    pub next: *mut NSVGclipPath,
+}
+#[test]
+fn bindgen_test_layout_NSVGclipPath() {
+    assert_eq!(::std::mem::size_of::<NSVGclipPath>() , 80usize , concat ! (
+               "Size of: " , stringify ! ( NSVGclipPath ) ));
+    assert_eq! (::std::mem::align_of::<NSVGclipPath>() , 8usize , concat ! (
+                "Alignment of " , stringify ! ( NSVGclipPath ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGclipPath, id) , 0usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGclipPath ) , "::" ,
+                stringify ! ( id ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGclipPath, shapes) , 64usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGclipPath ) , "::" ,
+                stringify ! ( shapes ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGclipPath, next) , 72usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGclipPath ) , "::" ,
+                stringify ! ( next ) ));
+}
+impl Clone for NSVGclipPath {
+    fn clone(&self) -> Self { *self }
+}
+#[repr(C)]
+#[derive(Debug, Copy)]
+pub struct NSVGmask {
+    pub id: [::std::os::raw::c_char; 64usize],
+    pub region: *mut NSVGshape,
+    pub shapes: *mut NSVGshape,
+    pub next: *mut NSVGmask,
+}
+#[test]
+fn bindgen_test_layout_NSVGmask() {
+    assert_eq!(::std::mem::size_of::<NSVGmask>() , 88usize , concat ! (
+               "Size of: " , stringify ! ( NSVGmask ) ));
+    assert_eq! (::std::mem::align_of::<NSVGmask>() , 8usize , concat ! (
+                "Alignment of " , stringify ! ( NSVGmask ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGmask, id) , 0usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGmask ) , "::" ,
+                stringify ! ( id ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGmask, region) , 64usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGmask ) , "::" ,
+                stringify ! ( region ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGmask, shapes) , 72usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGmask ) , "::" ,
+                stringify ! ( shapes ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGmask, next) , 80usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGmask ) , "::" ,
+                stringify ! ( next ) ));
+}
+impl Clone for NSVGmask {
+    fn clone(&self) -> Self { *self }
+}
+#[repr(C)]
+#[derive(Debug, Copy)]
+pub struct NSVGtext {
+    pub xform: [f32; 6usize],
+    pub x: f32,
+    pub y: f32,
+    pub dx: f32,
+    pub dy: f32,
+    pub fontSize: f32,
+    pub fontFamily: [::std::os::raw::c_char; 64usize],
+    pub anchor: ::std::os::raw::c_char,
+    pub flags: ::std::os::raw::c_uchar,
+    pub content: *mut ::std::os::raw::c_char,
+    pub shape: *mut NSVGshape,
+    pub next: *mut NSVGtext,
+}
+#[test]
+fn bindgen_test_layout_NSVGtext() {
+    assert_eq!(::std::mem::size_of::<NSVGtext>() , 136usize , concat ! (
+               "Size of: " , stringify ! ( NSVGtext ) ));
+    assert_eq! (::std::mem::align_of::<NSVGtext>() , 8usize , concat ! (
+                "Alignment of " , stringify ! ( NSVGtext ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGtext, xform) , 0usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGtext ) , "::" ,
+                stringify ! ( xform ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGtext, x) , 24usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGtext ) , "::" ,
+                stringify ! ( x ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGtext, y) , 28usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGtext ) , "::" ,
+                stringify ! ( y ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGtext, dx) , 32usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGtext ) , "::" ,
+                stringify ! ( dx ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGtext, dy) , 36usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGtext ) , "::" ,
+                stringify ! ( dy ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGtext, fontSize) , 40usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGtext ) , "::" ,
+                stringify ! ( fontSize ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGtext, fontFamily) , 44usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGtext ) , "::" ,
+                stringify ! ( fontFamily ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGtext, anchor) , 108usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGtext ) , "::" ,
+                stringify ! ( anchor ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGtext, flags) , 109usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGtext ) , "::" ,
+                stringify ! ( flags ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGtext, content) , 112usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGtext ) , "::" ,
+                stringify ! ( content ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGtext, shape) , 120usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGtext ) , "::" ,
+                stringify ! ( shape ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGtext, next) , 128usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGtext ) , "::" ,
                 stringify ! ( next ) ));
 }
+impl Clone for NSVGtext {
+    fn clone(&self) -> Self { *self }
+}
 #[repr(C)]
 #[derive(Debug, Copy)]
 pub struct NSVGimage {
     pub width: f32,
     pub height: f32,
     pub shapes: *mut NSVGshape,
+    pub clipPaths: *mut NSVGclipPath,
+    pub masks: *mut NSVGmask,
+    pub layers: *mut NSVGlayer,
+    pub texts: *mut NSVGtext,
+    pub viewBox: [f32; 4usize],
+    pub alignX: ::std::os::raw::c_char,
+    pub alignY: ::std::os::raw::c_char,
+    pub alignType: ::std::os::raw::c_char,
 }
 #[test]
 fn bindgen_test_layout_NSVGimage() {
-    assert_eq!(::std::mem::size_of::<NSVGimage>() , 16usize , concat ! (
+    assert_eq!(::std::mem::size_of::<NSVGimage>() , 72usize , concat ! (
                "Size of: " , stringify ! ( NSVGimage ) ));
     assert_eq! (::std::mem::align_of::<NSVGimage>() , 8usize , concat ! (
                 "Alignment of " , stringify ! ( NSVGimage ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGimage ) ) . width as * const _ as
-                usize } , 0usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGimage, width) , 0usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGimage ) , "::" ,
                 stringify ! ( width ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGimage ) ) . height as * const _ as
-                usize } , 4usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGimage, height) , 4usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGimage ) , "::" ,
                 stringify ! ( height ) ));
-    assert_eq! (unsafe {
-                & ( * ( 0 as * const NSVGimage ) ) . shapes as * const _ as
-                usize } , 8usize , concat ! (
+    assert_eq! (::std::mem::offset_of!(NSVGimage, shapes) , 8usize , concat ! (
                 "Alignment of field: " , stringify ! ( NSVGimage ) , "::" ,
                 stringify ! ( shapes ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGimage, clipPaths) , 16usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGimage ) , "::" ,
+                stringify ! ( clipPaths ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGimage, masks) , 24usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGimage ) , "::" ,
+                stringify ! ( masks ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGimage, layers) , 32usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGimage ) , "::" ,
+                stringify ! ( layers ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGimage, texts) , 40usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGimage ) , "::" ,
+                stringify ! ( texts ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGimage, viewBox) , 48usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGimage ) , "::" ,
+                stringify ! ( viewBox ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGimage, alignX) , 64usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGimage ) , "::" ,
+                stringify ! ( alignX ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGimage, alignY) , 65usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGimage ) , "::" ,
+                stringify ! ( alignY ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGimage, alignType) , 66usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGimage ) , "::" ,
+                stringify ! ( alignType ) ));
 }
 impl Clone for NSVGimage {
     fn clone(&self) -> Self { *self }
@@ -380,6 +594,87 @@ extern "C" {
                      units: *const ::std::os::raw::c_char, dpi: f32)
      -> *mut NSVGimage;
 }
+pub type NSVGwarningCallback =
+    ::std::option::Option<unsafe extern "C" fn(ud: *mut ::std::os::raw::c_void,
+                                               warning: ::std::os::raw::c_int,
+                                               offset: ::std::os::raw::c_int,
+                                               line: ::std::os::raw::c_int,
+                                               column: ::std::os::raw::c_int,
+                                               element: *const ::std::os::raw::c_char,
+                                               value: *const ::std::os::raw::c_char)>;
+#[repr(C)]
+#[derive(Debug, Copy)]
+pub struct NSVGparseOptions {
+    pub warningCb: NSVGwarningCallback,
+    pub warningUd: *mut ::std::os::raw::c_void,
+    pub currentColor: ::std::os::raw::c_uint,
+    pub variableCount: ::std::os::raw::c_int,
+    pub variableNames: *mut *const ::std::os::raw::c_char,
+    pub variableValues: *mut *const ::std::os::raw::c_char,
+}
+#[test]
+fn bindgen_test_layout_NSVGparseOptions() {
+    assert_eq!(::std::mem::size_of::<NSVGparseOptions>() , 40usize , concat ! (
+               "Size of: " , stringify ! ( NSVGparseOptions ) ));
+    assert_eq! (::std::mem::align_of::<NSVGparseOptions>() , 8usize , concat ! (
+                "Alignment of " , stringify ! ( NSVGparseOptions ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGparseOptions, warningCb) , 0usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGparseOptions ) , "::" ,
+                stringify ! ( warningCb ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGparseOptions, warningUd) , 8usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGparseOptions ) , "::" ,
+                stringify ! ( warningUd ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGparseOptions, currentColor) , 16usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGparseOptions ) , "::" ,
+                stringify ! ( currentColor ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGparseOptions, variableCount) , 20usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGparseOptions ) , "::" ,
+                stringify ! ( variableCount ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGparseOptions, variableNames) , 24usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGparseOptions ) , "::" ,
+                stringify ! ( variableNames ) ));
+    assert_eq! (::std::mem::offset_of!(NSVGparseOptions, variableValues) , 32usize , concat ! (
+                "Alignment of field: " , stringify ! ( NSVGparseOptions ) , "::" ,
+                stringify ! ( variableValues ) ));
+}
+impl Clone for NSVGparseOptions {
+    fn clone(&self) -> Self { *self }
+}
+extern "C" {
+    pub fn nsvgParseWithWarnings(input: *mut ::std::os::raw::c_char,
+                                 units: *const ::std::os::raw::c_char, dpi: f32,
+                                 cb: NSVGwarningCallback,
+                                 ud: *mut ::std::os::raw::c_void)
+     -> *mut NSVGimage;
+}
+extern "C" {
+    pub fn nsvgParseWithOptions(input: *mut ::std::os::raw::c_char,
+                                units: *const ::std::os::raw::c_char, dpi: f32,
+                                options: *const NSVGparseOptions)
+     -> *mut NSVGimage;
+}
+extern "C" {
+    pub fn nsvgSetBitmapPixels(bitmap: *mut NSVGbitmap,
+                               rgba: *const ::std::os::raw::c_uchar,
+                               width: ::std::os::raw::c_int,
+                               height: ::std::os::raw::c_int)
+     -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn nsvgShareBitmapPixels(bitmap: *mut NSVGbitmap,
+                                 source: *const NSVGbitmap);
+}
+extern "C" {
+    pub fn nsvgSetTextOutline(text: *mut NSVGtext, pts: *const f32,
+                              npts: *const ::std::os::raw::c_int,
+                              closed: *const ::std::os::raw::c_char,
+                              npaths: ::std::os::raw::c_int)
+     -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn nsvgSetPaintColor(paint: *mut NSVGpaint,
+                             color: ::std::os::raw::c_uint);
+}
 extern "C" {
     pub fn nsvgDelete(image: *mut NSVGimage);
 }
@@ -398,6 +693,27 @@ extern "C" {
                          w: ::std::os::raw::c_int, h: ::std::os::raw::c_int,
                          stride: ::std::os::raw::c_int);
 }
+extern "C" {
+    pub fn nsvgRasterizeXY(r: *mut NSVGrasterizer, image: *mut NSVGimage,
+                           tx: f32, ty: f32, sx: f32, sy: f32,
+                           dst: *mut ::std::os::raw::c_uchar,
+                           w: ::std::os::raw::c_int, h: ::std::os::raw::c_int,
+                           stride: ::std::os::raw::c_int);
+}
+extern "C" {
+    pub fn nsvgSetQuality(r: *mut NSVGrasterizer,
+                          subsamples: ::std::os::raw::c_int, tessTol: f32,
+                          distTol: f32, aliased: ::std::os::raw::c_int);
+}
+extern "C" {
+    pub fn nsvgSetShapeMask(r: *mut NSVGrasterizer,
+                            mask: *const ::std::os::raw::c_uchar,
+                            count: ::std::os::raw::c_int);
+}
+extern "C" {
+    pub fn nsvgSetPremultiplied(r: *mut NSVGrasterizer,
+                                premultiplied: ::std::os::raw::c_int);
+}
 extern "C" {
     pub fn nsvgDeleteRasterizer(arg1: *mut NSVGrasterizer);
 }
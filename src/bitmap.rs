@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use image;
+use image::{ImageDecoder, ImageFormat};
+
+use bindings;
+
+/**
+ * The most pixels a bitmap is decoded with, so a small image claiming huge
+ * dimensions can't exhaust memory. Larger bitmaps aren't loaded.
+ */
+const MAX_BITMAP_PIXELS: u64 = 1 << 25;
+
+/**
+ * Loads every bitmap of `image` which isn't loaded yet, from its `data:` URI
+ * or whatever `resolve` returns for its `href`. Each `href` is decoded once,
+ * and its pixels shared by every bitmap referring to it. Returns how many
+ * were loaded.
+ */
+pub(crate) fn load_bitmaps<F>(image: *mut bindings::NSVGimage, mut resolve: F) -> usize
+  where F: FnMut(&str) -> Option<Vec<u8>>
+{
+  let bitmaps = bitmaps(image);
+  let mut decoded: HashMap<String, Option<*const bindings::NSVGbitmap>> = HashMap::new();
+  let mut loaded = 0;
+
+  // Instances of a bitmap loaded earlier share its pixels
+  for &bitmap in &bitmaps {
+    let current = unsafe { &*bitmap };
+    if !current.pixels.is_null() && !current.href.is_null() {
+      let href = unsafe { std::ffi::CStr::from_ptr(current.href) }.to_string_lossy();
+      decoded.entry(href.into_owned()).or_insert(Some(bitmap));
+    }
+  }
+
+  for bitmap in bitmaps {
+    let current = unsafe { &*bitmap };
+    if !current.pixels.is_null() || current.href.is_null() {
+      continue;
+    }
+
+    let href = unsafe { std::ffi::CStr::from_ptr(current.href) }.to_string_lossy().into_owned();
+    if let Some(&source) = decoded.get(&href) {
+      // Undecodable bitmaps are left unloaded, and so aren't drawn
+      if let Some(source) = source {
+        unsafe { bindings::nsvgShareBitmapPixels(bitmap, source) };
+        loaded += 1;
+      }
+      continue;
+    }
+
+    let source = if decode(&href, &mut resolve, bitmap) {
+      loaded += 1;
+      Some(bitmap as *const _)
+    } else {
+      None
+    };
+    decoded.insert(href, source);
+  }
+
+  loaded
+}
+
+/**
+ * Decodes the bitmap `href` refers to into `bitmap`, returning whether it was.
+ */
+fn decode<F>(href: &str, resolve: &mut F, bitmap: *mut bindings::NSVGbitmap) -> bool
+  where F: FnMut(&str) -> Option<Vec<u8>>
+{
+  let data = match decode_data_uri(href) {
+    Some(data) => data,
+    None if href.starts_with("data:") => return false,
+    None => match resolve(href) {
+      Some(data) => data,
+      None => return false,
+    },
+  };
+
+  match dimensions(&data) {
+    Some((width, height)) if width as u64 * height as u64 <= MAX_BITMAP_PIXELS => (),
+    _ => return false,
+  }
+
+  let pixels = match image::load_from_memory(&data) {
+    Ok(pixels) => pixels.to_rgba(),
+    Err(_) => return false,
+  };
+
+  let (width, height) = pixels.dimensions();
+  let raw = pixels.into_raw();
+  unsafe { bindings::nsvgSetBitmapPixels(bitmap, raw.as_ptr(), width as i32, height as i32) != 0 }
+}
+
+/**
+ * The dimensions `data` claims to have, read from its header without decoding it.
+ */
+fn dimensions(data: &[u8]) -> Option<(u32, u32)> {
+  let reader = Cursor::new(data);
+  let dimensions = match image::guess_format(data).ok()? {
+    ImageFormat::PNG => image::png::PNGDecoder::new(reader).dimensions(),
+    ImageFormat::JPEG => image::jpeg::JPEGDecoder::new(reader).dimensions(),
+    ImageFormat::GIF => image::gif::Decoder::new(reader).dimensions(),
+    ImageFormat::WEBP => image::webp::WebpDecoder::new(reader).dimensions(),
+    ImageFormat::BMP => image::bmp::BMPDecoder::new(reader).dimensions(),
+    ImageFormat::TIFF => image::tiff::TIFFDecoder::new(reader).ok()?.dimensions(),
+    ImageFormat::ICO => image::ico::ICODecoder::new(reader).ok()?.dimensions(),
+    ImageFormat::HDR => image::hdr::HDRAdapter::new(reader).ok()?.dimensions(),
+    ImageFormat::PNM => image::pnm::PNMDecoder::new(reader).ok()?.dimensions(),
+    _ => return None,
+  };
+  dimensions.ok()
+}
+
+/**
+ * The bitmaps of shapes and of the masks they are drawn through.
+ */
+fn bitmaps(image: *mut bindings::NSVGimage) -> Vec<*mut bindings::NSVGbitmap> {
+  fn collect(mut shape: *mut bindings::NSVGshape, bitmaps: &mut Vec<*mut bindings::NSVGbitmap>) {
+    while let Some(current) = unsafe { shape.as_ref() } {
+      if current.fill.type_ as u32 == bindings::NSVGpaintType::NSVG_PAINT_IMAGE as u32 {
+        bitmaps.push(unsafe { *current.fill.__bindgen_anon_1.bitmap.as_ref() });
+      }
+      shape = current.next;
+    }
+  }
+
+  let mut bitmaps = Vec::new();
+  if let Some(image) = unsafe { image.as_ref() } {
+    collect(image.shapes, &mut bitmaps);
+
+    let mut mask = image.masks;
+    while let Some(current) = unsafe { mask.as_ref() } {
+      collect(current.shapes, &mut bitmaps);
+      mask = current.next;
+    }
+  }
+  bitmaps.retain(|bitmap| !bitmap.is_null());
+  bitmaps
+}
+
+/**
+ * The data of a `data:` URI, either base64 or percent encoded.
+ */
+pub(crate) fn decode_data_uri(uri: &str) -> Option<Vec<u8>> {
+  if !uri.starts_with("data:") {
+    return None;
+  }
+  let comma = uri.find(',')?;
+  let (header, data) = (&uri[5..comma], &uri[comma + 1..]);
+
+  if header.split(';').any(|part| part.trim() == "base64") {
+    decode_base64(data)
+  } else {
+    decode_percent(data)
+  }
+}
+
+fn decode_base64(data: &str) -> Option<Vec<u8>> {
+  let mut decoded = Vec::with_capacity(data.len() / 4 * 3);
+  let mut buffer = 0u32;
+  let mut bits = 0;
+
+  // Exported documents often wrap long data over several lines
+  for byte in data.bytes().filter(|byte| !byte.is_ascii_whitespace()) {
+    let value = match byte {
+      b'A'..=b'Z' => byte - b'A',
+      b'a'..=b'z' => byte - b'a' + 26,
+      b'0'..=b'9' => byte - b'0' + 52,
+      b'+' | b'-' => 62,
+      b'/' | b'_' => 63,
+      b'=' => break,
+      _ => return None,
+    };
+    buffer = buffer << 6 | value as u32;
+    bits += 6;
+    if bits >= 8 {
+      bits -= 8;
+      decoded.push((buffer >> bits) as u8);
+    }
+  }
+
+  Some(decoded)
+}
+
+fn decode_percent(data: &str) -> Option<Vec<u8>> {
+  let bytes = data.as_bytes();
+  let mut decoded = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+
+  while i < bytes.len() {
+    if bytes[i] == b'%' {
+      let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+      decoded.push(u8::from_str_radix(hex, 16).ok()?);
+      i += 3;
+    } else {
+      decoded.push(bytes[i]);
+      i += 1;
+    }
+  }
+
+  Some(decoded)
+}
@@ -26,10 +26,23 @@ fn main() {
 #![allow(non_snake_case)]
 #![allow(non_camel_case_types)]
 #![allow(non_upper_case_globals)]
+mod aspect_ratio;
+#[allow(clippy::all)]
 mod bindings;
+#[cfg(feature = "image")]
+mod bitmap;
+mod paint;
+mod parse_options;
+mod pixel;
+mod rasterizer;
+mod shape;
+mod text;
+mod warning;
 
 #[cfg(feature = "image")]
 pub extern crate image;
+#[cfg(feature = "svgz")]
+extern crate flate2;
 #[cfg(test)]
 extern crate tempfile;
 
@@ -38,7 +51,17 @@ use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path::Path;
 
-const BYTES_PER_PIXEL: usize = 4;
+pub use aspect_ratio::{Align, AspectMode, AspectRatio};
+pub use paint::{Bitmap, Gradient, GradientStop, Paint, Rgba, Spread};
+pub use parse_options::ParseOptions;
+pub use pixel::{AlphaMode, PixelFormat};
+pub use rasterizer::{Fit, Quality, RasterizeOptions, Rasterizer};
+pub use shape::{Curves, FillRule, LineCap, LineJoin, Paths, ShapePath, Shape, ShapeMut, Shapes, ShapesMut};
+pub use text::{BoxFont, FontProvider, Outline, Text, TextAnchor, Texts};
+pub use warning::{ParseWarning, WarningReason};
+
+use parse_options::RawVariables;
+use warning::WarningCollector;
 
 #[derive(Debug)]
 pub enum Error {
@@ -47,6 +70,8 @@ pub enum Error {
   ParseError,
   MallocError,
   RasterizeError,
+  BufferSizeError,
+  SvgzError,
 }
 
 impl From<std::ffi::NulError> for Error {
@@ -62,13 +87,11 @@ impl From<std::io::Error> for Error {
 }
 
 impl std::error::Error for Error {
-  fn description(&self) -> &str {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
     match *self {
-      Error::IoError(ref e) => e.description(),
-      Error::NulError(ref e) => e.description(),
-      Error::ParseError => "An unknown parsing error",
-      Error::MallocError => "Failed to allocate memory",
-      Error::RasterizeError => "Failed to rasterize SVG",
+      Error::IoError(ref e) => Some(e),
+      Error::NulError(ref e) => Some(e),
+      _ => None,
     }
   }
 }
@@ -81,10 +104,13 @@ impl std::fmt::Display for Error {
       Error::ParseError => write!(f, "An unknown parsing error"),
       Error::MallocError => write!(f, "Failed to allocate memory"),
       Error::RasterizeError => write!(f, "Failed to rasterize SVG"),
+      Error::BufferSizeError => write!(f, "Buffer is too small for the given dimensions and stride, or too large to allocate"),
+      Error::SvgzError => write!(f, "Compressed SVG (svgz) input requires the `svgz` feature"),
     }
   }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Units {
   Pixel,
   Point,
@@ -115,30 +141,17 @@ impl SvgImage {
   /**
    * Loads SVG data from a file at the given `Path`.
    *
+   * Gzip compressed (svgz) files are decompressed when the `svgz` feature is
+   * enabled, and return `Error::SvgzError` otherwise. The same goes for
+   * `parse_bytes` and `parse_reader`.
+   *
    * # Arguments
    * - `svg_path` - Path to the SVG you want to load
    * - `units` - The length unit identifier, you probably just want `nsvg::Units::Pixel`
    * - `dpi` - Probably just want `96.0`.
    */
   pub fn parse_file(svg_path: &Path, units: Units, dpi: f32) -> Result<SvgImage, Error> {
-    let file = File::open(svg_path)?;
-    let mut buf_reader = BufReader::new(file);
-    let mut contents = Vec::new();
-    buf_reader.read_to_end(&mut contents)?;
-
-    let svg_c_string = CString::new(contents)?.into_raw();
-
-    let image = unsafe {
-      let image = bindings::nsvgParse(svg_c_string, units.as_c_str(), dpi);
-      CString::from_raw(svg_c_string);
-      image
-    };
-
-    if image.is_null() {
-      Err(Error::ParseError)
-    } else {
-      Ok(SvgImage { image })
-    }
+    SvgImage::parse(read_file(svg_path)?, units, dpi)
   }
 
   /**
@@ -150,33 +163,203 @@ impl SvgImage {
    * - `dpi` - Probably just want `96.0`.
    */
   pub fn parse_str(svg_str: &str, units: Units, dpi: f32) -> Result<SvgImage, Error> {
-    let svg_c_string = CString::new(svg_str)?.into_raw();
+    SvgImage::parse(svg_str.into(), units, dpi)
+  }
+
+  /**
+   * Loads SVG data from the given bytes. Unlike `parse_str`, the data doesn't
+   * need to be valid UTF-8.
+   *
+   * # Arguments
+   * - `svg_bytes` - Contents of the SVG you want to load
+   * - `units` - The length unit identifier, you probably just want `nsvg::Units::Pixel`
+   * - `dpi` - Probably just want `96.0`.
+   */
+  pub fn parse_bytes(svg_bytes: &[u8], units: Units, dpi: f32) -> Result<SvgImage, Error> {
+    SvgImage::parse(svg_bytes.into(), units, dpi)
+  }
+
+  /**
+   * Loads SVG data by reading `reader` to the end.
+   *
+   * # Arguments
+   * - `reader` - Source of the SVG you want to load, such as an archive entry
+   * - `units` - The length unit identifier, you probably just want `nsvg::Units::Pixel`
+   * - `dpi` - Probably just want `96.0`.
+   */
+  pub fn parse_reader<R: Read>(reader: R, units: Units, dpi: f32) -> Result<SvgImage, Error> {
+    SvgImage::parse(read_all(reader)?, units, dpi)
+  }
+
+  /**
+   * Loads SVG data from a file at the given `Path`, with more control over
+   * parsing, such as substituting `currentColor` and CSS variables.
+   *
+   * # Arguments
+   * - `svg_path` - Path to the SVG you want to load
+   * - `options` - The units, dpi and substitutions to use
+   */
+  pub fn parse_file_with_options(svg_path: &Path, options: &ParseOptions) -> Result<SvgImage, Error> {
+    SvgImage::parse_with_options(read_file(svg_path)?, options)
+  }
+
+  /**
+   * Loads SVG data from the given SVG text contents, with more control over
+   * parsing, such as substituting `currentColor` and CSS variables.
+   *
+   * # Arguments
+   * - `svg_str` - Text contents of the SVG you want to load
+   * - `options` - The units, dpi and substitutions to use
+   */
+  pub fn parse_str_with_options(svg_str: &str, options: &ParseOptions) -> Result<SvgImage, Error> {
+    SvgImage::parse_with_options(svg_str.into(), options)
+  }
+
+  /**
+   * Loads SVG data from the given bytes, with more control over parsing, such
+   * as substituting `currentColor` and CSS variables.
+   *
+   * # Arguments
+   * - `svg_bytes` - Contents of the SVG you want to load
+   * - `options` - The units, dpi and substitutions to use
+   */
+  pub fn parse_bytes_with_options(svg_bytes: &[u8], options: &ParseOptions) -> Result<SvgImage, Error> {
+    SvgImage::parse_with_options(svg_bytes.into(), options)
+  }
+
+  /**
+   * Loads SVG data by reading `reader` to the end, with more control over
+   * parsing, such as substituting `currentColor` and CSS variables.
+   *
+   * # Arguments
+   * - `reader` - Source of the SVG you want to load, such as an archive entry
+   * - `options` - The units, dpi and substitutions to use
+   */
+  pub fn parse_reader_with_options<R: Read>(reader: R, options: &ParseOptions) -> Result<SvgImage, Error> {
+    SvgImage::parse_with_options(read_all(reader)?, options)
+  }
+
+  /**
+   * Loads SVG data from a file at the given `Path` like
+   * `parse_file_with_options`, along with any problems found in the document,
+   * in the order they appear.
+   *
+   * Parsing only fails for the same reasons as `parse_file`. Treat any
+   * warnings as errors if you need to reject malformed documents.
+   *
+   * # Arguments
+   * - `svg_path` - Path to the SVG you want to load
+   * - `options` - The units, dpi and substitutions to use
+   */
+  pub fn parse_file_with_warnings(svg_path: &Path, options: &ParseOptions) -> Result<(SvgImage, Vec<ParseWarning>), Error> {
+    SvgImage::parse_with_warnings(read_file(svg_path)?, options)
+  }
+
+  /**
+   * Loads SVG data from the given SVG text contents like
+   * `parse_str_with_options`, along with any problems found in the document,
+   * in the order they appear.
+   *
+   * # Arguments
+   * - `svg_str` - Text contents of the SVG you want to load
+   * - `options` - The units, dpi and substitutions to use
+   */
+  pub fn parse_str_with_warnings(svg_str: &str, options: &ParseOptions) -> Result<(SvgImage, Vec<ParseWarning>), Error> {
+    SvgImage::parse_with_warnings(svg_str.into(), options)
+  }
+
+  /**
+   * Loads SVG data from the given bytes like `parse_bytes_with_options`,
+   * along with any problems found in the document, in the order they appear.
+   *
+   * # Arguments
+   * - `svg_bytes` - Contents of the SVG you want to load
+   * - `options` - The units, dpi and substitutions to use
+   */
+  pub fn parse_bytes_with_warnings(svg_bytes: &[u8], options: &ParseOptions) -> Result<(SvgImage, Vec<ParseWarning>), Error> {
+    SvgImage::parse_with_warnings(svg_bytes.into(), options)
+  }
+
+  /**
+   * Loads SVG data by reading `reader` to the end like
+   * `parse_reader_with_options`, along with any problems found in the
+   * document, in the order they appear.
+   *
+   * # Arguments
+   * - `reader` - Source of the SVG you want to load, such as an archive entry
+   * - `options` - The units, dpi and substitutions to use
+   */
+  pub fn parse_reader_with_warnings<R: Read>(reader: R, options: &ParseOptions) -> Result<(SvgImage, Vec<ParseWarning>), Error> {
+    SvgImage::parse_with_warnings(read_all(reader)?, options)
+  }
+
+  fn parse_with_warnings(contents: Vec<u8>, options: &ParseOptions) -> Result<(SvgImage, Vec<ParseWarning>), Error> {
+    let mut collector = WarningCollector::default();
+    let image = SvgImage::parse_decompressed(decompress(contents)?, options, Some(&mut collector))?;
+
+    Ok((image, collector.into_warnings()))
+  }
+
+  fn parse(contents: Vec<u8>, units: Units, dpi: f32) -> Result<SvgImage, Error> {
+    SvgImage::parse_with_options(contents, &ParseOptions { units, dpi, ..Default::default() })
+  }
+
+  fn parse_with_options(contents: Vec<u8>, options: &ParseOptions) -> Result<SvgImage, Error> {
+    SvgImage::parse_decompressed(decompress(contents)?, options, None)
+  }
+
+  fn parse_decompressed(contents: Vec<u8>, options: &ParseOptions, collector: Option<&mut WarningCollector>) -> Result<SvgImage, Error> {
+    let mut variables = RawVariables::new(&options.variables)?;
+    let svg_c_string = CString::new(contents)?.into_raw();
+
+    let mut raw_options = bindings::NSVGparseOptions {
+      warningCb: None,
+      warningUd: std::ptr::null_mut(),
+      currentColor: options.current_color.to_packed(),
+      variableCount: 0,
+      variableNames: std::ptr::null_mut(),
+      variableValues: std::ptr::null_mut(),
+    };
+    variables.apply(&mut raw_options);
+
+    if let Some(collector) = collector {
+      raw_options.warningCb = Some(warning::collect_warning);
+      raw_options.warningUd = collector as *mut WarningCollector as *mut std::os::raw::c_void;
+    }
 
     let image = unsafe {
-      let image = bindings::nsvgParse(svg_c_string, units.as_c_str(), dpi);
-      CString::from_raw(svg_c_string);
+      let image = bindings::nsvgParseWithOptions(svg_c_string, options.units.as_c_str(), options.dpi, &raw_options);
+      drop(CString::from_raw(svg_c_string));
       image
     };
 
     if image.is_null() {
       Err(Error::ParseError)
     } else {
-      Ok(SvgImage { image })
+      let mut svg = SvgImage { image };
+      svg.load_data_images();
+      Ok(svg)
     }
   }
 
+  #[cfg(feature = "image")]
+  fn load_data_images(&mut self) {
+    bitmap::load_bitmaps(self.image, |_| None);
+  }
+
+  #[cfg(not(feature = "image"))]
+  fn load_data_images(&mut self) {}
+
   /**
    * Turns the loaded SVG into an RgbaImage bitmap
    *
    * # Argument
    * - `scale` - The factor the vector will be scaled by when rasterizing.
-   * 1.0 is the original size.
+   *   1.0 is the original size.
    */
   #[cfg(feature = "image")]
   pub fn rasterize(&self, scale: f32) -> Result<image::RgbaImage, Error> {
-    let rasterizer = SVGRasterizer::new()?;
-
-    rasterizer.rasterize(self, scale)
+    Rasterizer::new()?.rasterize(self, scale)
   }
 
   /**
@@ -184,12 +367,98 @@ impl SvgImage {
    *
    * # Argument
    * - `scale` - The factor the vector will be scaled by when rasterizing.
-   * 1.0 is the original size.
+   *   1.0 is the original size.
    */
   pub fn rasterize_to_raw_rgba(&self, scale: f32) -> Result<(u32, u32, Vec<u8>), Error> {
-    let rasterizer = SVGRasterizer::new()?;
+    Rasterizer::new()?.rasterize_to_raw_rgba(self, scale)
+  }
+
+  /**
+   * Turns the loaded SVG into an RgbaImage bitmap, with control over the area
+   * rendered and the size of the output.
+   *
+   * # Argument
+   * - `options` - The scale, offset and output size to use
+   */
+  #[cfg(feature = "image")]
+  pub fn rasterize_with_options(&self, options: &RasterizeOptions) -> Result<image::RgbaImage, Error> {
+    Rasterizer::new()?.rasterize_with_options(self, options)
+  }
+
+  /**
+   * Turns the loaded SVG into an RgbaImage bitmap of exactly `width` by `height`
+   * pixels, scaled and centered according to `fit`.
+   *
+   * Without the `image` feature, use `RasterizeOptions::fit` with
+   * `rasterize_to_raw_with_options` instead.
+   *
+   * # Arguments
+   * - `width` - Width of the output in pixels
+   * - `height` - Height of the output in pixels
+   * - `fit` - How the document is scaled to fit the output
+   */
+  #[cfg(feature = "image")]
+  pub fn rasterize_to_size(&self, width: u32, height: u32, fit: Fit) -> Result<image::RgbaImage, Error> {
+    Rasterizer::new()?.rasterize_to_size(self, width, height, fit)
+  }
+
+  /**
+   * Turns the loaded SVG into raw array data, along with width and height
+   * information, with control over the area rendered, the size of the output
+   * and its pixel format.
+   *
+   * # Argument
+   * - `options` - The scale, offset, output size, pixel format and background to use
+   */
+  pub fn rasterize_to_raw_with_options(&self, options: &RasterizeOptions) -> Result<(u32, u32, Vec<u8>), Error> {
+    Rasterizer::new()?.rasterize_to_raw_with_options(self, options)
+  }
+
+  /**
+   * Turns only the shapes that `filter` accepts into an RgbaImage bitmap, such
+   * as to pull a single sprite out of a sprite sheet, or leave out a layer.
+   *
+   * ```no_run
+   * # extern crate nsvg;
+   * # fn main() {
+   * let svg = nsvg::parse_file("icons.svg".as_ref(), nsvg::Units::Pixel, 96.0).unwrap();
+   * let options = nsvg::RasterizeOptions::default();
+   * let without_shadow = svg.rasterize_shapes(&options, |shape| !shape.has_id("shadow")).unwrap();
+   * # }
+   * ```
+   *
+   * # Arguments
+   * - `options` - The scale, offset, output size and background to use
+   * - `filter` - Called with each shape, returning whether it should be drawn
+   */
+  #[cfg(feature = "image")]
+  pub fn rasterize_shapes<F: FnMut(&Shape) -> bool>(&self, options: &RasterizeOptions, filter: F) -> Result<image::RgbaImage, Error> {
+    Rasterizer::new()?.rasterize_shapes_with_options(self, options, filter)
+  }
+
+  /**
+   * Turns only the shapes with one of the given `id`s, or inside a group with
+   * one of them, into an RgbaImage bitmap.
+   *
+   * # Arguments
+   * - `options` - The scale, offset, output size and background to use
+   * - `ids` - The `id`s of the shapes and groups to draw
+   */
+  #[cfg(feature = "image")]
+  pub fn rasterize_ids(&self, options: &RasterizeOptions, ids: &[&str]) -> Result<image::RgbaImage, Error> {
+    self.rasterize_shapes(options, |shape| ids.iter().any(|id| shape.has_id(id)))
+  }
 
-    rasterizer.rasterize_to_raw_rgba(self, scale)
+  /**
+   * Turns only the shapes that `filter` accepts into raw array data, along
+   * with width and height information.
+   *
+   * # Arguments
+   * - `options` - The scale, offset, output size, pixel format and background to use
+   * - `filter` - Called with each shape, returning whether it should be drawn
+   */
+  pub fn rasterize_shapes_to_raw<F: FnMut(&Shape) -> bool>(&self, options: &RasterizeOptions, filter: F) -> Result<(u32, u32, Vec<u8>), Error> {
+    Rasterizer::new()?.rasterize_shapes_to_raw_with_options(self, options, filter)
   }
 
   /**
@@ -213,6 +482,171 @@ impl SvgImage {
       unsafe { (*self.image).height }
     }
   }
+
+  /**
+   * The area of the document's user space fitted into its `width()` by
+   * `height()`, as `[min_x, min_y, width, height]`. When the document has no
+   * `viewBox`, this is its size or the bounds of its shapes.
+   */
+  pub fn view_box(&self) -> [f32; 4] {
+    if self.image.is_null() {
+      panic!("NSVGimage pointer is unexpectedly null!");
+    } else {
+      unsafe { (*self.image).viewBox }
+    }
+  }
+
+  /**
+   * How the `view_box()` is fitted into the `width()` by `height()` of the
+   * document, from its `preserveAspectRatio`.
+   */
+  pub fn aspect_ratio(&self) -> AspectRatio {
+    if self.image.is_null() {
+      panic!("NSVGimage pointer is unexpectedly null!");
+    } else {
+      let image = unsafe { &*self.image };
+      AspectRatio::from_raw(image.alignX, image.alignY, image.alignType)
+    }
+  }
+
+  /**
+   * Iterates over the shapes of the SVG document, in the order they are drawn.
+   */
+  pub fn shapes(&self) -> Shapes<'_> {
+    if self.image.is_null() {
+      panic!("NSVGimage pointer is unexpectedly null!");
+    } else {
+      Shapes::new(unsafe { (*self.image).shapes })
+    }
+  }
+
+  /**
+   * Iterates over the shapes of the SVG document, in the order they are drawn,
+   * allowing them to be restyled or hidden.
+   */
+  pub fn shapes_mut(&mut self) -> ShapesMut<'_> {
+    if self.image.is_null() {
+      panic!("NSVGimage pointer is unexpectedly null!");
+    } else {
+      ShapesMut::new(unsafe { (*self.image).shapes })
+    }
+  }
+
+  /**
+   * The first shape with the given `id`, allowing it to be restyled or hidden.
+   * Shapes without an `id` of their own take their group's, use `shapes_mut`
+   * and `Shape::has_id` to change every shape in a group.
+   *
+   * ```no_run
+   * # extern crate nsvg;
+   * # fn main() {
+   * let mut svg = nsvg::parse_file("icon.svg".as_ref(), nsvg::Units::Pixel, 96.0).unwrap();
+   *
+   * if let Some(mut badge) = svg.shape_mut("badge") {
+   *   badge.set_fill_color(nsvg::Rgba::new(255, 255, 255, 255));
+   * }
+   * # }
+   * ```
+   */
+  pub fn shape_mut(&mut self, id: &str) -> Option<ShapeMut<'_>> {
+    self.shapes_mut().find(|shape| shape.shape().id() == id)
+  }
+
+  /**
+   * Loads the `<image>` bitmaps which aren't embedded as `data:` URIs, those
+   * are loaded while parsing. `resolve` is called with each `href` and returns
+   * the PNG or JPEG data it refers to, if any. Bitmaps which can't be resolved
+   * or decoded aren't drawn, nor are those of more than 32 megapixels. Each
+   * `href` is decoded once, and its pixels shared by every `<use>` of it.
+   * Returns how many bitmaps were loaded.
+   *
+   * ```no_run
+   * # extern crate nsvg;
+   * # fn main() {
+   * let path = std::path::Path::new("drawings/photo.svg");
+   * let mut svg = nsvg::parse_file(path, nsvg::Units::Pixel, 96.0).unwrap();
+   *
+   * svg.load_images(|href| std::fs::read(path.with_file_name(href)).ok());
+   * # }
+   * ```
+   */
+  #[cfg(feature = "image")]
+  pub fn load_images<F: FnMut(&str) -> Option<Vec<u8>>>(&mut self, resolve: F) -> usize {
+    if self.image.is_null() {
+      panic!("NSVGimage pointer is unexpectedly null!");
+    }
+    bitmap::load_bitmaps(self.image, resolve)
+  }
+
+  /**
+   * Iterates over the runs of text of the SVG document, in document order.
+   */
+  pub fn texts(&self) -> Texts<'_> {
+    if self.image.is_null() {
+      panic!("NSVGimage pointer is unexpectedly null!");
+    } else {
+      Texts::new(unsafe { (*self.image).texts })
+    }
+  }
+
+  /**
+   * Outlines the text of the SVG document with `provider`, after which it is
+   * drawn like any other shape. Text isn't drawn until then, as nsvg doesn't
+   * load fonts itself. Outlining again replaces the previous outlines. Returns
+   * how many runs of text `provider` outlined.
+   *
+   * ```
+   * # extern crate nsvg;
+   * # fn main() {
+   * let mut svg = nsvg::parse_str(r#"
+   *   <svg width="100" height="20"><text y="16">Hello</text></svg>
+   * "#, nsvg::Units::Pixel, 96.0).unwrap();
+   *
+   * assert_eq!(svg.outline_texts(&mut nsvg::BoxFont), 1);
+   * # }
+   * ```
+   */
+  pub fn outline_texts<P: FontProvider + ?Sized>(&mut self, provider: &mut P) -> usize {
+    if self.image.is_null() {
+      panic!("NSVGimage pointer is unexpectedly null!");
+    }
+    text::outline_texts(unsafe { (*self.image).texts }, provider)
+  }
+}
+
+fn read_file(svg_path: &Path) -> Result<Vec<u8>, Error> {
+  let file = File::open(svg_path)?;
+  read_all(BufReader::new(file))
+}
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/**
+ * Inflates gzip compressed (svgz) contents, passing anything else through.
+ */
+#[cfg(feature = "svgz")]
+fn decompress(contents: Vec<u8>) -> Result<Vec<u8>, Error> {
+  if contents.starts_with(&GZIP_MAGIC) {
+    read_all(flate2::read::MultiGzDecoder::new(&contents[..]))
+  } else {
+    Ok(contents)
+  }
+}
+
+#[cfg(not(feature = "svgz"))]
+fn decompress(contents: Vec<u8>) -> Result<Vec<u8>, Error> {
+  if contents.starts_with(&GZIP_MAGIC) {
+    Err(Error::SvgzError)
+  } else {
+    Ok(contents)
+  }
+}
+
+fn read_all<R: Read>(mut reader: R) -> Result<Vec<u8>, Error> {
+  let mut contents = Vec::new();
+  reader.read_to_end(&mut contents)?;
+
+  Ok(contents)
 }
 
 impl Drop for SvgImage {
@@ -248,71 +682,39 @@ pub fn parse_str(svg_str: &str, units: Units, dpi: f32) -> Result<SvgImage, Erro
   SvgImage::parse_str(svg_str, units, dpi)
 }
 
-struct SVGRasterizer {
-  rasterizer: *mut bindings::NSVGrasterizer
-}
-
-impl SVGRasterizer {
-  fn new() -> Result<SVGRasterizer, Error> {
-    let rasterizer = unsafe { bindings::nsvgCreateRasterizer() };
-
-    if rasterizer.is_null() {
-      Err(Error::MallocError)
-    } else {
-      Ok(SVGRasterizer { rasterizer })
-    }
-  }
-
-  #[cfg(feature = "image")]
-  fn rasterize(&self, image: &SvgImage, scale: f32) -> Result<image::RgbaImage, Error> {
-    let (width, height, raw) = self.rasterize_to_raw_rgba(image, scale)?;
-
-    image::RgbaImage::from_raw(width, height, raw)
-      .ok_or(Error::RasterizeError)
-  }
-
-  fn rasterize_to_raw_rgba(&self, image: &SvgImage, scale: f32) -> Result<(u32, u32, Vec<u8>), Error> {
-    let width = (image.width() * scale) as usize;
-    let height = (image.height() * scale) as usize;
-    let capacity = BYTES_PER_PIXEL * width * height;
-    let mut dst = Vec::with_capacity(capacity);
-    let stride = width * BYTES_PER_PIXEL;
-
-    unsafe {
-      bindings::nsvgRasterize(      // Rasterizes SVG image, returns RGBA image (non-premultiplied alpha)
-        self.rasterizer,  //   rasterizer - pointer to rasterizer context
-        image.image,      //   image - pointer to image to rasterize
-        0.0, 0.0,         //   tx,ty - image offset (applied after scaling)
-        scale,            //   scale - image scale
-        dst.as_mut_ptr(), //   dst - pointer to destination image data, 4 bytes per pixel (RGBA)
-        width as i32,     //   w - width of the image to render
-        height as i32,    //   h - height of the image to render
-        stride as i32     //   stride - number of bytes per scaleline in the destination buffer
-      );
-
-      // Need to manually set the length of the vector to match the data that's been put in it
-      dst.set_len(capacity);
-    }
-
-    Ok((width as u32, height as u32, dst))
-  }
+/**
+ * Loads SVG data from the given bytes, which don't need to be valid UTF-8.
+ *
+ * # Arguments
+ * - `svg_bytes` - Contents of the SVG you want to load
+ * - `units` - The length unit identifier, you probably just want `nsvg::Units::Pixel`
+ * - `dpi` - Probably just want `96.0`.
+ */
+pub fn parse_bytes(svg_bytes: &[u8], units: Units, dpi: f32) -> Result<SvgImage, Error> {
+  SvgImage::parse_bytes(svg_bytes, units, dpi)
 }
 
-impl Drop for SVGRasterizer {
-  fn drop(&mut self) {
-    if !self.rasterizer.is_null() {
-      unsafe { bindings::nsvgDeleteRasterizer(self.rasterizer) };
-      self.rasterizer = std::ptr::null_mut();
-    }
-  }
+/**
+ * Loads SVG data by reading `reader` to the end.
+ *
+ * # Arguments
+ * - `reader` - Source of the SVG you want to load, such as an archive entry
+ * - `units` - The length unit identifier, you probably just want `nsvg::Units::Pixel`
+ * - `dpi` - Probably just want `96.0`.
+ */
+pub fn parse_reader<R: Read>(reader: R, units: Units, dpi: f32) -> Result<SvgImage, Error> {
+  SvgImage::parse_reader(reader, units, dpi)
 }
 
 #[cfg(test)]
 mod tests {
+  #![allow(clippy::match_like_matches_macro)]
+
   use super::*;
 
   use std::fs::copy;
   use std::io::Write;
+  use std::path::Path;
   use tempfile::{NamedTempFile, tempdir};
 
   #[test]
@@ -331,6 +733,77 @@ mod tests {
     assert_eq!(svg.height(), 256.0);
   }
 
+  #[test]
+  fn can_parse_bytes() {
+    // "café" in Latin-1, which isn't valid UTF-8
+    let mut latin1 = b"<svg width=\"10\" height=\"20\"><title>caf".to_vec();
+    latin1.extend_from_slice(&[0xe9]);
+    latin1.extend_from_slice(b"</title><rect width=\"10\" height=\"20\" /></svg>");
+
+    let svg = SvgImage::parse_bytes(&latin1, Units::Pixel, 96.0).unwrap();
+
+    assert_eq!(svg.width(), 10.0);
+    assert_eq!(svg.height(), 20.0);
+    assert_eq!(svg.shapes().count(), 1);
+  }
+
+  #[test]
+  fn can_parse_reader() {
+    let file = File::open(Path::new("examples/example.svg")).unwrap();
+    let svg = SvgImage::parse_reader(file, Units::Pixel, 96.0).unwrap();
+
+    assert_eq!(svg.width(), 256.0);
+    assert_eq!(svg.height(), 256.0);
+
+    let svg = parse_reader(&b"<svg width=\"3\" height=\"4\"></svg>"[..], Units::Pixel, 96.0).unwrap();
+    assert_eq!((svg.width(), svg.height()), (3.0, 4.0));
+  }
+
+  #[test]
+  fn error_when_parsing_bytes_containing_nul() {
+    let svg = SvgImage::parse_bytes(b"<svg>\0</svg>", Units::Pixel, 96.0);
+
+    assert!(matches!(svg, Err(Error::NulError(_))));
+  }
+
+  #[test]
+  #[cfg(feature = "svgz")]
+  fn can_parse_svgz() {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(include_bytes!("../examples/example.svg")).unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    let svg = SvgImage::parse_bytes(&compressed, Units::Pixel, 96.0).unwrap();
+    assert_eq!((svg.width(), svg.height()), (256.0, 256.0));
+
+    let svg = SvgImage::parse_reader(&compressed[..], Units::Pixel, 96.0).unwrap();
+    assert_eq!((svg.width(), svg.height()), (256.0, 256.0));
+
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(&compressed).unwrap();
+    let (svg, _) = SvgImage::parse_file_with_warnings(file.path(), &ParseOptions::default()).unwrap();
+    assert_eq!((svg.width(), svg.height()), (256.0, 256.0));
+  }
+
+  #[test]
+  #[cfg(feature = "svgz")]
+  fn error_when_parsing_corrupt_svgz() {
+    let svg = SvgImage::parse_bytes(&[0x1f, 0x8b, 0x08, 0x00, 0xff], Units::Pixel, 96.0);
+
+    assert!(matches!(svg, Err(Error::IoError(_))));
+  }
+
+  #[test]
+  #[cfg(not(feature = "svgz"))]
+  fn error_when_parsing_svgz_without_the_feature() {
+    let svg = SvgImage::parse_bytes(&[0x1f, 0x8b, 0x08, 0x00, 0x00], Units::Pixel, 96.0);
+
+    assert!(matches!(svg, Err(Error::SvgzError)));
+  }
+
   #[test]
   fn can_parse_file_at_non_ascii_path() {
     let dir = tempdir().unwrap();
@@ -370,6 +843,232 @@ mod tests {
     assert!(is_parse_error);
   }
 
+  #[test]
+  fn well_formed_documents_have_no_warnings() {
+    let (svg, warnings) = SvgImage::parse_str_with_warnings(QUADRANTS, &ParseOptions::default()).unwrap();
+
+    assert_eq!(svg.shapes().count(), 4);
+    assert_eq!(warnings, vec![]);
+  }
+
+  #[test]
+  fn can_report_parse_warnings() {
+    let (svg, warnings) = SvgImage::parse_str_with_warnings(
+      "<svg width=\"100\" height=\"100\">\n  <rect width=\"ten\" height=\"10furlongs\"/>\n  <g>\n    <!-- é --><blink></blink><circle r=\"2\"/>\n  </svg>\n  </defs>\n",
+      &ParseOptions::default()
+    ).unwrap();
+
+    let summary: Vec<_> = warnings.iter()
+      .map(|warning| (warning.reason.clone(), warning.element.as_str(), warning.line, warning.column))
+      .collect();
+
+    assert_eq!(summary, vec![
+      (WarningReason::BadNumber("ten".into()), "rect", 2, 3),
+      (WarningReason::UnknownUnit("furlongs".into()), "rect", 2, 3),
+      (WarningReason::UnclosedTag, "g", 3, 3),
+      (WarningReason::UnsupportedElement, "blink", 4, 15),
+      (WarningReason::MismatchedEndTag, "defs", 6, 3),
+    ]);
+    assert_eq!(warnings[0].offset, 33);
+    assert_eq!(warnings[0].to_string(), "line 2, column 3: \"ten\" in <rect> is not a number");
+
+    // The rest of the document is still parsed
+    assert_eq!(svg.shapes().count(), 1);
+  }
+
+  #[test]
+  fn can_report_a_truncated_document() {
+    let (_, warnings) = SvgImage::parse_str_with_warnings("<svg>\n<rect width=\"1", &ParseOptions::default()).unwrap();
+
+    let unclosed: Vec<_> = warnings.iter()
+      .map(|warning| (warning.reason.clone(), warning.element.as_str(), warning.line))
+      .collect();
+
+    assert_eq!(unclosed, vec![
+      (WarningReason::UnclosedTag, "svg", 1),
+      (WarningReason::UnclosedTag, "rect", 2),
+    ]);
+  }
+
+  #[test]
+  fn can_report_warnings_from_any_source() {
+    let source = "<svg>\n<rect width=\"ten\"/>\n</svg>";
+    let options = ParseOptions::default();
+    let reasons = |warnings: Vec<ParseWarning>| -> Vec<_> {
+      warnings.into_iter().map(|warning| (warning.reason, warning.line)).collect()
+    };
+    let expected = vec![(WarningReason::BadNumber("ten".into()), 2)];
+
+    let (_, warnings) = SvgImage::parse_str_with_warnings(source, &options).unwrap();
+    assert_eq!(reasons(warnings), expected);
+    let (_, warnings) = SvgImage::parse_bytes_with_warnings(source.as_bytes(), &options).unwrap();
+    assert_eq!(reasons(warnings), expected);
+    let (_, warnings) = SvgImage::parse_reader_with_warnings(source.as_bytes(), &options).unwrap();
+    assert_eq!(reasons(warnings), expected);
+  }
+
+  #[test]
+  fn can_inspect_shapes() {
+    let svg = SvgImage::parse_str(r#"
+      <svg width="100" height="100">
+        <g id="group" display="none">
+          <rect x="10" y="20" width="30" height="40"
+            stroke-width="4" stroke-dasharray="2 3" stroke-dashoffset="1"
+            stroke-linejoin="round" stroke-linecap="square" stroke-miterlimit="8"
+            fill-rule="evenodd" opacity="0.5" />
+        </g>
+        <path id="line" d="M 0 0 L 10 10" />
+      </svg>
+    "#, Units::Pixel, 96.0).unwrap();
+
+    let shapes: Vec<Shape> = svg.shapes().collect();
+    assert_eq!(shapes.len(), 2);
+
+    let rect = shapes[0];
+    assert_eq!(rect.id(), "group");
+    assert_eq!(rect.opacity(), 0.5);
+    assert_eq!(rect.stroke_width(), 4.0);
+    assert_eq!(rect.stroke_dash_array(), &[2.0, 3.0]);
+    assert_eq!(rect.stroke_dash_offset(), 1.0);
+    assert_eq!(rect.stroke_line_join(), LineJoin::Round);
+    assert_eq!(rect.stroke_line_cap(), LineCap::Square);
+    assert_eq!(rect.miter_limit(), 8.0);
+    assert_eq!(rect.fill_rule(), FillRule::EvenOdd);
+    assert!(!rect.is_visible());
+    assert_eq!(rect.bounds(), [10.0, 20.0, 40.0, 60.0]);
+
+    let line = shapes[1];
+    assert_eq!(line.id(), "line");
+    assert!(line.is_visible());
+    assert!(line.stroke_dash_array().is_empty());
+    assert_eq!(line.stroke_line_join(), LineJoin::Miter);
+    assert_eq!(line.stroke_line_cap(), LineCap::Butt);
+    assert_eq!(line.fill_rule(), FillRule::NonZero);
+  }
+
+  #[test]
+  fn can_inspect_paths() {
+    let svg = SvgImage::parse_str(r#"
+      <svg width="100" height="100">
+        <path d="M 0 0 L 30 0 L 30 30 Z M 50 50 C 60 50 70 60 70 70" />
+      </svg>
+    "#, Units::Pixel, 96.0).unwrap();
+
+    let shape = svg.shapes().next().unwrap();
+    let paths: Vec<_> = shape.paths().collect();
+    assert_eq!(paths.len(), 2);
+
+    let closed = paths.iter().find(|path| path.is_closed()).unwrap();
+    assert_eq!(closed.bounds(), [0.0, 0.0, 30.0, 30.0]);
+    assert_eq!(closed.points().len(), 10);
+    assert_eq!(closed.curves().count(), 3);
+
+    let open = paths.iter().find(|path| !path.is_closed()).unwrap();
+    assert_eq!(open.points(), &[[50.0, 50.0], [60.0, 50.0], [70.0, 60.0], [70.0, 70.0]]);
+    assert_eq!(open.curves().collect::<Vec<_>>(), vec![[[50.0, 50.0], [60.0, 50.0], [70.0, 60.0], [70.0, 70.0]]]);
+  }
+
+  #[test]
+  fn can_inspect_solid_paints() {
+    let svg = SvgImage::parse_str(r##"
+      <svg width="100" height="100">
+        <rect width="10" height="10" fill="#ff8000" fill-opacity="0.5" stroke="none" />
+        <rect width="10" height="10" fill="none" stroke="blue" />
+      </svg>
+    "##, Units::Pixel, 96.0).unwrap();
+
+    let shapes: Vec<Shape> = svg.shapes().collect();
+
+    match shapes[0].fill() {
+      Paint::Color(color) => assert_eq!(color, Rgba::new(255, 128, 0, 127)),
+      paint => panic!("expected a color, got {:?}", paint),
+    }
+    assert!(matches!(shapes[0].stroke(), Paint::None));
+
+    assert!(matches!(shapes[1].fill(), Paint::None));
+    match shapes[1].stroke() {
+      Paint::Color(color) => assert_eq!(color, Rgba::new(0, 0, 255, 255)),
+      paint => panic!("expected a color, got {:?}", paint),
+    }
+  }
+
+  #[test]
+  fn can_inspect_gradient_paints() {
+    let svg = SvgImage::parse_str(r##"
+      <svg width="100" height="100">
+        <defs>
+          <linearGradient id="linear" spreadMethod="reflect">
+            <stop offset="1" stop-color="#0000ff" />
+            <stop offset="0" stop-color="#ff0000" stop-opacity="0.5" />
+          </linearGradient>
+          <radialGradient id="radial" spreadMethod="repeat" fx="50%" fy="50%">
+            <stop offset="0" stop-color="white" />
+            <stop offset="0.5" stop-color="black" />
+            <stop offset="1" stop-color="white" />
+          </radialGradient>
+        </defs>
+        <rect width="100" height="100" fill="url(#linear)" stroke="url(#radial)" />
+      </svg>
+    "##, Units::Pixel, 96.0).unwrap();
+
+    let shape = svg.shapes().next().unwrap();
+
+    match shape.fill() {
+      Paint::LinearGradient(gradient) => {
+        assert_eq!(gradient.spread(), Spread::Reflect);
+        let stops = gradient.stops();
+        assert_eq!(stops.len(), 2);
+        assert_eq!(stops[0].offset(), 0.0);
+        assert_eq!(stops[0].color(), Rgba::new(255, 0, 0, 127));
+        assert_eq!(stops[1].offset(), 1.0);
+        assert_eq!(stops[1].color(), Rgba::new(0, 0, 255, 255));
+
+        // The left edge maps to the first stop, the right edge to the last
+        let t = gradient.xform();
+        assert!((0.0 * t[1] + 50.0 * t[3] + t[5]).abs() < 1e-4);
+        assert!((100.0 * t[1] + 50.0 * t[3] + t[5] - 1.0).abs() < 1e-4);
+      },
+      paint => panic!("expected a linear gradient, got {:?}", paint),
+    }
+
+    match shape.stroke() {
+      Paint::RadialGradient(gradient) => {
+        assert_eq!(gradient.spread(), Spread::Repeat);
+        assert_eq!(gradient.stops().len(), 3);
+        assert_eq!(gradient.stops()[1].color(), Rgba::new(0, 0, 0, 255));
+        assert_eq!(gradient.fx(), 1.0);
+        assert_eq!(gradient.fy(), 1.0);
+      },
+      paint => panic!("expected a radial gradient, got {:?}", paint),
+    }
+  }
+
+  #[test]
+  fn can_read_every_gradient_stop() {
+    let svg = SvgImage::parse_str(r##"
+      <svg width="100" height="100">
+        <linearGradient id="three">
+          <stop offset="0" stop-color="#ff0000" />
+          <stop offset="0.25" stop-color="#00ff00" />
+          <stop offset="1" stop-color="#0000ff" stop-opacity="0" />
+        </linearGradient>
+        <rect width="100" height="100" fill="url(#three)" />
+      </svg>
+    "##, Units::Pixel, 96.0).unwrap();
+
+    match svg.shapes().next().unwrap().fill() {
+      Paint::LinearGradient(gradient) => {
+        let stops: Vec<_> = gradient.stops().iter().map(|stop| (stop.offset(), stop.color())).collect();
+        assert_eq!(stops, vec![
+          (0.0, Rgba::new(255, 0, 0, 255)),
+          (0.25, Rgba::new(0, 255, 0, 255)),
+          (1.0, Rgba::new(0, 0, 255, 0)),
+        ]);
+      },
+      paint => panic!("expected a linear gradient, got {:?}", paint),
+    }
+  }
+
   #[test]
   #[cfg(feature = "image")]
   fn can_rasterize() {
@@ -387,6 +1086,957 @@ mod tests {
     assert_eq!((width, height), (256, 256));
   }
 
+  #[test]
+  fn can_reuse_rasterizer() {
+    let example = SvgImage::parse_file(Path::new("examples/example.svg"), Units::Pixel, 96.0).unwrap();
+    let small = SvgImage::parse_str(r#"<svg width="10" height="20"><rect width="10" height="20" /></svg>"#, Units::Pixel, 96.0).unwrap();
+    let mut rasterizer = Rasterizer::new().unwrap();
+
+    let (width, height, raw) = rasterizer.rasterize_to_raw_rgba(&example, 1.0).unwrap();
+    assert_eq!((width, height, raw.len()), (256, 256, 256 * 256 * 4));
+
+    let (width, height, raw) = rasterizer.rasterize_to_raw_rgba(&small, 3.0).unwrap();
+    assert_eq!((width, height, raw.len()), (30, 60, 30 * 60 * 4));
+    assert!(raw.chunks(4).all(|pixel| pixel == [0, 0, 0, 255]));
+
+    let (width, height, raw) = rasterizer.rasterize_to_raw_rgba(&example, 0.5).unwrap();
+    assert_eq!((width, height, raw.len()), (128, 128, 128 * 128 * 4));
+  }
+
+  #[test]
+  fn can_send_rasterizer_to_another_thread() {
+    let mut rasterizer = Rasterizer::new().unwrap();
+
+    let (width, height) = std::thread::spawn(move || {
+      let svg = SvgImage::parse_file(Path::new("examples/example.svg"), Units::Pixel, 96.0).unwrap();
+      let (width, height, _raw_rgba) = rasterizer.rasterize_to_raw_rgba(&svg, 1.0).unwrap();
+      (width, height)
+    }).join().unwrap();
+
+    assert_eq!((width, height), (256, 256));
+  }
+
+  #[test]
+  fn can_rasterize_into_a_region_of_a_larger_buffer() {
+    let svg = SvgImage::parse_str(r#"<svg width="2" height="2"><rect width="2" height="2" /></svg>"#, Units::Pixel, 96.0).unwrap();
+    let mut rasterizer = Rasterizer::new().unwrap();
+
+    // A 4x4 framebuffer, drawing into the 2x2 region at (1, 1)
+    let stride = 4 * 4;
+    let mut framebuffer = vec![7u8; stride * 4];
+    let offset = stride + 4;
+    rasterizer.rasterize_into(&svg, &mut framebuffer[offset..], 2, 2, stride, 1.0).unwrap();
+
+    for y in 0..4 {
+      for x in 0..4 {
+        let pixel = &framebuffer[y * stride + x * 4..][..4];
+        if (1..3).contains(&x) && (1..3).contains(&y) {
+          assert_eq!(pixel, [0, 0, 0, 255], "pixel at {}, {}", x, y);
+        } else {
+          assert_eq!(pixel, [7, 7, 7, 7], "pixel at {}, {}", x, y);
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn error_when_rasterizing_into_a_buffer_that_is_too_small() {
+    let svg = SvgImage::parse_file(Path::new("examples/example.svg"), Units::Pixel, 96.0).unwrap();
+    let mut rasterizer = Rasterizer::new().unwrap();
+    let mut buffer = vec![0u8; 16 * 16 * 4 - 1];
+
+    let result = rasterizer.rasterize_into(&svg, &mut buffer, 16, 16, 16 * 4, 1.0);
+    assert!(matches!(result, Err(Error::BufferSizeError)));
+
+    // Stride must be at least one row of pixels
+    let mut buffer = vec![0u8; 16 * 16 * 4];
+    let result = rasterizer.rasterize_into(&svg, &mut buffer, 16, 16, 16 * 4 - 1, 1.0);
+    assert!(matches!(result, Err(Error::BufferSizeError)));
+
+    assert!(buffer.iter().all(|&byte| byte == 0));
+  }
+
+  #[test]
+  fn error_when_rasterizing_to_an_image_too_large_to_allocate() {
+    let svg = SvgImage::parse_str(QUADRANTS, Units::Pixel, 96.0).unwrap();
+    let mut rasterizer = Rasterizer::new().unwrap();
+
+    let result = rasterizer.rasterize_to_raw_rgba(&svg, 1e9);
+    assert!(matches!(result, Err(Error::BufferSizeError)));
+    let options = RasterizeOptions { width: Some(u32::MAX), height: Some(u32::MAX), ..Default::default() };
+    let result = rasterizer.rasterize_to_raw_with_options(&svg, &options);
+    assert!(matches!(result, Err(Error::BufferSizeError)));
+  }
+
+  const QUADRANTS: &str = r##"
+    <svg width="4" height="4">
+      <rect width="2" height="2" fill="red" />
+      <rect x="2" width="2" height="2" fill="#00ff00" />
+      <rect y="2" width="2" height="2" fill="blue" />
+      <rect x="2" y="2" width="2" height="2" fill="white" />
+    </svg>
+  "##;
+
+  #[test]
+  fn can_rasterize_a_zoomed_in_region() {
+    let svg = SvgImage::parse_str(QUADRANTS, Units::Pixel, 96.0).unwrap();
+
+    // The top-left quarter at 8x
+    let options = RasterizeOptions { scale: 8.0, width: Some(16), height: Some(16), ..Default::default() };
+    let (width, height, raw) = svg.rasterize_to_raw_with_options(&options).unwrap();
+
+    assert_eq!((width, height), (16, 16));
+    assert!(raw.chunks(4).all(|pixel| pixel == [255, 0, 0, 255]));
+  }
+
+  #[test]
+  fn can_rasterize_with_an_offset() {
+    let svg = SvgImage::parse_str(QUADRANTS, Units::Pixel, 96.0).unwrap();
+
+    // The bottom-right quarter at 8x
+    let options = RasterizeOptions { scale: 8.0, offset: (-16.0, -16.0), width: Some(16), height: Some(16), ..Default::default() };
+    let (_, _, raw) = svg.rasterize_to_raw_with_options(&options).unwrap();
+    assert!(raw.chunks(4).all(|pixel| pixel == [255, 255, 255, 255]));
+
+    // Shifted right by a pixel, leaving the first column empty
+    let options = RasterizeOptions { offset: (1.0, 0.0), ..Default::default() };
+    let (width, height, raw) = svg.rasterize_to_raw_with_options(&options).unwrap();
+    assert_eq!((width, height), (4, 4));
+    assert_eq!(raw[3], 0);
+    assert_eq!(&raw[4..8], [255, 0, 0, 255]);
+    assert_eq!(&raw[12..16], [0, 255, 0, 255]);
+  }
+
+  #[test]
+  fn output_size_defaults_to_the_scaled_document() {
+    let svg = SvgImage::parse_str(QUADRANTS, Units::Pixel, 96.0).unwrap();
+
+    assert_eq!(RasterizeOptions::with_scale(2.5).output_size(&svg), (10, 10));
+    assert_eq!(RasterizeOptions { width: Some(3), ..RasterizeOptions::with_scale(2.0) }.output_size(&svg), (3, 8));
+  }
+
+  #[test]
+  fn can_rasterize_with_premultiplied_alpha() {
+    let svg = SvgImage::parse_str(r##"
+      <svg width="3" height="1">
+        <rect x="1" width="1" height="1" fill="#ff8000" fill-opacity="0.5" />
+      </svg>
+    "##, Units::Pixel, 96.0).unwrap();
+
+    let (_, _, straight) = svg.rasterize_to_raw_rgba(1.0).unwrap();
+    assert_eq!(&straight[4..8], [255, 126, 0, 127]);
+    // Edge color bleeds into the transparent neighbours
+    assert_eq!(&straight[8..], [255, 126, 0, 0]);
+
+    let options = RasterizeOptions { alpha: AlphaMode::Premultiplied, ..Default::default() };
+    let (_, _, premultiplied) = svg.rasterize_to_raw_with_options(&options).unwrap();
+    assert_eq!(&premultiplied[4..8], [127, 63, 0, 127]);
+    assert_eq!(&premultiplied[8..], [0, 0, 0, 0]);
+  }
+
+  #[test]
+  fn can_rasterize_to_other_pixel_formats() {
+    let svg = SvgImage::parse_str(r##"
+      <svg width="2" height="1">
+        <rect width="1" height="1" fill="#ff8000" />
+        <rect x="1" width="1" height="1" fill="#0000ff" fill-opacity="0.5" />
+      </svg>
+    "##, Units::Pixel, 96.0).unwrap();
+
+    let rasterize = |format, alpha| {
+      let options = RasterizeOptions { format, alpha, ..Default::default() };
+      let (width, height, raw) = svg.rasterize_to_raw_with_options(&options).unwrap();
+      assert_eq!((width, height), (2, 1));
+      raw
+    };
+
+    assert_eq!(rasterize(PixelFormat::Bgra8, AlphaMode::Straight), [0, 128, 255, 255, 255, 0, 0, 127]);
+    assert_eq!(rasterize(PixelFormat::Bgra8, AlphaMode::Premultiplied), [0, 128, 255, 255, 127, 0, 0, 127]);
+    assert_eq!(rasterize(PixelFormat::Rgb8, AlphaMode::Straight), [255, 128, 0, 0, 0, 127]);
+    assert_eq!(rasterize(PixelFormat::A8, AlphaMode::Straight), [255, 127]);
+    assert_eq!(rasterize(PixelFormat::L8, AlphaMode::Straight), [145, 9]);
+  }
+
+  #[test]
+  fn can_rasterize_other_pixel_formats_into_a_buffer() {
+    let svg = SvgImage::parse_str(r#"<svg width="2" height="2"><rect width="2" height="2" /></svg>"#, Units::Pixel, 96.0).unwrap();
+    let mut rasterizer = Rasterizer::new().unwrap();
+    let options = RasterizeOptions { format: PixelFormat::A8, ..Default::default() };
+
+    // Rows padded to 3 bytes, which must be left alone
+    let mut mask = vec![7u8; 5];
+    rasterizer.rasterize_into_with_options(&svg, &mut mask, 3, &options).unwrap();
+    assert_eq!(mask, [255, 255, 7, 255, 255]);
+
+    let mut mask = vec![0u8; 4];
+    let result = rasterizer.rasterize_into_with_options(&svg, &mut mask, 1, &options);
+    assert!(matches!(result, Err(Error::BufferSizeError)));
+  }
+
+  #[test]
+  fn can_rasterize_over_a_background() {
+    let svg = SvgImage::parse_str(r##"
+      <svg width="2" height="1">
+        <rect width="1" height="1" fill="#ff0000" fill-opacity="0.5" />
+      </svg>
+    "##, Units::Pixel, 96.0).unwrap();
+
+    let rasterize = |format, background| {
+      let options = RasterizeOptions { format, background: Some(background), ..Default::default() };
+      svg.rasterize_to_raw_with_options(&options).unwrap().2
+    };
+
+    let blue = Rgba::new(0, 0, 255, 255);
+    assert_eq!(rasterize(PixelFormat::Rgba8, blue), [127, 0, 128, 255, 0, 0, 255, 255]);
+    assert_eq!(rasterize(PixelFormat::Bgra8, blue), [128, 0, 127, 255, 255, 0, 0, 255]);
+    assert_eq!(rasterize(PixelFormat::Rgb8, blue), [127, 0, 128, 0, 0, 255]);
+    assert_eq!(rasterize(PixelFormat::A8, blue), [255, 255]);
+
+    // A translucent background stays translucent
+    let faint_white = Rgba::new(255, 255, 255, 51);
+    assert_eq!(rasterize(PixelFormat::Rgba8, faint_white), [255, 43, 43, 153, 255, 255, 255, 51]);
+  }
+
+  #[test]
+  #[cfg(feature = "image")]
+  fn can_rasterize_an_image_over_a_background() {
+    let svg = SvgImage::parse_str(WIDE, Units::Pixel, 96.0).unwrap();
+    let options = RasterizeOptions {
+      background: Some(Rgba::new(255, 255, 255, 255)),
+      ..RasterizeOptions::fit(&svg, 4, 4, Fit::Contain)
+    };
+
+    let image = svg.rasterize_with_options(&options).unwrap();
+
+    assert_eq!(image.get_pixel(0, 0).data, [255, 255, 255, 255]);
+    assert_eq!(image.get_pixel(0, 1).data, [255, 0, 0, 255]);
+  }
+
+  const LAYERS: &str = r#"
+    <svg width="3" height="1">
+      <g id="background">
+        <rect id="icon-on" width="1" height="1" fill="red" />
+        <g id="shadow">
+          <rect x="1" width="1" height="1" fill="blue" />
+        </g>
+      </g>
+      <rect id="badge" x="2" width="1" height="1" fill="yellow" />
+    </svg>
+  "#;
+
+  #[test]
+  fn can_find_the_groups_of_shapes() {
+    let svg = SvgImage::parse_str(LAYERS, Units::Pixel, 96.0).unwrap();
+    let shapes: Vec<_> = svg.shapes().collect();
+
+    assert_eq!(shapes[0].groups().collect::<Vec<_>>(), ["background"]);
+    assert_eq!(shapes[1].id(), "shadow");
+    assert_eq!(shapes[1].groups().collect::<Vec<_>>(), ["background", "shadow"]);
+    assert_eq!(shapes[2].groups().count(), 0);
+
+    assert!(shapes[0].has_id("icon-on"));
+    assert!(shapes[0].has_id("background"));
+    assert!(!shapes[0].has_id("shadow"));
+  }
+
+  #[test]
+  fn can_rasterize_some_shapes() {
+    let svg = SvgImage::parse_str(LAYERS, Units::Pixel, 96.0).unwrap();
+    let options = RasterizeOptions::default();
+    let alphas = |raw: Vec<u8>| raw.chunks(4).map(|pixel| pixel[3]).collect::<Vec<_>>();
+
+    let (_, _, raw) = svg.rasterize_shapes_to_raw(&options, |shape| !shape.has_id("shadow")).unwrap();
+    assert_eq!(alphas(raw), [255, 0, 255]);
+
+    let mut rasterizer = Rasterizer::new().unwrap();
+    let (_, _, raw) = rasterizer.rasterize_shapes_to_raw_with_options(&svg, &options, |_| false).unwrap();
+    assert_eq!(alphas(raw), [0, 0, 0]);
+
+    // The filter only applies to the call it was given to
+    let (_, _, raw) = rasterizer.rasterize_to_raw_rgba(&svg, 1.0).unwrap();
+    assert_eq!(alphas(raw), [255, 255, 255]);
+  }
+
+  #[test]
+  #[cfg(feature = "image")]
+  fn can_rasterize_shapes_by_id() {
+    let svg = SvgImage::parse_str(LAYERS, Units::Pixel, 96.0).unwrap();
+    let image = svg.rasterize_ids(&RasterizeOptions::default(), &["icon-on", "badge"]).unwrap();
+
+    assert_eq!(image.get_pixel(0, 0).data, [255, 0, 0, 255]);
+    assert_eq!(image.get_pixel(1, 0).data[3], 0);
+    assert_eq!(image.get_pixel(2, 0).data, [255, 255, 0, 255]);
+  }
+
+  #[test]
+  fn can_instance_elements_with_use() {
+    let svg = SvgImage::parse_str(r##"
+      <svg width="20" height="20" xmlns:xlink="http://www.w3.org/1999/xlink">
+        <defs>
+          <rect id="dot" width="2" height="2" fill="red" />
+        </defs>
+        <use xlink:href="#dot" x="5" y="1" />
+        <use href="#later" transform="translate(10 0)" fill="blue" />
+        <use id="loop" href="#loop" />
+        <g id="later"><circle cx="1" cy="1" r="1" /></g>
+      </svg>
+    "##, Units::Pixel, 96.0).unwrap();
+
+    let shapes: Vec<_> = svg.shapes().collect();
+    assert_eq!(shapes.len(), 3);
+    assert_eq!(shapes[0].bounds(), [5.0, 1.0, 7.0, 3.0]);
+    assert_eq!(shapes[1].bounds(), [10.0, 0.0, 12.0, 2.0]);
+    // Presentation attributes of <use> are inherited by the instance
+    assert!(matches!(shapes[1].fill(), Paint::Color(color) if color == Rgba::new(0, 0, 255, 255)));
+    assert_eq!(shapes[2].bounds(), [0.0, 0.0, 2.0, 2.0]);
+  }
+
+  #[test]
+  fn can_instance_symbols() {
+    let svg = SvgImage::parse_str(r##"
+      <svg width="20" height="20">
+        <symbol id="icon" viewBox="0 0 10 5">
+          <rect width="10" height="5" />
+        </symbol>
+        <use href="#icon" x="2" y="4" width="4" height="4" />
+        <use href="#icon" x="10" width="2" height="8" />
+      </svg>
+    "##, Units::Pixel, 96.0).unwrap();
+
+    // The symbol itself isn't drawn, and its viewBox is fitted into each <use>
+    let bounds: Vec<_> = svg.shapes().map(|shape| shape.bounds()).collect();
+    assert_eq!(bounds, [[2.0, 5.0, 6.0, 7.0], [10.0, 3.5, 12.0, 4.5]]);
+  }
+
+  #[test]
+  fn can_limit_nested_instances() {
+    // Each level instances the previous one ten times, 10^8 rectangles in all
+    let mut source = String::from(r##"<svg width="10" height="10"><symbol id="s0"><rect width="1" height="1" /></symbol>"##);
+    for level in 1..9 {
+      source.push_str(&format!(r##"<symbol id="s{}">"##, level));
+      for _ in 0..10 {
+        source.push_str(&format!(r##"<use href="#s{}" />"##, level - 1));
+      }
+      source.push_str("</symbol>");
+    }
+    source.push_str(r##"<use href="#s8" /></svg>"##);
+
+    let (svg, warnings) = SvgImage::parse_str_with_warnings(&source, &ParseOptions::default()).unwrap();
+    assert!(svg.shapes().count() < 100_000);
+    let reasons: Vec<_> = warnings.iter().map(|warning| (&warning.reason, warning.element.as_str())).collect();
+    assert_eq!(reasons, [(&WarningReason::TooManyInstances, "use")]);
+  }
+
+  #[test]
+  fn can_clip_shapes() {
+    let svg = SvgImage::parse_str(r##"
+      <svg width="8" height="2">
+        <defs>
+          <clipPath id="left"><rect width="2" height="2" /></clipPath>
+          <clipPath id="half" clipPathUnits="objectBoundingBox">
+            <rect width="0.5" height="1" />
+          </clipPath>
+        </defs>
+        <rect width="4" height="1" fill="red" clip-path="url(#left)" />
+        <g clip-path="url(#left)" transform="translate(4 0)">
+          <rect width="4" height="1" fill="blue" />
+        </g>
+        <rect y="1" width="4" height="1" fill="yellow" clip-path="url(#missing)" />
+        <rect x="4" y="1" width="4" height="1" fill="#00ff00" clip-path="url(#half)" />
+      </svg>
+    "##, Units::Pixel, 96.0).unwrap();
+
+    // Clip paths themselves aren't drawn
+    assert_eq!(svg.shapes().count(), 4);
+
+    let mut rasterizer = Rasterizer::new().unwrap();
+    let (_, _, raw) = rasterizer.rasterize_to_raw_rgba(&svg, 1.0).unwrap();
+    // Transparent pixels keep the color of their neighbours, only alpha matters
+    let pixels: Vec<_> = raw.chunks(4).map(|pixel| if pixel[3] == 0 { &[0, 0, 0, 0] } else { pixel }).collect();
+    let (red, blue, yellow, lime, none) = ([255, 0, 0, 255], [0, 0, 255, 255], [255, 255, 0, 255], [0, 255, 0, 255], [0, 0, 0, 0]);
+    assert_eq!(pixels[..8], [red, red, none, none, blue, blue, none, none]);
+    assert_eq!(pixels[8..], [yellow, yellow, yellow, yellow, lime, lime, none, none]);
+  }
+
+  #[test]
+  fn can_clip_clip_paths() {
+    let svg = SvgImage::parse_str(r##"
+      <svg width="8" height="3">
+        <defs>
+          <clipPath id="left"><rect width="4" height="3" /></clipPath>
+          <clipPath id="nested">
+            <rect width="8" height="3" clip-path="url(#left)" />
+            <rect x="6" width="2" height="3" />
+          </clipPath>
+          <clipPath id="own" clip-path="url(#left)">
+            <rect x="2" width="6" height="3" />
+          </clipPath>
+          <clipPath id="loop"><rect width="8" height="3" clip-path="url(#loop)" /></clipPath>
+        </defs>
+        <rect width="8" height="1" fill="red" clip-path="url(#nested)" />
+        <rect y="1" width="8" height="1" fill="blue" clip-path="url(#own)" />
+        <rect y="2" width="8" height="1" fill="yellow" clip-path="url(#loop)" />
+      </svg>
+    "##, Units::Pixel, 96.0).unwrap();
+
+    let mut rasterizer = Rasterizer::new().unwrap();
+    let (_, _, raw) = rasterizer.rasterize_to_raw_rgba(&svg, 1.0).unwrap();
+    let alphas: Vec<_> = raw.chunks(4).map(|pixel| pixel[3]).collect();
+    assert_eq!(alphas[..8], [255, 255, 255, 255, 0, 0, 255, 255]);
+    assert_eq!(alphas[8..16], [0, 0, 255, 255, 0, 0, 0, 0]);
+    // A clip path which contains itself hides what it clips
+    assert_eq!(alphas[16..], [0; 8]);
+  }
+
+  #[test]
+  fn can_mask_shapes() {
+    let svg = SvgImage::parse_str(r##"
+      <svg width="8" height="2">
+        <defs>
+          <mask id="fade">
+            <rect width="4" height="1" fill="white" />
+            <rect x="2" width="2" height="1" fill="black" />
+          </mask>
+          <mask id="gray" maskContentUnits="objectBoundingBox">
+            <rect width="1" height="1" fill="#808080" />
+          </mask>
+          <mask id="area" maskUnits="userSpaceOnUse" x="0" y="1" width="2" height="1">
+            <rect width="8" height="2" fill="white" />
+          </mask>
+        </defs>
+        <rect width="4" height="1" fill="red" mask="url(#fade)" />
+        <rect x="4" width="4" height="1" fill="blue" mask="url(#gray)" />
+        <g mask="url(#area)">
+          <rect y="1" width="8" height="1" fill="yellow" />
+        </g>
+      </svg>
+    "##, Units::Pixel, 96.0).unwrap();
+
+    // Mask contents aren't drawn
+    assert_eq!(svg.shapes().count(), 3);
+
+    let mut rasterizer = Rasterizer::new().unwrap();
+    let (_, _, raw) = rasterizer.rasterize_to_raw_rgba(&svg, 1.0).unwrap();
+    let alphas: Vec<_> = raw.chunks(4).map(|pixel| pixel[3]).collect();
+    assert_eq!(alphas, [255, 255, 0, 0, 128, 128, 128, 128, 255, 255, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(raw[..4], [255, 0, 0, 255]);
+    assert_eq!(raw[16..20], [0, 0, 255, 128]);
+    assert_eq!(raw[32..36], [255, 255, 0, 255]);
+  }
+
+  #[test]
+  fn can_mask_and_clip_mask_contents() {
+    let svg = SvgImage::parse_str(r##"
+      <svg width="20" height="3">
+        <defs>
+          <mask id="left" maskUnits="userSpaceOnUse" x="0" y="0" width="20" height="3">
+            <rect width="10" height="3" fill="white" />
+          </mask>
+          <clipPath id="right"><rect x="10" width="10" height="3" /></clipPath>
+          <mask id="masked" maskUnits="userSpaceOnUse" x="0" y="0" width="20" height="3">
+            <rect width="20" height="3" fill="white" mask="url(#left)" />
+          </mask>
+          <mask id="clipped" maskUnits="userSpaceOnUse" x="0" y="0" width="20" height="3">
+            <g transform="translate(-5 0)">
+              <rect x="5" width="20" height="3" fill="white" clip-path="url(#right)" />
+            </g>
+          </mask>
+          <mask id="itself">
+            <rect width="20" height="3" fill="white" mask="url(#itself)" />
+          </mask>
+        </defs>
+        <rect width="20" height="1" fill="red" mask="url(#masked)" />
+        <rect y="1" width="20" height="1" fill="blue" mask="url(#clipped)" />
+        <rect y="2" width="20" height="1" fill="yellow" mask="url(#itself)" />
+      </svg>
+    "##, Units::Pixel, 96.0).unwrap();
+
+    let mut rasterizer = Rasterizer::new().unwrap();
+    let (_, _, raw) = rasterizer.rasterize_to_raw_rgba(&svg, 1.0).unwrap();
+    let alphas: Vec<_> = raw.chunks(4).map(|pixel| pixel[3]).collect();
+    assert_eq!(alphas[..20], [vec![255; 10], vec![0; 10]].concat()[..]);
+    // The clip path is in the user space of the clipped rectangle, and moves with it
+    assert_eq!(alphas[20..40], [vec![0; 5], vec![255; 10], vec![0; 5]].concat()[..]);
+    // A mask which contains itself hides what it masks
+    assert_eq!(alphas[40..], [0; 20]);
+  }
+
+  #[test]
+  fn can_composite_translucent_groups() {
+    let svg = SvgImage::parse_str(r##"
+      <svg width="4" height="1">
+        <g opacity="0.5">
+          <rect width="2" height="1" fill="red" />
+          <rect x="1" width="2" height="1" fill="blue" />
+        </g>
+        <g opacity="0.5">
+          <g opacity="0.5">
+            <rect x="3" width="1" height="1" fill="#00ff00" opacity="0.5" />
+          </g>
+        </g>
+      </svg>
+    "##, Units::Pixel, 96.0).unwrap();
+
+    let opacities: Vec<_> = svg.shapes().map(|shape| (shape.own_opacity(), shape.group_opacity(), shape.opacity())).collect();
+    assert_eq!(opacities, [(1.0, 0.5, 0.5), (1.0, 0.5, 0.5), (0.5, 0.25, 0.125)]);
+
+    // The blue rectangle covers the red one, instead of both showing through
+    let mut rasterizer = Rasterizer::new().unwrap();
+    let (_, _, raw) = rasterizer.rasterize_to_raw_rgba(&svg, 1.0).unwrap();
+    let pixels: Vec<_> = raw.chunks(4).collect();
+    assert_eq!(pixels, [[255, 0, 0, 128], [0, 0, 255, 128], [0, 0, 255, 128], [0, 255, 0, 31]]);
+  }
+
+  #[test]
+  fn translucent_groups_cover_their_strokes() {
+    // Layers only cover their shapes, which must include how far strokes reach
+    let svg = SvgImage::parse_str(r##"
+      <svg width="10" height="10">
+        <g opacity="0.5">
+          <rect x="4" y="4" width="2" height="2" fill="none" stroke="red" stroke-width="8" />
+        </g>
+      </svg>
+    "##, Units::Pixel, 96.0).unwrap();
+
+    let mut rasterizer = Rasterizer::new().unwrap();
+    let (_, _, raw) = rasterizer.rasterize_to_raw_rgba(&svg, 1.0).unwrap();
+    assert_eq!(raw[..4], [255, 0, 0, 128]);
+    assert_eq!(raw[raw.len() - 4..], [255, 0, 0, 128]);
+  }
+
+  // A 2x1 PNG, with a red and a blue pixel
+  #[cfg(feature = "image")]
+  const TWO_PIXEL_PNG: &str = "iVBORw0KGgoAAAANSUhEUgAAAAIAAAABCAYAAAD0In+KAAAADklEQVR4nGP4z8AAQv8BD/kD/YURmXYAAAAASUVORK5CYII=";
+
+  #[test]
+  #[cfg(feature = "image")]
+  fn can_draw_embedded_images() {
+    let svg = SvgImage::parse_str(&format!(r##"
+      <svg width="4" height="2">
+        <image width="4" height="2" preserveAspectRatio="none" href="data:image/png;base64,{}" />
+      </svg>
+    "##, TWO_PIXEL_PNG), Units::Pixel, 96.0).unwrap();
+
+    match svg.shapes().next().unwrap().fill() {
+      Paint::Image(bitmap) => {
+        assert!(bitmap.href().starts_with("data:image/png;base64,"));
+        assert_eq!(bitmap.viewport(), [0.0, 0.0, 4.0, 2.0]);
+        assert_eq!(bitmap.size(), Some((2, 1)));
+      },
+      fill => panic!("Unexpected fill {:?}", fill),
+    }
+
+    let mut rasterizer = Rasterizer::new().unwrap();
+    let (_, _, raw) = rasterizer.rasterize_to_raw_rgba(&svg, 1.0).unwrap();
+    let pixels: Vec<_> = raw.chunks(4).take(4).collect();
+    assert_eq!(pixels[0], [255, 0, 0, 255]);
+    assert_eq!(pixels[3], [0, 0, 255, 255]);
+
+    // Inner pixels blend the neighbouring texels
+    assert!(pixels[1][0] > pixels[1][2] && pixels[1][2] > 0);
+  }
+
+  #[test]
+  #[cfg(feature = "image")]
+  fn can_resolve_linked_images() {
+    let mut svg = SvgImage::parse_str(r##"
+      <svg width="4" height="1">
+        <image id="pixels" width="2" height="1" xlink:href="pixels.png" />
+        <use href="#pixels" x="2" />
+      </svg>
+    "##, Units::Pixel, 96.0).unwrap();
+
+    let mut rasterizer = Rasterizer::new().unwrap();
+    let (_, _, raw) = rasterizer.rasterize_to_raw_rgba(&svg, 1.0).unwrap();
+    assert!(raw.iter().all(|&byte| byte == 0));
+
+    // Every instance of an image shares the pixels it was decoded to once
+    let png = bitmap::decode_data_uri(&format!("data:;base64,{}", TWO_PIXEL_PNG)).unwrap();
+    let mut hrefs = Vec::new();
+    assert_eq!(svg.load_images(|href| { hrefs.push(href.to_string()); Some(png.clone()) }), 2);
+    assert_eq!(hrefs, ["pixels.png"]);
+
+    let (_, _, raw) = rasterizer.rasterize_to_raw_rgba(&svg, 1.0).unwrap();
+    assert_eq!(raw, [255, 0, 0, 255, 0, 0, 255, 255, 255, 0, 0, 255, 0, 0, 255, 255]);
+  }
+
+  #[test]
+  #[cfg(feature = "image")]
+  fn cannot_load_images_with_too_many_pixels() {
+    let mut svg = SvgImage::parse_str(r##"
+      <svg width="2" height="1">
+        <image width="2" height="1" xlink:href="huge.png" />
+      </svg>
+    "##, Units::Pixel, 96.0).unwrap();
+
+    // Just the header of a 100000x100000 PNG, which would take 40GB to decode
+    let png = vec![
+      137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13, 73, 72, 68, 82,
+      0, 1, 134, 160, 0, 1, 134, 160, 8, 6, 0, 0, 0, 168, 82, 11, 200,
+    ];
+    assert_eq!(svg.load_images(|_| Some(png.clone())), 0);
+
+    match svg.shapes().next().unwrap().fill() {
+      Paint::Image(bitmap) => assert_eq!(bitmap.size(), None),
+      fill => panic!("Unexpected fill {:?}", fill),
+    }
+  }
+
+  #[test]
+  fn can_outline_text() {
+    let mut svg = SvgImage::parse_str(r##"
+      <svg width="40" height="20">
+        <text x="20" y="10" font-family="'Open Sans', serif" font-size="10" text-anchor="middle" fill="#0000ff">
+          A <tspan fill="#ff0000" dy="5">B</tspan>
+        </text>
+      </svg>
+    "##, Units::Pixel, 96.0).unwrap();
+
+    let runs: Vec<_> = svg.texts().map(|text| (text.content().into_owned(), text.x(), text.y(), text.offset())).collect();
+    assert_eq!(runs, [("A".into(), Some(20.0), Some(10.0), (0.0, 0.0)), (" B".into(), None, None, (0.0, 5.0))]);
+    let text = svg.texts().next().unwrap();
+    assert_eq!(text.font_families().collect::<Vec<_>>(), ["Open Sans", "serif"]);
+    assert_eq!((text.font_size(), text.anchor()), (10.0, TextAnchor::Middle));
+
+    // Nothing is drawn until the text is outlined
+    let mut rasterizer = Rasterizer::new().unwrap();
+    let (_, _, raw) = rasterizer.rasterize_to_raw_rgba(&svg, 1.0).unwrap();
+    assert!(raw.iter().all(|&byte| byte == 0));
+
+    let mut families = Vec::new();
+    let outlined = svg.outline_texts(&mut |run: &Text| {
+      families.push(run.font_family().to_string());
+      None
+    });
+    assert_eq!((outlined, families.len()), (0, 2));
+    // Runs left without an outline don't keep the estimated size of the text
+    assert!(svg.texts().all(|text| text.shape().bounds() == [0.0; 4]));
+
+    // Three boxes 6 wide centred on x = 20, the second run 5 lower
+    assert_eq!(svg.outline_texts(&mut BoxFont), 2);
+    let bounds: Vec<_> = svg.texts().map(|text| text.shape().bounds()).collect();
+    assert_eq!(bounds, [[11.5, 3.0, 16.5, 10.0], [23.5, 8.0, 28.5, 15.0]]);
+
+    let (_, _, raw) = rasterizer.rasterize_to_raw_rgba(&svg, 1.0).unwrap();
+    let pixel = |x: usize, y: usize| &raw[(y * 40 + x) * 4..(y * 40 + x) * 4 + 4];
+    assert_eq!(pixel(13, 6), [0, 0, 255, 255]);
+    assert_eq!(pixel(25, 12), [255, 0, 0, 255]);
+    assert_eq!(pixel(20, 6)[3], 0);
+  }
+
+  #[test]
+  fn can_decode_character_references() {
+    let svg = SvgImage::parse_str(r##"
+      <svg width="40" height="20">
+        <text>&lt;&amp;&gt; &#65;&#x42;&#X63; &#233; &#x1F600;</text>
+        <text>&#x; &#; &#0; &#xD800; &#xDFFF; &#x110000; &# 65; &#-65; &#x+41; &bogus;</text>
+      </svg>
+    "##, Units::Pixel, 96.0).unwrap();
+
+    let contents: Vec<_> = svg.texts().map(|text| text.content()).collect();
+    assert_eq!(contents, [
+      "<&> ABc \u{e9} \u{1F600}",
+      "&#x; &#; &#0; &#xD800; &#xDFFF; &#x110000; &# 65; &#-65; &#x+41; &bogus;",
+    ]);
+
+    // Text in other encodings isn't lost, only its invalid bytes are
+    let svg = SvgImage::parse_bytes(b"<svg><text font-family=\"Caf\xe9 Sans\">caf\xe9</text></svg>", Units::Pixel, 96.0).unwrap();
+    let text = svg.texts().next().unwrap();
+    assert_eq!(text.content(), "caf\u{FFFD}");
+    assert_eq!(text.font_family(), "Caf\u{FFFD} Sans");
+  }
+
+  #[test]
+  fn can_fit_the_view_box() {
+    let svg = SvgImage::parse_str(r##"
+      <svg width="40" height="20" viewBox="0 0 10 10" preserveAspectRatio="xMaxYMin meet">
+        <rect width="10" height="10" fill="#0000ff" />
+      </svg>
+    "##, Units::Pixel, 96.0).unwrap();
+
+    assert_eq!(svg.view_box(), [0.0, 0.0, 10.0, 10.0]);
+    let aspect_ratio = svg.aspect_ratio();
+    assert_eq!(aspect_ratio, AspectRatio { x: Align::Max, y: Align::Min, mode: AspectMode::Meet });
+    assert_eq!(aspect_ratio.transform(svg.view_box(), svg.width(), svg.height()), [2.0, 0.0, 0.0, 2.0, 20.0, 0.0]);
+    assert_eq!(svg.shapes().next().unwrap().bounds(), [20.0, 0.0, 40.0, 20.0]);
+
+    // Centered and fitted inside by default
+    let svg = SvgImage::parse_str(r##"<svg width="40" height="20" viewBox="0 0 10 10" />"##, Units::Pixel, 96.0).unwrap();
+    assert_eq!(svg.aspect_ratio(), AspectRatio::default());
+  }
+
+  #[test]
+  fn can_draw_nested_viewports() {
+    let svg = SvgImage::parse_str(r##"
+      <svg width="8" height="2">
+        <svg x="1" width="2" height="2" viewBox="0 0 1 1">
+          <rect x="-1" width="3" height="1" fill="#ff0000" />
+        </svg>
+        <svg x="4" width="2" height="2" overflow="visible">
+          <rect x="-1" width="2" height="2" fill="#0000ff" />
+        </svg>
+      </svg>
+    "##, Units::Pixel, 96.0).unwrap();
+
+    // The viewBox is scaled into the viewport, and clipped to it
+    let bounds: Vec<_> = svg.shapes().map(|shape| shape.bounds()).collect();
+    assert_eq!(bounds, [[-1.0, 0.0, 5.0, 2.0], [3.0, 0.0, 5.0, 2.0]]);
+
+    let mut rasterizer = Rasterizer::new().unwrap();
+    let (_, _, raw) = rasterizer.rasterize_to_raw_rgba(&svg, 1.0).unwrap();
+    let alpha: Vec<_> = raw.chunks(4).take(8).map(|pixel| pixel[3]).collect();
+    assert_eq!(alpha, [0, 255, 255, 255, 255, 0, 0, 0]);
+    assert_eq!(&raw[4..8], [255, 0, 0, 255]);
+    assert_eq!(&raw[12..16], [0, 0, 255, 255]);
+  }
+
+  #[test]
+  fn can_report_viewports_which_cant_be_clipped() {
+    let nested = "<svg width=\"4\" height=\"4\">".repeat(5);
+    let source = format!("<svg width=\"4\" height=\"4\">\n{}<rect width=\"8\" height=\"8\" />{}</svg>", nested, "</svg>".repeat(5));
+    let (svg, warnings) = SvgImage::parse_str_with_warnings(&source, &ParseOptions::default()).unwrap();
+
+    let reasons: Vec<_> = warnings.iter().map(|warning| (warning.reason.clone(), warning.element.as_str(), warning.column)).collect();
+    assert_eq!(reasons, [(WarningReason::TooManyClipPaths, "svg", 105)]);
+    assert_eq!(svg.shapes().count(), 1);
+  }
+
+  #[test]
+  fn can_report_groups_too_deep_to_draw_as_layers() {
+    let nested = "<g opacity=\"0.9\">".repeat(12);
+    let source = format!("<svg width=\"1\" height=\"1\">{}<rect width=\"1\" height=\"1\" fill=\"#ff0000\" /><rect width=\"1\" height=\"1\" fill=\"#0000ff\" />{}</svg>", nested, "</g>".repeat(12));
+    let (svg, warnings) = SvgImage::parse_str_with_warnings(&source, &ParseOptions::default()).unwrap();
+
+    // Only the groups beyond the layers available are reported
+    let reasons: Vec<_> = warnings.iter().map(|warning| (warning.reason.clone(), warning.element.as_str())).collect();
+    assert_eq!(reasons, vec![(WarningReason::TooManyLayers, "g"); 4]);
+    assert_eq!(warnings[0].column, 27 + 17 * 8);
+    assert_eq!(svg.shapes().count(), 2);
+  }
+
+  #[test]
+  fn can_apply_style_sheets() {
+    let svg = SvgImage::parse_str(r##"
+      <svg width="6" height="1">
+        <defs>
+          <style type="text/css"><![CDATA[
+            /* Exported from an editor > with comments */
+            @media print { rect { fill: white } }
+            rect { fill: blue; stroke: none }
+            .cls-1, .cls-2 { fill: #ff0000 }
+            rect.cls-2 { fill-opacity: 0.5 }
+            #badge { fill: yellow }
+            circle > rect, rect:hover { fill: white }
+          ]]></style>
+        </defs>
+        <rect width="1" height="1" />
+        <rect x="1" width="1" height="1" class="cls-1" />
+        <rect x="2" width="1" height="1" class="other cls-2" />
+        <rect id="badge" x="3" width="1" height="1" class="cls-1" />
+        <rect x="4" width="1" height="1" class="cls-1" fill="green" />
+        <rect x="5" width="1" height="1" class="cls-1" style="fill: #00ff00" />
+      </svg>
+    "##, Units::Pixel, 96.0).unwrap();
+
+    let fills: Vec<_> = svg.shapes()
+      .map(|shape| match shape.fill() {
+        Paint::Color(color) => color,
+        _ => panic!("expected a solid fill"),
+      })
+      .collect();
+
+    assert_eq!(fills, [
+      Rgba::new(0, 0, 255, 255),
+      Rgba::new(255, 0, 0, 255),
+      Rgba::new(255, 0, 0, 127),
+      Rgba::new(255, 255, 0, 255),
+      // Style sheets override presentation attributes, but not inline styles
+      Rgba::new(255, 0, 0, 255),
+      Rgba::new(0, 255, 0, 255),
+    ]);
+  }
+
+  #[test]
+  fn can_substitute_current_color_and_variables() {
+    let svg = r##"
+      <svg width="8" height="1">
+        <rect width="1" height="1" fill="currentColor" />
+        <g color="#0000ff">
+          <rect x="1" width="1" height="1" style="fill: currentColor" />
+        </g>
+        <rect x="2" width="1" height="1" fill="var(--accent)" />
+        <rect x="3" width="1" height="1" fill="var(--missing, #00ff00 )" />
+        <rect x="4" width="1" height="1" fill="var(--missing)" />
+        <rect x="5" width="1" height="1" fill="currentColor" color="#00ffff" />
+        <rect x="6" width="1" height="1" style="fill: currentColor; color: #ff00ff" />
+        <rect x="7" width="1" height="1" fill="currentColor" style="color: #ffffff" color="#000000" />
+      </svg>
+    "##;
+    let options = ParseOptions {
+      current_color: Rgba::new(255, 0, 0, 255),
+      variables: vec![("--accent".to_string(), "yellow".to_string())].into_iter().collect(),
+      ..Default::default()
+    };
+
+    let fills: Vec<_> = SvgImage::parse_str_with_options(svg, &options).unwrap()
+      .shapes()
+      .map(|shape| match shape.fill() {
+        Paint::Color(color) => color,
+        _ => panic!("expected a solid fill"),
+      })
+      .collect();
+
+    assert_eq!(fills, [
+      Rgba::new(255, 0, 0, 255),
+      Rgba::new(0, 0, 255, 255),
+      Rgba::new(255, 255, 0, 255),
+      Rgba::new(0, 255, 0, 255),
+      Rgba::new(0, 0, 0, 255),
+      // The element's own color applies wherever it's set, and its style wins
+      Rgba::new(0, 255, 255, 255),
+      Rgba::new(255, 0, 255, 255),
+      Rgba::new(255, 255, 255, 255),
+    ]);
+
+    // Names don't need the leading dashes, and currentColor defaults to black
+    let options = ParseOptions {
+      variables: vec![("accent".to_string(), "none".to_string())].into_iter().collect(),
+      ..Default::default()
+    };
+    let svg = SvgImage::parse_str_with_options(svg, &options).unwrap();
+    let shapes: Vec<_> = svg.shapes().collect();
+    assert!(matches!(shapes[0].fill(), Paint::Color(color) if color == Rgba::new(0, 0, 0, 255)));
+    assert!(matches!(shapes[2].fill(), Paint::None));
+  }
+
+  #[test]
+  fn can_change_shapes() {
+    let mut svg = SvgImage::parse_str(r##"
+      <svg width="3" height="1">
+        <defs>
+          <linearGradient id="fade"><stop offset="0" stop-color="red" /></linearGradient>
+        </defs>
+        <rect id="icon-on" width="1" height="1" fill="url(#fade)" />
+        <rect id="shadow" x="1" width="1" height="1" fill="blue" />
+        <rect id="badge" x="2" width="1" height="1" fill="yellow" />
+      </svg>
+    "##, Units::Pixel, 96.0).unwrap();
+
+    assert!(svg.shape_mut("missing").is_none());
+
+    let mut icon = svg.shape_mut("icon-on").unwrap();
+    icon.set_fill_color(Rgba::new(0, 255, 0, 255));
+    icon.set_stroke_width(2.0);
+    assert!(matches!(icon.shape().fill(), Paint::Color(color) if color == Rgba::new(0, 255, 0, 255)));
+    assert_eq!(icon.shape().stroke_width(), 2.0);
+
+    svg.shape_mut("shadow").unwrap().set_visible(false);
+    svg.shape_mut("badge").unwrap().set_opacity(2.0);
+    assert_eq!(svg.shape_mut("badge").unwrap().shape().opacity(), 1.0);
+
+    for mut shape in svg.shapes_mut().filter(|shape| shape.shape().has_id("badge")) {
+      shape.set_opacity(0.5);
+    }
+
+    let (_, _, raw) = svg.rasterize_to_raw_rgba(1.0).unwrap();
+    assert_eq!(&raw[0..4], [0, 255, 0, 255]);
+    assert_eq!(raw[7], 0);
+    assert_eq!(&raw[8..], [255, 255, 0, 127]);
+  }
+
+  #[test]
+  fn can_rasterize_at_other_qualities() {
+    let svg = SvgImage::parse_str(r#"
+      <svg width="12" height="12">
+        <circle cx="6" cy="6" r="4.5" fill="black" />
+      </svg>
+    "#, Units::Pixel, 96.0).unwrap();
+    let mut rasterizer = Rasterizer::new().unwrap();
+    let alphas = |rasterizer: &mut Rasterizer| {
+      let (_, _, raw) = rasterizer.rasterize_to_raw_rgba(&svg, 1.0).unwrap();
+      raw.chunks(4).map(|pixel| pixel[3]).collect::<Vec<_>>()
+    };
+
+    assert_eq!(rasterizer.quality(), Quality::default());
+    assert!(alphas(&mut rasterizer).iter().any(|&alpha| alpha > 0 && alpha < 255));
+
+    rasterizer.set_quality(Quality::aliased());
+    let aliased = alphas(&mut rasterizer);
+    assert!(aliased.iter().all(|&alpha| alpha == 0 || alpha == 255));
+    assert_eq!(aliased[6 * 12 + 6], 255);
+
+    // Subsample weights that don't divide 255 evenly still fully cover the middle
+    rasterizer.set_quality(Quality { subsamples: 16, ..Quality::high() });
+    assert_eq!(alphas(&mut rasterizer)[6 * 12 + 6], 255);
+
+    rasterizer.set_quality(Quality { subsamples: 1000, ..Quality::fast() });
+    assert_eq!(rasterizer.quality().subsamples, 32);
+    assert_eq!(alphas(&mut rasterizer)[6 * 12 + 6], 255);
+  }
+
+  const WIDE: &str = r#"
+    <svg width="4" height="2">
+      <rect width="4" height="2" fill="red" />
+    </svg>
+  "#;
+
+  fn opaque_columns_and_rows(raw: &[u8], width: usize) -> (usize, usize) {
+    let height = raw.len() / 4 / width;
+    let opaque = |x: usize, y: usize| raw[(y * width + x) * 4 + 3] == 255;
+    let columns = (0..width).filter(|&x| (0..height).any(|y| opaque(x, y))).count();
+    let rows = (0..height).filter(|&y| (0..width).any(|x| opaque(x, y))).count();
+    (columns, rows)
+  }
+
+  #[test]
+  fn can_fit_to_size() {
+    let svg = SvgImage::parse_str(WIDE, Units::Pixel, 96.0).unwrap();
+
+    let rasterize = |fit| {
+      let options = RasterizeOptions::fit(&svg, 8, 8, fit);
+      let (width, height, raw) = svg.rasterize_to_raw_with_options(&options).unwrap();
+      assert_eq!((width, height), (8, 8));
+      raw
+    };
+
+    // Padded above and below
+    let contain = rasterize(Fit::Contain);
+    assert_eq!(opaque_columns_and_rows(&contain, 8), (8, 4));
+    assert_eq!(contain[3], 0);
+    assert_eq!(&contain[(2 * 8) * 4..][..4], [255, 0, 0, 255]);
+
+    assert_eq!(opaque_columns_and_rows(&rasterize(Fit::Width), 8), (8, 4));
+    assert_eq!(opaque_columns_and_rows(&rasterize(Fit::Cover), 8), (8, 8));
+    assert_eq!(opaque_columns_and_rows(&rasterize(Fit::Height), 8), (8, 8));
+    assert_eq!(opaque_columns_and_rows(&rasterize(Fit::Stretch), 8), (8, 8));
+  }
+
+  #[test]
+  fn fit_centers_the_document() {
+    let svg = SvgImage::parse_str(WIDE, Units::Pixel, 96.0).unwrap();
+
+    let options = RasterizeOptions::fit(&svg, 8, 8, Fit::Contain);
+    assert_eq!((options.scale, options.scale_y), (2.0, Some(2.0)));
+    assert_eq!(options.offset, (0.0, 2.0));
+
+    let options = RasterizeOptions::fit(&svg, 8, 8, Fit::Cover);
+    assert_eq!((options.scale, options.scale_y), (4.0, Some(4.0)));
+    assert_eq!(options.offset, (-4.0, 0.0));
+
+    let options = RasterizeOptions::fit(&svg, 8, 8, Fit::Stretch);
+    assert_eq!((options.scale, options.scale_y), (2.0, Some(4.0)));
+    assert_eq!(options.offset, (0.0, 0.0));
+  }
+
+  #[test]
+  #[cfg(feature = "image")]
+  fn can_rasterize_to_an_exact_size() {
+    let svg = SvgImage::parse_file(Path::new("examples/example.svg"), Units::Pixel, 96.0).unwrap();
+
+    assert_eq!(svg.rasterize_to_size(48, 48, Fit::Contain).unwrap().dimensions(), (48, 48));
+    assert_eq!(svg.rasterize_to_size(33, 17, Fit::Cover).unwrap().dimensions(), (33, 17));
+  }
+
   #[test]
   #[cfg(feature = "image")]
   fn can_rasterize_and_scale() {
@@ -0,0 +1,279 @@
+use std::ffi::CStr;
+use std::marker::PhantomData;
+
+use bindings;
+
+/**
+ * An 8-bit per channel color with non-premultiplied alpha.
+ */
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Rgba {
+  pub r: u8,
+  pub g: u8,
+  pub b: u8,
+  pub a: u8,
+}
+
+impl Rgba {
+  pub fn new(r: u8, g: u8, b: u8, a: u8) -> Rgba {
+    Rgba { r, g, b, a }
+  }
+
+  /**
+   * Decodes a NanoSVG packed color, which stores red in the lowest byte and
+   * alpha in the highest.
+   */
+  pub(crate) fn from_packed(color: u32) -> Rgba {
+    Rgba {
+      r: color as u8,
+      g: (color >> 8) as u8,
+      b: (color >> 16) as u8,
+      a: (color >> 24) as u8,
+    }
+  }
+
+  /**
+   * Encodes the color the way NanoSVG packs it, with red in the lowest byte
+   * and alpha in the highest.
+   */
+  pub fn to_packed(&self) -> u32 {
+    self.r as u32 | (self.g as u32) << 8 | (self.b as u32) << 16 | (self.a as u32) << 24
+  }
+}
+
+/**
+ * What happens to a gradient outside of its first and last stop.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Spread {
+  Pad,
+  Reflect,
+  Repeat,
+}
+
+impl Spread {
+  fn from_raw(raw: std::os::raw::c_char) -> Spread {
+    match raw as u32 {
+      x if x == bindings::NSVGspreadType::NSVG_SPREAD_REFLECT as u32 => Spread::Reflect,
+      x if x == bindings::NSVGspreadType::NSVG_SPREAD_REPEAT as u32 => Spread::Repeat,
+      _ => Spread::Pad,
+    }
+  }
+}
+
+/**
+ * How a shape is filled or stroked.
+ */
+#[derive(Debug, Copy, Clone)]
+pub enum Paint<'a> {
+  None,
+  Color(Rgba),
+  LinearGradient(Gradient<'a>),
+  RadialGradient(Gradient<'a>),
+  /**
+   * The bitmap of an `<image>` element, filling its viewport.
+   */
+  Image(Bitmap<'a>),
+}
+
+impl<'a> Paint<'a> {
+  pub(crate) fn new(paint: &'a bindings::NSVGpaint) -> Paint<'a> {
+    let paint_type = paint.type_ as u32;
+
+    if paint_type == bindings::NSVGpaintType::NSVG_PAINT_COLOR as u32 {
+      Paint::Color(Rgba::from_packed(unsafe { *paint.__bindgen_anon_1.color.as_ref() }))
+    } else if paint_type == bindings::NSVGpaintType::NSVG_PAINT_LINEAR_GRADIENT as u32 {
+      Gradient::new(paint).map_or(Paint::None, Paint::LinearGradient)
+    } else if paint_type == bindings::NSVGpaintType::NSVG_PAINT_RADIAL_GRADIENT as u32 {
+      Gradient::new(paint).map_or(Paint::None, Paint::RadialGradient)
+    } else if paint_type == bindings::NSVGpaintType::NSVG_PAINT_IMAGE as u32 {
+      Bitmap::new(paint).map_or(Paint::None, Paint::Image)
+    } else {
+      Paint::None
+    }
+  }
+}
+
+/**
+ * A read-only view of a linear or radial gradient.
+ */
+#[derive(Copy, Clone)]
+pub struct Gradient<'a> {
+  // Not a reference, which would only cover the first of the stops
+  gradient: *const bindings::NSVGgradient,
+  marker: PhantomData<&'a bindings::NSVGgradient>,
+}
+
+impl<'a> Gradient<'a> {
+  fn new(paint: &'a bindings::NSVGpaint) -> Option<Gradient<'a>> {
+    let gradient = unsafe { *paint.__bindgen_anon_1.gradient.as_ref() };
+
+    if gradient.is_null() {
+      None
+    } else {
+      Some(Gradient { gradient, marker: PhantomData })
+    }
+  }
+
+  /**
+   * The 2x3 affine matrix `[a, b, c, d, e, f]` mapping image coordinates into
+   * gradient space.
+   *
+   * For a linear gradient the `y` coordinate in gradient space is the position
+   * along the gradient, from 0.0 at the first stop to 1.0 at the last. For a
+   * radial gradient the distance from the origin is used instead.
+   */
+  pub fn xform(&self) -> [f32; 6] {
+    unsafe { (*self.gradient).xform }
+  }
+
+  pub fn spread(&self) -> Spread {
+    Spread::from_raw(unsafe { (*self.gradient).spread })
+  }
+
+  /**
+   * The focal point x coordinate of a radial gradient, relative to its radius.
+   */
+  pub fn fx(&self) -> f32 {
+    unsafe { (*self.gradient).fx }
+  }
+
+  /**
+   * The focal point y coordinate of a radial gradient, relative to its radius.
+   */
+  pub fn fy(&self) -> f32 {
+    unsafe { (*self.gradient).fy }
+  }
+
+  /**
+   * The color stops of the gradient, ordered by offset.
+   */
+  pub fn stops(&self) -> &'a [GradientStop] {
+    unsafe {
+      let count = (*self.gradient).nstops.max(0) as usize;
+      let stops = std::ptr::addr_of!((*self.gradient).stops) as *const GradientStop;
+      std::slice::from_raw_parts(stops, count)
+    }
+  }
+}
+
+impl<'a> std::fmt::Debug for Gradient<'a> {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    f.debug_struct("Gradient")
+      .field("xform", &self.xform())
+      .field("spread", &self.spread())
+      .field("fx", &self.fx())
+      .field("fy", &self.fy())
+      .field("stops", &self.stops())
+      .finish()
+  }
+}
+
+/**
+ * A single color stop of a `Gradient`.
+ */
+#[repr(transparent)]
+#[derive(Copy, Clone)]
+pub struct GradientStop {
+  stop: bindings::NSVGgradientStop,
+}
+
+impl GradientStop {
+  pub fn color(&self) -> Rgba {
+    Rgba::from_packed(self.stop.color)
+  }
+
+  /**
+   * Position of the stop along the gradient, between 0.0 and 1.0.
+   */
+  pub fn offset(&self) -> f32 {
+    self.stop.offset
+  }
+}
+
+impl std::fmt::Debug for GradientStop {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    f.debug_struct("GradientStop")
+      .field("color", &self.color())
+      .field("offset", &self.offset())
+      .finish()
+  }
+}
+
+/**
+ * A read-only view of the bitmap drawn by an `<image>` element.
+ */
+#[derive(Copy, Clone)]
+pub struct Bitmap<'a> {
+  bitmap: &'a bindings::NSVGbitmap,
+}
+
+impl<'a> Bitmap<'a> {
+  fn new(paint: &'a bindings::NSVGpaint) -> Option<Bitmap<'a>> {
+    let bitmap = unsafe { *paint.__bindgen_anon_1.bitmap.as_ref() };
+
+    if bitmap.is_null() {
+      None
+    } else {
+      Some(Bitmap { bitmap: unsafe { &*bitmap } })
+    }
+  }
+
+  /**
+   * Where the bitmap comes from, such as a `data:` URI or a relative path.
+   */
+  pub fn href(&self) -> &'a str {
+    if self.bitmap.href.is_null() {
+      ""
+    } else {
+      unsafe { CStr::from_ptr(self.bitmap.href) }.to_str().unwrap_or("")
+    }
+  }
+
+  /**
+   * The area `[x, y, width, height]` the bitmap is fitted into, in the user
+   * space of the `<image>` element.
+   */
+  pub fn viewport(&self) -> [f32; 4] {
+    self.bitmap.viewport
+  }
+
+  /**
+   * The 2x3 affine matrix `[a, b, c, d, e, f]` mapping the user space of the
+   * `<image>` element into image coordinates.
+   */
+  pub fn placement(&self) -> [f32; 6] {
+    self.bitmap.placement
+  }
+
+  /**
+   * The 2x3 affine matrix `[a, b, c, d, e, f]` mapping image coordinates into
+   * bitmap pixels, once the bitmap is loaded.
+   */
+  pub fn xform(&self) -> [f32; 6] {
+    self.bitmap.xform
+  }
+
+  /**
+   * The width and height of the bitmap in pixels, or `None` if it hasn't been
+   * loaded. Bitmaps which aren't loaded are not drawn.
+   */
+  pub fn size(&self) -> Option<(u32, u32)> {
+    if self.bitmap.pixels.is_null() {
+      None
+    } else {
+      Some((self.bitmap.width as u32, self.bitmap.height as u32))
+    }
+  }
+}
+
+impl<'a> std::fmt::Debug for Bitmap<'a> {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    f.debug_struct("Bitmap")
+      .field("href", &self.href())
+      .field("viewport", &self.viewport())
+      .field("placement", &self.placement())
+      .field("size", &self.size())
+      .finish()
+  }
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::os::raw::c_char;
+
+use bindings;
+use paint::Rgba;
+use {Error, Units};
+
+/**
+ * Controls how an SVG document is parsed.
+ *
+ * Use struct update syntax to change only what you need:
+ *
+ * ```
+ * # extern crate nsvg;
+ * # fn main() {
+ * // Theme an icon which is drawn with currentColor and var(--accent)
+ * let options = nsvg::ParseOptions {
+ *   current_color: nsvg::Rgba::new(255, 255, 255, 255),
+ *   variables: vec![("accent".to_string(), "#ff8000".to_string())].into_iter().collect(),
+ *   ..Default::default()
+ * };
+ * # }
+ * ```
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseOptions {
+  /**
+   * The length unit identifier, you probably just want `Units::Pixel`.
+   */
+  pub units: Units,
+  /**
+   * Dots per inch used to convert physical units, probably just want `96.0`.
+   */
+  pub dpi: f32,
+  /**
+   * The color `currentColor` stands for, unless the document sets its own with
+   * a `color` attribute. Defaults to black. Alpha is ignored, use opacity
+   * attributes for transparency.
+   */
+  pub current_color: Rgba,
+  /**
+   * Values substituted for `var(--name)`, keyed by name with or without the
+   * leading `--`. Attributes using an unknown variable without a fallback are
+   * left unset.
+   */
+  pub variables: HashMap<String, String>,
+}
+
+impl Default for ParseOptions {
+  fn default() -> ParseOptions {
+    ParseOptions {
+      units: Units::Pixel,
+      dpi: 96.0,
+      current_color: Rgba::new(0, 0, 0, 255),
+      variables: HashMap::new(),
+    }
+  }
+}
+
+/**
+ * The variables of `ParseOptions` as C strings, which must outlive parsing.
+ */
+pub(crate) struct RawVariables {
+  strings: Vec<CString>,
+  names: Vec<*const c_char>,
+  values: Vec<*const c_char>,
+}
+
+impl RawVariables {
+  pub(crate) fn new(variables: &HashMap<String, String>) -> Result<RawVariables, Error> {
+    let mut strings = Vec::with_capacity(variables.len() * 2);
+
+    for (name, value) in variables {
+      strings.push(CString::new(name.as_str())?);
+      strings.push(CString::new(value.as_str())?);
+    }
+
+    // Moving a CString doesn't move its contents, so the pointers stay valid
+    let names = strings.iter().step_by(2).map(|name| name.as_ptr()).collect();
+    let values = strings.iter().skip(1).step_by(2).map(|value| value.as_ptr()).collect();
+
+    Ok(RawVariables { strings, names, values })
+  }
+
+  /**
+   * Fills in the variables of `options`, borrowing from `self`.
+   */
+  pub(crate) fn apply(&mut self, options: &mut bindings::NSVGparseOptions) {
+    options.variableCount = self.names.len() as std::os::raw::c_int;
+    options.variableNames = self.names.as_mut_ptr();
+    options.variableValues = self.values.as_mut_ptr();
+  }
+}
@@ -0,0 +1,132 @@
+use paint::Rgba;
+
+/**
+ * How transparency is stored in rasterized pixels.
+ */
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AlphaMode {
+  /**
+   * Color channels are independent of alpha, as expected by most image formats.
+   * The color of transparent pixels is bled in from their neighbours, to
+   * avoid dark fringes when the image is filtered.
+   */
+  #[default]
+  Straight,
+  /**
+   * Color channels are already multiplied by alpha, as expected by most GPU
+   * blending and compositing libraries. Transparent pixels are all zero.
+   */
+  Premultiplied,
+}
+
+/**
+ * The layout of each pixel in rasterized output.
+ */
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PixelFormat {
+  /**
+   * Red, green, blue and alpha, 8 bits each.
+   */
+  #[default]
+  Rgba8,
+  /**
+   * Blue, green, red and alpha, 8 bits each, as used by Cairo and many
+   * window system surfaces on little-endian machines.
+   */
+  Bgra8,
+  /**
+   * Red, green and blue, 8 bits each, composited over the background, or
+   * black if there is none.
+   */
+  Rgb8,
+  /**
+   * 8 bits of alpha only, such as for coverage masks and glyph atlases.
+   */
+  A8,
+  /**
+   * 8 bits of luminance, composited over the background, or black if there
+   * is none.
+   */
+  L8,
+}
+
+impl PixelFormat {
+  /**
+   * The number of bytes used by a single pixel.
+   */
+  pub fn bytes_per_pixel(&self) -> usize {
+    match *self {
+      PixelFormat::Rgba8 | PixelFormat::Bgra8 => 4,
+      PixelFormat::Rgb8 => 3,
+      PixelFormat::A8 | PixelFormat::L8 => 1,
+    }
+  }
+
+  /**
+   * Whether this format keeps the alpha channel alongside color, so the
+   * `AlphaMode` matters.
+   */
+  pub(crate) fn has_color_and_alpha(&self) -> bool {
+    matches!(*self, PixelFormat::Rgba8 | PixelFormat::Bgra8)
+  }
+}
+
+/**
+ * Converts a row of RGBA pixels into `format`. Formats without alpha expect
+ * `src` to be premultiplied.
+ */
+pub(crate) fn convert_row(src: &[u8], dst: &mut [u8], format: PixelFormat) {
+  let bytes = format.bytes_per_pixel();
+
+  for (from, to) in src.chunks_exact(4).zip(dst.chunks_exact_mut(bytes)) {
+    match format {
+      PixelFormat::Rgba8 => to.copy_from_slice(from),
+      PixelFormat::Bgra8 => to.copy_from_slice(&[from[2], from[1], from[0], from[3]]),
+      PixelFormat::Rgb8 => to.copy_from_slice(&from[..3]),
+      PixelFormat::A8 => to[0] = from[3],
+      PixelFormat::L8 => to[0] = luminance(from[0], from[1], from[2]),
+    }
+  }
+}
+
+/**
+ * Composites a row of premultiplied RGBA pixels over `background` in place,
+ * leaving them with the given alpha mode.
+ */
+pub(crate) fn composite_row(row: &mut [u8], background: Rgba, alpha: AlphaMode) {
+  let back = [
+    mul(background.r, background.a),
+    mul(background.g, background.a),
+    mul(background.b, background.a),
+    background.a,
+  ];
+
+  for pixel in row.chunks_exact_mut(4) {
+    let under = 255 - pixel[3];
+
+    for (channel, &back) in pixel.iter_mut().zip(back.iter()) {
+      *channel = channel.saturating_add(mul(back, under));
+    }
+
+    let a = pixel[3] as u32;
+    if alpha == AlphaMode::Straight && a != 0 && a != 255 {
+      for channel in &mut pixel[..3] {
+        *channel = ((*channel as u32 * 255 + a / 2) / a).min(255) as u8;
+      }
+    }
+  }
+}
+
+/**
+ * Multiplies two 0-255 values as if they were 0.0-1.0.
+ */
+fn mul(a: u8, b: u8) -> u8 {
+  ((a as u32 * b as u32 + 127) / 255) as u8
+}
+
+/**
+ * Rec. 709 luma of an sRGB color.
+ */
+fn luminance(r: u8, g: u8, b: u8) -> u8 {
+  ((54 * r as u32 + 183 * g as u32 + 19 * b as u32 + 128) >> 8) as u8
+}
@@ -0,0 +1,612 @@
+#[cfg(feature = "image")]
+use image;
+
+use bindings;
+use paint::Rgba;
+use pixel::{self, AlphaMode, PixelFormat};
+use shape::Shape;
+use {Error, SvgImage};
+
+/**
+ * NanoSVG always rasterizes to RGBA.
+ */
+const RGBA_BYTES_PER_PIXEL: usize = 4;
+
+/**
+ * How an SVG is scaled to fit an exact output size.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Fit {
+  /**
+   * Scale uniformly so the whole document fits, centered and padded with transparency.
+   */
+  Contain,
+  /**
+   * Scale uniformly so the document covers the whole output, centered and cropped.
+   */
+  Cover,
+  /**
+   * Scale each axis independently so the document exactly fills the output.
+   */
+  Stretch,
+  /**
+   * Scale uniformly to match the output width, centered vertically.
+   */
+  Width,
+  /**
+   * Scale uniformly to match the output height, centered horizontally.
+   */
+  Height,
+}
+
+/**
+ * Controls which part of an SVG is rasterized, and how large the output is.
+ *
+ * Use struct update syntax to change only what you need:
+ *
+ * ```
+ * # extern crate nsvg;
+ * # fn main() {
+ * // Zoom into the top-left quarter of a 256x256 document at 8x
+ * let options = nsvg::RasterizeOptions {
+ *   scale: 8.0,
+ *   width: Some(128 * 8),
+ *   height: Some(128 * 8),
+ *   ..Default::default()
+ * };
+ * # }
+ * ```
+ */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RasterizeOptions {
+  /**
+   * The factor the vector will be scaled by when rasterizing. 1.0 is the original size.
+   */
+  pub scale: f32,
+  /**
+   * Vertical scale factor, when it should differ from `scale`. Defaults to `scale`.
+   */
+  pub scale_y: Option<f32>,
+  /**
+   * Translation in output pixels, applied after scaling. Use `(-x * scale, -y * scale)`
+   * to start the output at the document coordinate `(x, y)`.
+   */
+  pub offset: (f32, f32),
+  /**
+   * Width of the output in pixels. Defaults to the document width times `scale`.
+   */
+  pub width: Option<u32>,
+  /**
+   * Height of the output in pixels. Defaults to the document height times `scale`.
+   */
+  pub height: Option<u32>,
+  /**
+   * Whether the output has straight or premultiplied alpha. Defaults to straight.
+   * Only applies to pixel formats with both color and alpha.
+   */
+  pub alpha: AlphaMode,
+  /**
+   * The layout of each pixel in the output. Defaults to RGBA.
+   */
+  pub format: PixelFormat,
+  /**
+   * A solid color to composite the output over. Defaults to none, leaving
+   * empty areas transparent.
+   */
+  pub background: Option<Rgba>,
+}
+
+impl RasterizeOptions {
+  /**
+   * Options that rasterize the whole document at the given scale.
+   */
+  pub fn with_scale(scale: f32) -> RasterizeOptions {
+    RasterizeOptions { scale, ..Default::default() }
+  }
+
+  /**
+   * Options that scale and center `image` to produce an output of exactly
+   * `width` by `height` pixels.
+   */
+  pub fn fit(image: &SvgImage, width: u32, height: u32, fit: Fit) -> RasterizeOptions {
+    let scale_x = if image.width() > 0.0 { width as f32 / image.width() } else { 0.0 };
+    let scale_y = if image.height() > 0.0 { height as f32 / image.height() } else { 0.0 };
+
+    let (scale_x, scale_y) = match fit {
+      Fit::Contain => (scale_x.min(scale_y), scale_x.min(scale_y)),
+      Fit::Cover => (scale_x.max(scale_y), scale_x.max(scale_y)),
+      Fit::Stretch => (scale_x, scale_y),
+      Fit::Width => (scale_x, scale_x),
+      Fit::Height => (scale_y, scale_y),
+    };
+
+    RasterizeOptions {
+      scale: scale_x,
+      scale_y: Some(scale_y),
+      offset: (
+        (width as f32 - image.width() * scale_x) * 0.5,
+        (height as f32 - image.height() * scale_y) * 0.5,
+      ),
+      width: Some(width),
+      height: Some(height),
+      ..Default::default()
+    }
+  }
+
+  /**
+   * The width and height in pixels that rasterizing `image` with these options produces.
+   */
+  pub fn output_size(&self, image: &SvgImage) -> (u32, u32) {
+    (
+      self.width.unwrap_or((image.width() * self.scale) as u32),
+      self.height.unwrap_or((image.height() * self.scale_y()) as u32),
+    )
+  }
+
+  fn scale_y(&self) -> f32 {
+    self.scale_y.unwrap_or(self.scale)
+  }
+}
+
+impl Default for RasterizeOptions {
+  fn default() -> RasterizeOptions {
+    RasterizeOptions {
+      scale: 1.0,
+      scale_y: None,
+      offset: (0.0, 0.0),
+      width: None,
+      height: None,
+      alpha: AlphaMode::Straight,
+      format: PixelFormat::Rgba8,
+      background: None,
+    }
+  }
+}
+
+/**
+ * Trades rasterization speed for smoothness.
+ *
+ * ```
+ * # extern crate nsvg;
+ * # fn main() {
+ * let mut rasterizer = nsvg::Rasterizer::new().unwrap();
+ *
+ * // Crisper edges for tiny icons
+ * rasterizer.set_quality(nsvg::Quality { subsamples: 15, ..nsvg::Quality::high() });
+ * # }
+ * ```
+ */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Quality {
+  /**
+   * Number of vertical samples taken per pixel, between 1 and 32. More
+   * samples give smoother nearly horizontal edges, but take longer.
+   */
+  pub subsamples: u32,
+  /**
+   * How closely curves are approximated by line segments. Smaller values
+   * give smoother curves with more segments.
+   */
+  pub curve_tolerance: f32,
+  /**
+   * Points of a flattened path closer together than this are merged.
+   */
+  pub distance_tolerance: f32,
+  /**
+   * Whether edges are anti-aliased. When false, each pixel is either fully
+   * covered or empty, as wanted for pixel art and hit masks.
+   */
+  pub antialias: bool,
+}
+
+impl Quality {
+  /**
+   * Quicker, coarser rendering, for large images where individual pixels
+   * hardly matter.
+   */
+  pub fn fast() -> Quality {
+    Quality { subsamples: 3, curve_tolerance: 1.0, distance_tolerance: 0.1, antialias: true }
+  }
+
+  /**
+   * Smoother rendering, for small images where every pixel matters.
+   */
+  pub fn high() -> Quality {
+    Quality { subsamples: 15, curve_tolerance: 0.05, distance_tolerance: 0.01, antialias: true }
+  }
+
+  /**
+   * Rendering without anti-aliasing, so every pixel is fully inside or
+   * outside a shape.
+   */
+  pub fn aliased() -> Quality {
+    Quality { subsamples: 1, antialias: false, ..Quality::default() }
+  }
+}
+
+impl Default for Quality {
+  /**
+   * NanoSVG's usual quality.
+   */
+  fn default() -> Quality {
+    Quality { subsamples: 5, curve_tolerance: 0.25, distance_tolerance: 0.01, antialias: true }
+  }
+}
+
+/**
+ * Turns parsed SVG images into bitmaps.
+ *
+ * Creating a rasterizer allocates working memory which grows to fit the
+ * largest image it has drawn. When rasterizing many images, create one
+ * `Rasterizer` up front and reuse it to avoid reallocating that memory
+ * every time.
+ *
+ * A `Rasterizer` can be moved to another thread, but can only be used by
+ * one thread at a time.
+ */
+pub struct Rasterizer {
+  rasterizer: *mut bindings::NSVGrasterizer,
+  // RGBA pixels waiting to be converted to another pixel format
+  scratch: Vec<u8>,
+  quality: Quality,
+  // One byte per shape of the image being drawn, zero to skip it
+  shape_mask: Option<Vec<u8>>,
+}
+
+// The rasterizer context holds only its own heap allocations, and all access
+// to it goes through `&mut self`.
+unsafe impl Send for Rasterizer {}
+
+impl Rasterizer {
+  /**
+   * Creates a new rasterizer context.
+   */
+  pub fn new() -> Result<Rasterizer, Error> {
+    let rasterizer = unsafe { bindings::nsvgCreateRasterizer() };
+
+    if rasterizer.is_null() {
+      Err(Error::MallocError)
+    } else {
+      Ok(Rasterizer { rasterizer, scratch: Vec::new(), quality: Quality::default(), shape_mask: None })
+    }
+  }
+
+  /**
+   * The quality images are rasterized at.
+   */
+  pub fn quality(&self) -> Quality {
+    self.quality
+  }
+
+  /**
+   * Changes the quality of every image rasterized from now on. Subsamples
+   * are clamped between 1 and 32.
+   */
+  pub fn set_quality(&mut self, quality: Quality) {
+    let quality = Quality { subsamples: quality.subsamples.clamp(1, 32), ..quality };
+
+    unsafe {
+      bindings::nsvgSetQuality(
+        self.rasterizer,
+        quality.subsamples as std::os::raw::c_int,
+        quality.curve_tolerance,
+        quality.distance_tolerance,
+        !quality.antialias as std::os::raw::c_int,
+      );
+    }
+
+    self.quality = quality;
+  }
+
+  /**
+   * Turns the given SVG into an RgbaImage bitmap
+   *
+   * # Arguments
+   * - `image` - The SVG to rasterize
+   * - `scale` - The factor the vector will be scaled by when rasterizing.
+   *   1.0 is the original size.
+   */
+  #[cfg(feature = "image")]
+  pub fn rasterize(&mut self, image: &SvgImage, scale: f32) -> Result<image::RgbaImage, Error> {
+    self.rasterize_with_options(image, &RasterizeOptions::with_scale(scale))
+  }
+
+  /**
+   * Turns the given SVG into an RgbaImage bitmap, with control over the area
+   * rendered and the size of the output. The pixel format in `options` is
+   * ignored, the output is always RGBA.
+   *
+   * # Arguments
+   * - `image` - The SVG to rasterize
+   * - `options` - The scale, offset and output size to use
+   */
+  #[cfg(feature = "image")]
+  pub fn rasterize_with_options(&mut self, image: &SvgImage, options: &RasterizeOptions) -> Result<image::RgbaImage, Error> {
+    let options = RasterizeOptions { format: PixelFormat::Rgba8, ..*options };
+    let (width, height, raw) = self.rasterize_to_raw_with_options(image, &options)?;
+
+    image::RgbaImage::from_raw(width, height, raw)
+      .ok_or(Error::RasterizeError)
+  }
+
+  /**
+   * Turns the given SVG into an RgbaImage bitmap of exactly `width` by `height`
+   * pixels, scaled and centered according to `fit`.
+   *
+   * # Arguments
+   * - `image` - The SVG to rasterize
+   * - `width` - Width of the output in pixels
+   * - `height` - Height of the output in pixels
+   * - `fit` - How the document is scaled to fit the output
+   */
+  #[cfg(feature = "image")]
+  pub fn rasterize_to_size(&mut self, image: &SvgImage, width: u32, height: u32, fit: Fit) -> Result<image::RgbaImage, Error> {
+    self.rasterize_with_options(image, &RasterizeOptions::fit(image, width, height, fit))
+  }
+
+  /**
+   * Turns the given SVG into raw RGBA array data, along with width and height information.
+   *
+   * # Arguments
+   * - `image` - The SVG to rasterize
+   * - `scale` - The factor the vector will be scaled by when rasterizing.
+   *   1.0 is the original size.
+   */
+  pub fn rasterize_to_raw_rgba(&mut self, image: &SvgImage, scale: f32) -> Result<(u32, u32, Vec<u8>), Error> {
+    self.rasterize_to_raw_with_options(image, &RasterizeOptions::with_scale(scale))
+  }
+
+  /**
+   * Turns the given SVG into raw array data, along with width and height
+   * information, with control over the area rendered, the size of the output
+   * and its pixel format.
+   *
+   * # Arguments
+   * - `image` - The SVG to rasterize
+   * - `options` - The scale, offset, output size, pixel format and background to use
+   */
+  pub fn rasterize_to_raw_with_options(&mut self, image: &SvgImage, options: &RasterizeOptions) -> Result<(u32, u32, Vec<u8>), Error> {
+    let (width, height) = options.output_size(image);
+    let stride = width as usize * options.format.bytes_per_pixel();
+    let mut dst = vec![0; raw_buffer_size(width, height, stride)?];
+
+    self.rasterize_into_with_options(image, &mut dst, stride, options)?;
+
+    Ok((width, height, dst))
+  }
+
+  /**
+   * Draws the given SVG into an existing RGBA buffer, such as a mapped texture
+   * or a region of a larger framebuffer.
+   *
+   * Only the first `width * 4` bytes of each row are written, so the rest of
+   * each row is left untouched. Returns `Error::BufferSizeError` instead of
+   * drawing if `stride` is shorter than a row, or `dst` can't hold `height` rows.
+   *
+   * # Arguments
+   * - `image` - The SVG to rasterize
+   * - `dst` - Destination RGBA pixels, 4 bytes per pixel
+   * - `width` - Width in pixels of the area to draw
+   * - `height` - Height in pixels of the area to draw
+   * - `stride` - Number of bytes from the start of one row to the start of the next
+   * - `scale` - The factor the vector will be scaled by when rasterizing.
+   *   1.0 is the original size.
+   */
+  pub fn rasterize_into(&mut self, image: &SvgImage, dst: &mut [u8], width: u32, height: u32, stride: usize, scale: f32) -> Result<(), Error> {
+    let options = RasterizeOptions {
+      scale,
+      width: Some(width),
+      height: Some(height),
+      ..Default::default()
+    };
+
+    self.rasterize_into_with_options(image, dst, stride, &options)
+      .map(|_| ())
+  }
+
+  /**
+   * Draws the given SVG into an existing buffer, with control over the area
+   * rendered, the size of the output and its pixel format. Returns the width
+   * and height of the area drawn.
+   *
+   * Works the same as `rasterize_into`, but takes the dimensions from `options`.
+   *
+   * # Arguments
+   * - `image` - The SVG to rasterize
+   * - `dst` - Destination pixels, in the pixel format given by `options`
+   * - `stride` - Number of bytes from the start of one row to the start of the next
+   * - `options` - The scale, offset, output size, pixel format and background to use
+   */
+  pub fn rasterize_into_with_options(&mut self, image: &SvgImage, dst: &mut [u8], stride: usize, options: &RasterizeOptions) -> Result<(u32, u32), Error> {
+    let (width, height) = options.output_size(image);
+    let format = options.format;
+
+    check_buffer_size(dst.len(), width, height, stride, format.bytes_per_pixel())?;
+
+    if width == 0 || height == 0 {
+      return Ok((width, height));
+    }
+
+    // Formats without alpha are composited from premultiplied color
+    let alpha = if format.has_color_and_alpha() { options.alpha } else { AlphaMode::Premultiplied };
+    let row_bytes = width as usize * format.bytes_per_pixel();
+
+    if format == PixelFormat::Rgba8 {
+      match options.background {
+        Some(background) => {
+          self.rasterize_rgba(image, dst, stride, options, AlphaMode::Premultiplied);
+          for y in 0..height as usize {
+            pixel::composite_row(&mut dst[y * stride..][..row_bytes], background, alpha);
+          }
+        },
+        None => self.rasterize_rgba(image, dst, stride, options, alpha),
+      }
+      return Ok((width, height));
+    }
+
+    let scratch_stride = width as usize * RGBA_BYTES_PER_PIXEL;
+    let mut scratch = std::mem::take(&mut self.scratch);
+    scratch.resize(scratch_stride * height as usize, 0);
+
+    match options.background {
+      Some(background) => {
+        self.rasterize_rgba(image, &mut scratch, scratch_stride, options, AlphaMode::Premultiplied);
+        for (y, src) in scratch.chunks_exact_mut(scratch_stride).enumerate() {
+          pixel::composite_row(src, background, alpha);
+          pixel::convert_row(src, &mut dst[y * stride..][..row_bytes], format);
+        }
+      },
+      None => {
+        self.rasterize_rgba(image, &mut scratch, scratch_stride, options, alpha);
+        for (y, src) in scratch.chunks_exact(scratch_stride).enumerate() {
+          pixel::convert_row(src, &mut dst[y * stride..][..row_bytes], format);
+        }
+      },
+    }
+
+    self.scratch = scratch;
+
+    Ok((width, height))
+  }
+
+  /**
+   * Turns only the shapes of the given SVG that `filter` accepts into an
+   * RgbaImage bitmap. Shapes which are already hidden stay hidden.
+   *
+   * # Arguments
+   * - `image` - The SVG to rasterize
+   * - `options` - The scale, offset, output size and background to use
+   * - `filter` - Called with each shape, returning whether it should be drawn
+   */
+  #[cfg(feature = "image")]
+  pub fn rasterize_shapes_with_options<F>(&mut self, image: &SvgImage, options: &RasterizeOptions, filter: F) -> Result<image::RgbaImage, Error>
+    where F: FnMut(&Shape) -> bool
+  {
+    self.with_shape_mask(image, filter, |rasterizer| rasterizer.rasterize_with_options(image, options))
+  }
+
+  /**
+   * Turns only the shapes of the given SVG that `filter` accepts into raw
+   * array data, along with width and height information.
+   *
+   * # Arguments
+   * - `image` - The SVG to rasterize
+   * - `options` - The scale, offset, output size, pixel format and background to use
+   * - `filter` - Called with each shape, returning whether it should be drawn
+   */
+  pub fn rasterize_shapes_to_raw_with_options<F>(&mut self, image: &SvgImage, options: &RasterizeOptions, filter: F) -> Result<(u32, u32, Vec<u8>), Error>
+    where F: FnMut(&Shape) -> bool
+  {
+    self.with_shape_mask(image, filter, |rasterizer| rasterizer.rasterize_to_raw_with_options(image, options))
+  }
+
+  /**
+   * Draws only the shapes of the given SVG that `filter` accepts into an
+   * existing buffer. Returns the width and height of the area drawn.
+   *
+   * # Arguments
+   * - `image` - The SVG to rasterize
+   * - `dst` - Destination pixels, in the pixel format given by `options`
+   * - `stride` - Number of bytes from the start of one row to the start of the next
+   * - `options` - The scale, offset, output size, pixel format and background to use
+   * - `filter` - Called with each shape, returning whether it should be drawn
+   */
+  pub fn rasterize_shapes_into_with_options<F>(&mut self, image: &SvgImage, dst: &mut [u8], stride: usize, options: &RasterizeOptions, filter: F) -> Result<(u32, u32), Error>
+    where F: FnMut(&Shape) -> bool
+  {
+    self.with_shape_mask(image, filter, |rasterizer| rasterizer.rasterize_into_with_options(image, dst, stride, options))
+  }
+
+  /**
+   * Runs `rasterize` drawing only the shapes of `image` that `filter` accepts.
+   */
+  fn with_shape_mask<F, R, T>(&mut self, image: &SvgImage, mut filter: F, rasterize: R) -> Result<T, Error>
+    where F: FnMut(&Shape) -> bool, R: FnOnce(&mut Rasterizer) -> Result<T, Error>
+  {
+    self.shape_mask = Some(image.shapes().map(|shape| filter(&shape) as u8).collect());
+    let result = rasterize(self);
+    self.shape_mask = None;
+    result
+  }
+
+  /**
+   * Draws RGBA pixels into a buffer already checked to fit the output size.
+   */
+  fn rasterize_rgba(&mut self, image: &SvgImage, dst: &mut [u8], stride: usize, options: &RasterizeOptions, alpha: AlphaMode) {
+    let (width, height) = options.output_size(image);
+    let (tx, ty) = options.offset;
+
+    let (mask, count) = match self.shape_mask {
+      Some(ref mask) => (mask.as_ptr(), mask.len() as std::os::raw::c_int),
+      None => (std::ptr::null(), 0),
+    };
+
+    unsafe {
+      bindings::nsvgSetPremultiplied(self.rasterizer, (alpha == AlphaMode::Premultiplied) as std::os::raw::c_int);
+      bindings::nsvgSetShapeMask(self.rasterizer, mask, count);
+      bindings::nsvgRasterizeXY(    // Rasterizes SVG image, returns RGBA image
+        self.rasterizer,  //   rasterizer - pointer to rasterizer context
+        image.image,      //   image - pointer to image to rasterize
+        tx, ty,           //   tx,ty - image offset (applied after scaling)
+        options.scale,    //   sx - horizontal image scale
+        options.scale_y(),//   sy - vertical image scale
+        dst.as_mut_ptr(), //   dst - pointer to destination image data, 4 bytes per pixel (RGBA)
+        width as i32,     //   w - width of the image to render
+        height as i32,    //   h - height of the image to render
+        stride as i32     //   stride - number of bytes per scaleline in the destination buffer
+      );
+      bindings::nsvgSetShapeMask(self.rasterizer, std::ptr::null(), 0);
+    }
+  }
+}
+
+/**
+ * Makes sure `height` rows of `width` pixels, `stride` bytes apart, fit in a
+ * buffer of `len` bytes, and that the dimensions can be passed to NanoSVG.
+ */
+/**
+ * The size of a tightly packed buffer of `height` rows, or
+ * `Error::BufferSizeError` if it's too large for NanoSVG or to allocate.
+ */
+fn raw_buffer_size(width: u32, height: u32, stride: usize) -> Result<usize, Error> {
+  let max = std::os::raw::c_int::MAX as usize;
+
+  if width as usize > max || height as usize > max {
+    return Err(Error::BufferSizeError);
+  }
+
+  stride.checked_mul(height as usize)
+    .filter(|&size| size <= isize::MAX as usize)
+    .ok_or(Error::BufferSizeError)
+}
+
+fn check_buffer_size(len: usize, width: u32, height: u32, stride: usize, bytes_per_pixel: usize) -> Result<(), Error> {
+  let max = std::os::raw::c_int::MAX as usize;
+  let row = width as usize * bytes_per_pixel;
+
+  if width as usize > max || height as usize > max || stride > max || stride < row {
+    return Err(Error::BufferSizeError);
+  }
+
+  if height == 0 {
+    return Ok(());
+  }
+
+  // The last row doesn't need to be padded out to the full stride
+  let required = stride.checked_mul(height as usize - 1)
+    .and_then(|size| size.checked_add(row));
+
+  match required {
+    Some(required) if required <= len => Ok(()),
+    _ => Err(Error::BufferSizeError),
+  }
+}
+
+impl Drop for Rasterizer {
+  fn drop(&mut self) {
+    if !self.rasterizer.is_null() {
+      unsafe { bindings::nsvgDeleteRasterizer(self.rasterizer) };
+      self.rasterizer = std::ptr::null_mut();
+    }
+  }
+}
@@ -0,0 +1,431 @@
+use std::ffi::CStr;
+use std::marker::PhantomData;
+
+use bindings;
+use paint::{Paint, Rgba};
+
+/**
+ * How the corners of a stroked shape are drawn.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum LineJoin {
+  Miter,
+  Round,
+  Bevel,
+}
+
+impl LineJoin {
+  fn from_raw(raw: std::os::raw::c_char) -> LineJoin {
+    match raw as u32 {
+      x if x == bindings::NSVGlineJoin::NSVG_JOIN_ROUND as u32 => LineJoin::Round,
+      x if x == bindings::NSVGlineJoin::NSVG_JOIN_BEVEL as u32 => LineJoin::Bevel,
+      _ => LineJoin::Miter,
+    }
+  }
+}
+
+/**
+ * How the ends of an open stroked path are drawn.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum LineCap {
+  Butt,
+  Round,
+  Square,
+}
+
+impl LineCap {
+  fn from_raw(raw: std::os::raw::c_char) -> LineCap {
+    match raw as u32 {
+      x if x == bindings::NSVGlineCap::NSVG_CAP_ROUND as u32 => LineCap::Round,
+      x if x == bindings::NSVGlineCap::NSVG_CAP_SQUARE as u32 => LineCap::Square,
+      _ => LineCap::Butt,
+    }
+  }
+}
+
+/**
+ * The rule used to decide which parts of a shape are inside when filling.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FillRule {
+  NonZero,
+  EvenOdd,
+}
+
+impl FillRule {
+  fn from_raw(raw: std::os::raw::c_char) -> FillRule {
+    match raw as u32 {
+      x if x == bindings::NSVGfillRule::NSVG_FILLRULE_EVENODD as u32 => FillRule::EvenOdd,
+      _ => FillRule::NonZero,
+    }
+  }
+}
+
+/**
+ * A read-only view of a single shape in a parsed `SvgImage`.
+ *
+ * All coordinates are in the units the image was parsed with, and already have
+ * the viewBox and any transforms applied.
+ */
+#[derive(Copy, Clone)]
+pub struct Shape<'a> {
+  shape: &'a bindings::NSVGshape,
+}
+
+impl<'a> Shape<'a> {
+  pub(crate) fn new(shape: &'a bindings::NSVGshape) -> Shape<'a> {
+    Shape { shape }
+  }
+
+  /**
+   * The `id` attribute of the shape, or of the group it belongs to.
+   * Empty if neither had one.
+   */
+  pub fn id(&self) -> &'a str {
+    let id = unsafe { CStr::from_ptr(self.shape.id.as_ptr()) };
+    id.to_str().unwrap_or("")
+  }
+
+  /**
+   * The `id`s of the groups containing the shape, outermost first. Groups
+   * without an `id` are left out.
+   */
+  pub fn groups(&self) -> impl Iterator<Item = &'a str> {
+    let groups = if self.shape.groups.is_null() {
+      ""
+    } else {
+      unsafe { CStr::from_ptr(self.shape.groups) }.to_str().unwrap_or("")
+    };
+
+    groups.split(' ').filter(|id| !id.is_empty())
+  }
+
+  /**
+   * Whether `id` is the shape's own `id`, or that of any group containing it.
+   */
+  pub fn has_id(&self, id: &str) -> bool {
+    self.id() == id || self.groups().any(|group| group == id)
+  }
+
+  /**
+   * How the inside of the shape is painted.
+   */
+  pub fn fill(&self) -> Paint<'a> {
+    Paint::new(&self.shape.fill)
+  }
+
+  /**
+   * How the outline of the shape is painted.
+   */
+  pub fn stroke(&self) -> Paint<'a> {
+    Paint::new(&self.shape.stroke)
+  }
+
+  /**
+   * Opacity the shape is drawn with, between 0.0 and 1.0, including the
+   * opacity of its groups. This is `own_opacity() * group_opacity()`.
+   */
+  pub fn opacity(&self) -> f32 {
+    self.own_opacity() * self.group_opacity()
+  }
+
+  /**
+   * Opacity set on the shape itself, between 0.0 and 1.0, without that of
+   * its groups.
+   */
+  pub fn own_opacity(&self) -> f32 {
+    self.shape.opacity
+  }
+
+  /**
+   * Combined opacity of the translucent groups containing the shape. Each of
+   * these groups is drawn on its own layer which is then composited as a
+   * whole, so overlapping shapes inside it don't show through each other.
+   */
+  pub fn group_opacity(&self) -> f32 {
+    let mut opacity = 1.0;
+    let mut layer = self.shape.layer;
+    while let Some(current) = unsafe { layer.as_ref() } {
+      opacity *= current.opacity;
+      layer = current.parent;
+    }
+    opacity
+  }
+
+  /**
+   * Width of the stroke, already scaled.
+   */
+  pub fn stroke_width(&self) -> f32 {
+    self.shape.strokeWidth
+  }
+
+  /**
+   * Offset into the dash pattern at which the stroke starts, already scaled.
+   */
+  pub fn stroke_dash_offset(&self) -> f32 {
+    self.shape.strokeDashOffset
+  }
+
+  /**
+   * Lengths of alternating dashes and gaps, already scaled. Empty for a solid stroke.
+   */
+  pub fn stroke_dash_array(&self) -> &'a [f32] {
+    let count = (self.shape.strokeDashCount as usize).min(self.shape.strokeDashArray.len());
+    &self.shape.strokeDashArray[..count]
+  }
+
+  pub fn stroke_line_join(&self) -> LineJoin {
+    LineJoin::from_raw(self.shape.strokeLineJoin)
+  }
+
+  pub fn stroke_line_cap(&self) -> LineCap {
+    LineCap::from_raw(self.shape.strokeLineCap)
+  }
+
+  pub fn miter_limit(&self) -> f32 {
+    self.shape.miterLimit
+  }
+
+  pub fn fill_rule(&self) -> FillRule {
+    FillRule::from_raw(self.shape.fillRule)
+  }
+
+  /**
+   * Whether the shape will be drawn when rasterizing. Shapes inside a
+   * `display="none"` subtree are parsed but not visible.
+   */
+  pub fn is_visible(&self) -> bool {
+    self.shape.flags & bindings::NSVGflags::NSVG_FLAGS_VISIBLE as u8 != 0
+  }
+
+  /**
+   * Tight bounding box of the shape as `[min_x, min_y, max_x, max_y]`.
+   */
+  pub fn bounds(&self) -> [f32; 4] {
+    self.shape.bounds
+  }
+
+  /**
+   * Iterates over the paths that make up this shape.
+   */
+  pub fn paths(&self) -> Paths<'a> {
+    Paths { path: self.shape.paths, marker: PhantomData }
+  }
+}
+
+/**
+ * A shape in a parsed `SvgImage` which can be restyled or hidden, so the
+ * image can be rasterized again without parsing it again.
+ */
+pub struct ShapeMut<'a> {
+  shape: &'a mut bindings::NSVGshape,
+}
+
+impl<'a> ShapeMut<'a> {
+  pub(crate) fn new(shape: &'a mut bindings::NSVGshape) -> ShapeMut<'a> {
+    ShapeMut { shape }
+  }
+
+  /**
+   * A read-only view of the shape as it currently is.
+   */
+  pub fn shape(&self) -> Shape<'_> {
+    Shape::new(self.shape)
+  }
+
+  /**
+   * Shows or hides the shape when rasterizing.
+   */
+  pub fn set_visible(&mut self, visible: bool) {
+    let flag = bindings::NSVGflags::NSVG_FLAGS_VISIBLE as u8;
+
+    if visible {
+      self.shape.flags |= flag;
+    } else {
+      self.shape.flags &= !flag;
+    }
+  }
+
+  /**
+   * Fills the inside of the shape with a solid color, replacing any
+   * gradient. Shapes parsed with `fill="none"` start being filled.
+   */
+  pub fn set_fill_color(&mut self, color: Rgba) {
+    unsafe { bindings::nsvgSetPaintColor(&mut self.shape.fill, color.to_packed()) };
+  }
+
+  /**
+   * Strokes the outline of the shape with a solid color, replacing any
+   * gradient. Shapes without a stroke also need a stroke width to show one.
+   */
+  pub fn set_stroke_color(&mut self, color: Rgba) {
+    unsafe { bindings::nsvgSetPaintColor(&mut self.shape.stroke, color.to_packed()) };
+  }
+
+  /**
+   * Sets the opacity of the shape itself, clamped between 0.0 and 1.0. Its
+   * groups still apply their own opacity on top, see `Shape::own_opacity()`.
+   */
+  pub fn set_opacity(&mut self, opacity: f32) {
+    self.shape.opacity = opacity.clamp(0.0, 1.0);
+  }
+
+  /**
+   * Sets the width of the stroke, in the units the image was parsed with.
+   */
+  pub fn set_stroke_width(&mut self, width: f32) {
+    self.shape.strokeWidth = width.max(0.0);
+  }
+}
+
+/**
+ * Iterator over the shapes of an `SvgImage`, in drawing order, which can be
+ * changed.
+ */
+pub struct ShapesMut<'a> {
+  shape: *mut bindings::NSVGshape,
+  marker: PhantomData<&'a mut bindings::NSVGshape>,
+}
+
+impl<'a> ShapesMut<'a> {
+  pub(crate) fn new(shape: *mut bindings::NSVGshape) -> ShapesMut<'a> {
+    ShapesMut { shape, marker: PhantomData }
+  }
+}
+
+impl<'a> Iterator for ShapesMut<'a> {
+  type Item = ShapeMut<'a>;
+
+  fn next(&mut self) -> Option<ShapeMut<'a>> {
+    if self.shape.is_null() {
+      None
+    } else {
+      // Each shape is only handed out once, so the borrows never overlap
+      let shape = unsafe { &mut *self.shape };
+      self.shape = shape.next;
+      Some(ShapeMut::new(shape))
+    }
+  }
+}
+
+/**
+ * Iterator over the shapes of an `SvgImage`, in drawing order.
+ */
+pub struct Shapes<'a> {
+  shape: *mut bindings::NSVGshape,
+  marker: PhantomData<&'a bindings::NSVGshape>,
+}
+
+impl<'a> Shapes<'a> {
+  pub(crate) fn new(shape: *mut bindings::NSVGshape) -> Shapes<'a> {
+    Shapes { shape, marker: PhantomData }
+  }
+}
+
+impl<'a> Iterator for Shapes<'a> {
+  type Item = Shape<'a>;
+
+  fn next(&mut self) -> Option<Shape<'a>> {
+    if self.shape.is_null() {
+      None
+    } else {
+      let shape = unsafe { &*self.shape };
+      self.shape = shape.next;
+      Some(Shape::new(shape))
+    }
+  }
+}
+
+/**
+ * A read-only view of a single path in a `Shape`.
+ *
+ * A path is a sequence of cubic bezier curves. The first point is the start
+ * point, and every following group of three points is two control points and
+ * an end point.
+ */
+#[derive(Copy, Clone)]
+pub struct ShapePath<'a> {
+  path: &'a bindings::NSVGpath,
+}
+
+impl<'a> ShapePath<'a> {
+  /**
+   * All points of the path as `[x, y]` pairs:
+   * `start, [control1, control2, end], ...`
+   */
+  pub fn points(&self) -> &'a [[f32; 2]] {
+    if self.path.pts.is_null() || self.path.npts <= 0 {
+      &[]
+    } else {
+      unsafe {
+        std::slice::from_raw_parts(self.path.pts as *const [f32; 2], self.path.npts as usize)
+      }
+    }
+  }
+
+  /**
+   * Iterates over the cubic bezier curves of the path as
+   * `[start, control1, control2, end]`.
+   */
+  pub fn curves(&self) -> Curves<'a> {
+    Curves { points: self.points() }
+  }
+
+  /**
+   * Whether the path should be treated as closed.
+   */
+  pub fn is_closed(&self) -> bool {
+    self.path.closed != 0
+  }
+
+  /**
+   * Tight bounding box of the path as `[min_x, min_y, max_x, max_y]`.
+   */
+  pub fn bounds(&self) -> [f32; 4] {
+    self.path.bounds
+  }
+}
+
+/**
+ * Iterator over the paths of a `Shape`.
+ */
+pub struct Paths<'a> {
+  path: *mut bindings::NSVGpath,
+  marker: PhantomData<&'a bindings::NSVGpath>,
+}
+
+impl<'a> Iterator for Paths<'a> {
+  type Item = ShapePath<'a>;
+
+  fn next(&mut self) -> Option<ShapePath<'a>> {
+    if self.path.is_null() {
+      None
+    } else {
+      let path = unsafe { &*self.path };
+      self.path = path.next;
+      Some(ShapePath { path })
+    }
+  }
+}
+
+/**
+ * Iterator over the cubic bezier curves of a `ShapePath`.
+ */
+pub struct Curves<'a> {
+  points: &'a [[f32; 2]],
+}
+
+impl<'a> Iterator for Curves<'a> {
+  type Item = [[f32; 2]; 4];
+
+  fn next(&mut self) -> Option<[[f32; 2]; 4]> {
+    if self.points.len() < 4 {
+      None
+    } else {
+      let p = self.points;
+      self.points = &p[3..];
+      Some([p[0], p[1], p[2], p[3]])
+    }
+  }
+}
@@ -0,0 +1,392 @@
+use std::borrow::Cow;
+use std::ffi::CStr;
+use std::marker::PhantomData;
+
+use bindings;
+use shape::Shape;
+
+/**
+ * Where a line of text is aligned to its position.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TextAnchor {
+  Start,
+  Middle,
+  End,
+}
+
+impl TextAnchor {
+  fn from_raw(raw: std::os::raw::c_char) -> TextAnchor {
+    match raw as u32 {
+      x if x == bindings::NSVGtextAnchor::NSVG_ANCHOR_MIDDLE as u32 => TextAnchor::Middle,
+      x if x == bindings::NSVGtextAnchor::NSVG_ANCHOR_END as u32 => TextAnchor::End,
+      _ => TextAnchor::Start,
+    }
+  }
+}
+
+/**
+ * A read-only view of a run of text in a parsed `SvgImage`, the characters of
+ * a `<text>` or `<tspan>` element up to the next nested element.
+ *
+ * Positions and sizes are in the user space of the run, see `xform()`. Each
+ * run is drawn by its own `Shape`, which has no paths until the text is
+ * outlined with `SvgImage::outline_texts`.
+ */
+#[derive(Copy, Clone)]
+pub struct Text<'a> {
+  text: &'a bindings::NSVGtext,
+}
+
+impl<'a> Text<'a> {
+  pub(crate) fn new(text: &'a bindings::NSVGtext) -> Text<'a> {
+    Text { text }
+  }
+
+  /**
+   * The characters of the run, with white space collapsed as in a browser.
+   * Bytes which aren't valid UTF-8 are replaced with `U+FFFD`.
+   */
+  pub fn content(&self) -> Cow<'a, str> {
+    unsafe { CStr::from_ptr(self.text.content) }.to_string_lossy()
+  }
+
+  /**
+   * Where the run starts horizontally, if it starts a new line of text rather
+   * than continuing where the previous run ended.
+   */
+  pub fn x(&self) -> Option<f32> {
+    self.has_flag(bindings::NSVGtextFlags::NSVG_TEXT_X).then_some(self.text.x)
+  }
+
+  /**
+   * The baseline of the run, if it was given rather than continuing that of
+   * the previous run.
+   */
+  pub fn y(&self) -> Option<f32> {
+    self.has_flag(bindings::NSVGtextFlags::NSVG_TEXT_Y).then_some(self.text.y)
+  }
+
+  /**
+   * How far the run is shifted from where it would start, from `dx` and `dy`.
+   */
+  pub fn offset(&self) -> (f32, f32) {
+    (self.text.dx, self.text.dy)
+  }
+
+  /**
+   * The `font-family` of the run as written, such as `"'Open Sans', serif"`.
+   * Empty if none was set, and like `content()`, invalid UTF-8 is replaced.
+   */
+  pub fn font_family(&self) -> Cow<'a, str> {
+    unsafe { CStr::from_ptr(self.text.fontFamily.as_ptr()) }.to_string_lossy()
+  }
+
+  /**
+   * The font families of the run in order of preference, without quotes.
+   */
+  pub fn font_families(&self) -> impl Iterator<Item = String> {
+    self.font_family()
+      .split(',')
+      .map(|family| family.trim().trim_matches(|c| c == '"' || c == '\''))
+      .filter(|family| !family.is_empty())
+      .map(String::from)
+      .collect::<Vec<_>>()
+      .into_iter()
+  }
+
+  /**
+   * The font size of the run, 16 unless set.
+   */
+  pub fn font_size(&self) -> f32 {
+    self.text.fontSize
+  }
+
+  /**
+   * How the line of text the run is part of is aligned to its position.
+   */
+  pub fn anchor(&self) -> TextAnchor {
+    TextAnchor::from_raw(self.text.anchor)
+  }
+
+  /**
+   * The transform from the user space of the run to that of the image, as
+   * `[a, b, c, d, e, f]`.
+   */
+  pub fn xform(&self) -> [f32; 6] {
+    self.text.xform
+  }
+
+  /**
+   * The shape drawing the run.
+   */
+  pub fn shape(&self) -> Shape<'a> {
+    Shape::new(unsafe { &*self.text.shape })
+  }
+
+  fn has_flag(&self, flag: bindings::NSVGtextFlags) -> bool {
+    self.text.flags as u32 & flag as u32 != 0
+  }
+}
+
+impl<'a> std::fmt::Debug for Text<'a> {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    f.debug_struct("Text")
+      .field("content", &self.content())
+      .field("x", &self.x())
+      .field("y", &self.y())
+      .field("offset", &self.offset())
+      .field("font_family", &self.font_family())
+      .field("font_size", &self.font_size())
+      .field("anchor", &self.anchor())
+      .finish()
+  }
+}
+
+/**
+ * An iterator over the runs of text of an `SvgImage`, in document order.
+ */
+pub struct Texts<'a> {
+  text: *mut bindings::NSVGtext,
+  marker: PhantomData<&'a bindings::NSVGtext>,
+}
+
+impl<'a> Texts<'a> {
+  pub(crate) fn new(text: *mut bindings::NSVGtext) -> Texts<'a> {
+    Texts { text, marker: PhantomData }
+  }
+}
+
+impl<'a> Iterator for Texts<'a> {
+  type Item = Text<'a>;
+
+  fn next(&mut self) -> Option<Text<'a>> {
+    if self.text.is_null() {
+      None
+    } else {
+      let text = unsafe { &*self.text };
+      self.text = text.next;
+      Some(Text::new(text))
+    }
+  }
+}
+
+/**
+ * The outlines of the glyphs of a run of text, built by a `FontProvider`.
+ *
+ * Coordinates are in the user space of the run, with the start of its
+ * baseline at the origin and y pointing down, so glyphs are mostly above it.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outline {
+  advance: f32,
+  pts: Vec<f32>,
+  npts: Vec<i32>,
+  closed: Vec<std::os::raw::c_char>,
+  start: Option<usize>,
+}
+
+impl Outline {
+  /**
+   * An empty outline, followed by the next run `advance` further along the
+   * baseline.
+   */
+  pub fn new(advance: f32) -> Outline {
+    Outline { advance, pts: Vec::new(), npts: Vec::new(), closed: Vec::new(), start: None }
+  }
+
+  /**
+   * How far along the baseline the next run starts.
+   */
+  pub fn advance(&self) -> f32 {
+    self.advance
+  }
+
+  /**
+   * Starts a new path at `(x, y)`.
+   */
+  pub fn move_to(&mut self, x: f32, y: f32) {
+    self.finish(false);
+    self.start = Some(self.pts.len());
+    self.pts.extend_from_slice(&[x, y]);
+  }
+
+  /**
+   * Adds a straight line to `(x, y)`.
+   */
+  pub fn line_to(&mut self, x: f32, y: f32) {
+    let (x0, y0) = self.current();
+    self.cubic_to(x0 + (x - x0) / 3.0, y0 + (y - y0) / 3.0, x - (x - x0) / 3.0, y - (y - y0) / 3.0, x, y);
+  }
+
+  /**
+   * Adds a quadratic bezier curve through the control point `(cx, cy)` to
+   * `(x, y)`, as used by TrueType fonts.
+   */
+  pub fn quad_to(&mut self, cx: f32, cy: f32, x: f32, y: f32) {
+    let (x0, y0) = self.current();
+    self.cubic_to(x0 + (cx - x0) * 2.0 / 3.0, y0 + (cy - y0) * 2.0 / 3.0, x + (cx - x) * 2.0 / 3.0, y + (cy - y) * 2.0 / 3.0, x, y);
+  }
+
+  /**
+   * Adds a cubic bezier curve through the control points `(c1x, c1y)` and
+   * `(c2x, c2y)` to `(x, y)`.
+   */
+  pub fn cubic_to(&mut self, c1x: f32, c1y: f32, c2x: f32, c2y: f32, x: f32, y: f32) {
+    if self.start.is_none() {
+      let (x0, y0) = self.current();
+      self.move_to(x0, y0);
+    }
+    self.pts.extend_from_slice(&[c1x, c1y, c2x, c2y, x, y]);
+  }
+
+  /**
+   * Closes the current path with a straight line back to where it started.
+   */
+  pub fn close(&mut self) {
+    if let Some(start) = self.start {
+      let (x, y) = (self.pts[start], self.pts[start + 1]);
+      if self.current() != (x, y) {
+        self.line_to(x, y);
+      }
+    }
+    self.finish(true);
+  }
+
+  fn current(&self) -> (f32, f32) {
+    match self.pts.len() {
+      0 => (0.0, 0.0),
+      len => (self.pts[len - 2], self.pts[len - 1]),
+    }
+  }
+
+  fn finish(&mut self, closed: bool) {
+    if let Some(start) = self.start.take() {
+      let npts = (self.pts.len() - start) / 2;
+      if npts < 4 {
+        // A lone move_to draws nothing
+        self.pts.truncate(start);
+      } else {
+        self.npts.push(npts as i32);
+        self.closed.push(closed as std::os::raw::c_char);
+      }
+    }
+  }
+}
+
+/**
+ * Turns runs of text into outlines, usually by looking up glyphs in a font.
+ *
+ * nsvg doesn't load fonts itself, so text is only drawn once an `SvgImage`
+ * is outlined with a provider, see `SvgImage::outline_texts`.
+ */
+pub trait FontProvider {
+  /**
+   * The outlines of the `content()` of `run`, in its `font_family()` and at
+   * its `font_size()`. Runs without an outline aren't drawn.
+   */
+  fn outline(&mut self, run: &Text) -> Option<Outline>;
+}
+
+impl<F: FnMut(&Text) -> Option<Outline>> FontProvider for F {
+  fn outline(&mut self, run: &Text) -> Option<Outline> {
+    self(run)
+  }
+}
+
+/**
+ * A stand-in `FontProvider` drawing every character as a box of 0.6 by 0.7
+ * times the font size, for tests and previews without any fonts. White space
+ * is left empty.
+ */
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct BoxFont;
+
+impl FontProvider for BoxFont {
+  fn outline(&mut self, run: &Text) -> Option<Outline> {
+    let size = run.font_size();
+    let mut outline = Outline::new(0.0);
+    for c in run.content().chars() {
+      if !c.is_whitespace() {
+        let x = outline.advance;
+        outline.move_to(x + size * 0.05, -size * 0.7);
+        outline.line_to(x + size * 0.55, -size * 0.7);
+        outline.line_to(x + size * 0.55, 0.0);
+        outline.line_to(x + size * 0.05, 0.0);
+        outline.close();
+      }
+      outline.advance += size * 0.6;
+    }
+    Some(outline)
+  }
+}
+
+/**
+ * Outlines every run of text with `provider`, laying out the runs of each
+ * line one after the other and aligning the line to its anchor. Returns how
+ * many runs were outlined.
+ */
+pub(crate) fn outline_texts<P: FontProvider + ?Sized>(texts: *mut bindings::NSVGtext, provider: &mut P) -> usize {
+  let mut outlined = 0;
+  let mut line = Vec::new();
+  let (mut start, mut anchor) = (0.0, TextAnchor::Start);
+  let (mut x, mut y) = (0.0, 0.0);
+  let mut text = texts;
+
+  while !text.is_null() {
+    let run = Text::new(unsafe { &*text });
+    if let Some(run_x) = run.x() {
+      outlined += place_line(&mut line, anchor, x - start);
+      start = run_x;
+      anchor = run.anchor();
+      x = run_x;
+    }
+    if let Some(run_y) = run.y() {
+      y = run_y;
+    }
+    x += run.offset().0;
+    y += run.offset().1;
+
+    let outline = provider.outline(&run);
+    let advance = outline.as_ref().map_or(0.0, Outline::advance);
+    line.push(PlacedRun { text, outline, x, y });
+    x += advance;
+    text = unsafe { (*text).next };
+  }
+  outlined + place_line(&mut line, anchor, x - start)
+}
+
+struct PlacedRun {
+  text: *mut bindings::NSVGtext,
+  outline: Option<Outline>,
+  x: f32,
+  y: f32,
+}
+
+fn place_line(line: &mut Vec<PlacedRun>, anchor: TextAnchor, width: f32) -> usize {
+  let shift = match anchor {
+    TextAnchor::Start => 0.0,
+    TextAnchor::Middle => -width / 2.0,
+    TextAnchor::End => -width,
+  };
+  let mut outlined = 0;
+
+  for run in line.drain(..) {
+    // Runs without an outline lose any they had before
+    let found = run.outline.is_some();
+    let mut outline = run.outline.unwrap_or_else(|| Outline::new(0.0));
+    outline.finish(false);
+    for pt in outline.pts.chunks_mut(2) {
+      pt[0] += run.x + shift;
+      pt[1] += run.y;
+    }
+
+    let done = unsafe {
+      bindings::nsvgSetTextOutline(run.text, outline.pts.as_ptr(), outline.npts.as_ptr(), outline.closed.as_ptr(), outline.npts.len() as i32)
+    };
+    if done != 0 && found {
+      outlined += 1;
+    }
+  }
+  outlined
+}
@@ -0,0 +1,141 @@
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int, c_void};
+
+use bindings;
+
+/**
+ * Why a part of an SVG document was not understood.
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum WarningReason {
+  /**
+   * The element was never closed, or the document ended in the middle of its tag.
+   */
+  UnclosedTag,
+  /**
+   * An end tag that doesn't match any open element.
+   */
+  MismatchedEndTag,
+  /**
+   * An attribute value that should be a number, but isn't. It's treated as 0.
+   */
+  BadNumber(String),
+  /**
+   * A length with a unit NanoSVG doesn't recognise. It's treated as unitless.
+   */
+  UnknownUnit(String),
+  /**
+   * An element NanoSVG doesn't support, which was skipped. Its children are
+   * still parsed.
+   */
+  UnsupportedElement,
+  /**
+   * A `<use>` element which would make the document expand beyond a sane
+   * size, such as through nested instances. It and any further ones were
+   * skipped.
+   */
+  TooManyInstances,
+  /**
+   * A nested `<svg>` inside more clip paths than NanoSVG can apply at once,
+   * so its contents aren't clipped to its viewport.
+   */
+  TooManyClipPaths,
+  /**
+   * A translucent group nested inside more translucent groups than can be
+   * drawn in layers of their own, so its opacity applies to each of its
+   * shapes, which then show through each other.
+   */
+  TooManyLayers,
+}
+
+/**
+ * A problem found while parsing an SVG document, and where it was found.
+ *
+ * NanoSVG is forgiving, and draws what it can of a malformed document. These
+ * explain what it left out or guessed at.
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParseWarning {
+  pub reason: WarningReason,
+  /**
+   * Name of the offending element.
+   */
+  pub element: String,
+  /**
+   * Byte offset of the start of the offending element's tag.
+   */
+  pub offset: usize,
+  /**
+   * Line of the start of the offending element's tag, starting at 1.
+   */
+  pub line: usize,
+  /**
+   * Column of the start of the offending element's tag in characters, starting at 1.
+   */
+  pub column: usize,
+}
+
+impl std::fmt::Display for ParseWarning {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    write!(f, "line {}, column {}: ", self.line, self.column)?;
+
+    match self.reason {
+      WarningReason::UnclosedTag => write!(f, "<{}> is never closed", self.element),
+      WarningReason::MismatchedEndTag => write!(f, "</{}> doesn't match an open element", self.element),
+      WarningReason::BadNumber(ref value) => write!(f, "\"{}\" in <{}> is not a number", value, self.element),
+      WarningReason::UnknownUnit(ref unit) => write!(f, "unknown unit \"{}\" in <{}>", unit, self.element),
+      WarningReason::UnsupportedElement => write!(f, "unsupported element <{}> was skipped", self.element),
+      WarningReason::TooManyInstances => write!(f, "<{}> instances too many elements, it and later ones were skipped", self.element),
+      WarningReason::TooManyClipPaths => write!(f, "<{}> is inside too many clip paths to be clipped to its viewport", self.element),
+      WarningReason::TooManyLayers => write!(f, "<{}> is inside too many translucent groups to be drawn as one", self.element),
+    }
+  }
+}
+
+impl std::error::Error for ParseWarning {}
+
+/**
+ * Gathers warnings from `nsvgParseWithWarnings`.
+ */
+#[derive(Default)]
+pub(crate) struct WarningCollector {
+  warnings: Vec<ParseWarning>,
+}
+
+impl WarningCollector {
+  /**
+   * The warnings collected so far, in the order they appear in the source.
+   */
+  pub(crate) fn into_warnings(mut self) -> Vec<ParseWarning> {
+    self.warnings.sort_by_key(|warning| warning.offset);
+    self.warnings
+  }
+}
+
+/**
+ * Callback for `nsvgParseWithWarnings`, `ud` must point to a `WarningCollector`.
+ */
+pub(crate) unsafe extern "C" fn collect_warning(ud: *mut c_void, warning: c_int, offset: c_int, line: c_int, column: c_int, element: *const c_char, value: *const c_char) {
+  let collector = &mut *(ud as *mut WarningCollector);
+  let element = CStr::from_ptr(element).to_string_lossy().into_owned();
+  let value = CStr::from_ptr(value).to_string_lossy().into_owned();
+
+  let reason = match warning as u32 {
+    x if x == bindings::NSVGwarning::NSVG_WARNING_UNCLOSED_TAG as u32 => WarningReason::UnclosedTag,
+    x if x == bindings::NSVGwarning::NSVG_WARNING_MISMATCHED_END_TAG as u32 => WarningReason::MismatchedEndTag,
+    x if x == bindings::NSVGwarning::NSVG_WARNING_BAD_NUMBER as u32 => WarningReason::BadNumber(value),
+    x if x == bindings::NSVGwarning::NSVG_WARNING_UNKNOWN_UNIT as u32 => WarningReason::UnknownUnit(value),
+    x if x == bindings::NSVGwarning::NSVG_WARNING_USE_LIMIT as u32 => WarningReason::TooManyInstances,
+    x if x == bindings::NSVGwarning::NSVG_WARNING_CLIP_LIMIT as u32 => WarningReason::TooManyClipPaths,
+    x if x == bindings::NSVGwarning::NSVG_WARNING_LAYER_LIMIT as u32 => WarningReason::TooManyLayers,
+    _ => WarningReason::UnsupportedElement,
+  };
+
+  collector.warnings.push(ParseWarning {
+    reason,
+    element,
+    offset: offset.max(0) as usize,
+    line: line.max(1) as usize,
+    column: column.max(1) as usize,
+  });
+}